@@ -0,0 +1,46 @@
+-- Baseline schema, kept in step with migrations/sqlite.
+
+CREATE TABLE IF NOT EXISTS users (
+    id BIGSERIAL PRIMARY KEY,
+    username TEXT UNIQUE NOT NULL,
+    email TEXT UNIQUE NOT NULL,
+    password_hash TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS news_items (
+    id BIGSERIAL PRIMARY KEY,
+    title TEXT NOT NULL,
+    source TEXT NOT NULL,
+    published_at TIMESTAMPTZ NOT NULL,
+    summary TEXT NOT NULL,
+    url TEXT NOT NULL,
+    sentiment TEXT NOT NULL,
+    api_source TEXT NOT NULL DEFAULT '',
+    first_seen_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    -- Full-text document over titles (weight A) and summaries (weight B)
+    search_vector TSVECTOR GENERATED ALWAYS AS (
+        setweight(to_tsvector('english', title), 'A') ||
+        setweight(to_tsvector('english', summary), 'B')
+    ) STORED
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_news_items_url ON news_items (url);
+CREATE INDEX IF NOT EXISTS idx_news_items_published_at ON news_items (published_at);
+CREATE INDEX IF NOT EXISTS idx_news_items_search ON news_items USING GIN (search_vector);
+
+CREATE TABLE IF NOT EXISTS news_item_queries (
+    news_item_id BIGINT NOT NULL REFERENCES news_items (id) ON DELETE CASCADE,
+    query TEXT NOT NULL,
+    found_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (news_item_id, query)
+);
+
+CREATE TABLE IF NOT EXISTS price_history (
+    coin TEXT NOT NULL,
+    timestamp TIMESTAMPTZ NOT NULL,
+    open DOUBLE PRECISION NOT NULL,
+    high DOUBLE PRECISION NOT NULL,
+    low DOUBLE PRECISION NOT NULL,
+    close DOUBLE PRECISION NOT NULL,
+    PRIMARY KEY (coin, timestamp)
+);
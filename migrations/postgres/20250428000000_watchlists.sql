@@ -0,0 +1,8 @@
+-- Coins each user follows, stored as canonical names ("bitcoin") so they
+-- line up with news_item_queries.query.
+CREATE TABLE watchlists (
+    user_id BIGINT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    coin TEXT NOT NULL,
+    added_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (user_id, coin)
+);
@@ -0,0 +1,20 @@
+-- Articles users saved for later. news_items rows are RESTRICTed so that no
+-- retention or cleanup job can delete an article while it is bookmarked.
+CREATE TABLE bookmarks (
+    user_id BIGINT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    news_item_id BIGINT NOT NULL REFERENCES news_items (id) ON DELETE RESTRICT,
+    note TEXT,
+    created_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (user_id, news_item_id)
+);
+
+CREATE TABLE bookmark_tags (
+    user_id BIGINT NOT NULL,
+    news_item_id BIGINT NOT NULL,
+    tag TEXT NOT NULL,
+    PRIMARY KEY (user_id, news_item_id, tag),
+    FOREIGN KEY (user_id, news_item_id) REFERENCES bookmarks (user_id, news_item_id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_bookmark_tags_tag ON bookmark_tags (user_id, tag);
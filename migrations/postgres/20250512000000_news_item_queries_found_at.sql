@@ -0,0 +1,2 @@
+-- Backs Storage::news_found_since, which replays missed updates to SSE clients
+CREATE INDEX IF NOT EXISTS idx_news_item_queries_found_at ON news_item_queries (found_at);
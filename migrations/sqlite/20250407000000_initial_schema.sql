@@ -0,0 +1,67 @@
+-- Baseline schema. Every statement is idempotent because databases created
+-- before migrations existed already have some of these objects.
+
+CREATE TABLE IF NOT EXISTS users (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    username TEXT UNIQUE NOT NULL,
+    email TEXT UNIQUE NOT NULL,
+    password_hash TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS news_items (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    title TEXT NOT NULL,
+    source TEXT NOT NULL,
+    published_at TIMESTAMP NOT NULL,
+    summary TEXT NOT NULL,
+    url TEXT NOT NULL,
+    sentiment TEXT NOT NULL,
+    api_source TEXT NOT NULL DEFAULT '',
+    first_seen_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_news_items_url ON news_items (url);
+CREATE INDEX IF NOT EXISTS idx_news_items_published_at ON news_items (published_at);
+
+CREATE TABLE IF NOT EXISTS news_item_queries (
+    news_item_id INTEGER NOT NULL REFERENCES news_items (id) ON DELETE CASCADE,
+    query TEXT NOT NULL,
+    found_at TIMESTAMP NOT NULL,
+    PRIMARY KEY (news_item_id, query)
+);
+
+-- Full-text index over titles and summaries. External content, so the text
+-- is only stored once; the triggers keep it in sync.
+CREATE VIRTUAL TABLE IF NOT EXISTS news_items_fts USING fts5(
+    title,
+    summary,
+    content = 'news_items',
+    content_rowid = 'id',
+    tokenize = 'porter unicode61'
+);
+
+CREATE TRIGGER IF NOT EXISTS news_items_fts_insert AFTER INSERT ON news_items BEGIN
+    INSERT INTO news_items_fts (rowid, title, summary) VALUES (new.id, new.title, new.summary);
+END;
+
+CREATE TRIGGER IF NOT EXISTS news_items_fts_delete AFTER DELETE ON news_items BEGIN
+    INSERT INTO news_items_fts (news_items_fts, rowid, title, summary) VALUES ('delete', old.id, old.title, old.summary);
+END;
+
+CREATE TRIGGER IF NOT EXISTS news_items_fts_update AFTER UPDATE ON news_items BEGIN
+    INSERT INTO news_items_fts (news_items_fts, rowid, title, summary) VALUES ('delete', old.id, old.title, old.summary);
+    INSERT INTO news_items_fts (rowid, title, summary) VALUES (new.id, new.title, new.summary);
+END;
+
+-- Index whatever was archived before the index existed
+INSERT INTO news_items_fts (news_items_fts) VALUES ('rebuild');
+
+CREATE TABLE IF NOT EXISTS price_history (
+    coin TEXT NOT NULL,
+    timestamp TIMESTAMP NOT NULL,
+    open REAL NOT NULL,
+    high REAL NOT NULL,
+    low REAL NOT NULL,
+    close REAL NOT NULL,
+    PRIMARY KEY (coin, timestamp)
+);
@@ -0,0 +1,7 @@
+-- Indexes backing Database::list_articles filters
+CREATE INDEX IF NOT EXISTS idx_news_items_source ON news_items (source);
+CREATE INDEX IF NOT EXISTS idx_news_items_api_source ON news_items (api_source);
+CREATE INDEX IF NOT EXISTS idx_news_items_sentiment ON news_items (sentiment);
+CREATE INDEX IF NOT EXISTS idx_news_item_queries_query ON news_item_queries (query);
+
+ALTER TABLE users ADD COLUMN created_at TIMESTAMP;
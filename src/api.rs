@@ -1,11 +1,17 @@
-use reqwest;
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
-use std::env;
-use tracing;
-use serde_json;
+use futures_util::future::join_all;
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Instant;
+use crate::config::Config;
+use crate::feeds::FeedProvider;
+use crate::metrics::Metrics;
+use utoipa::ToSchema;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, ToSchema)]
 pub struct NewsItem {
     pub title: String,
     pub source: String,
@@ -16,72 +22,163 @@ pub struct NewsItem {
     pub api_source: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct NewsDataResponse {
-    pub status: String,
-    pub results: Vec<NewsDataArticle>,
+/// A source of news articles. Each provider maps its own upstream schema
+/// into `NewsItem`s tagged with its `api_source`.
+#[async_trait]
+pub trait NewsProvider: Send + Sync {
+    fn name(&self) -> &str;
+    async fn fetch(&self, query: &str) -> Result<Vec<NewsItem>, String>;
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct NewsDataArticle {
-    pub title: String,
-    pub link: String,
-    pub description: Option<String>,
-    #[serde(rename = "pubDate")]
-    pub pub_date: DateTime<Utc>,
-    #[serde(rename = "source_id")]
-    pub source_id: String,
-}
-
-pub async fn fetch_news(query: &str) -> Result<Vec<NewsItem>, String> {
-    match fetch_newsdata(query).await {
-        Ok(news) => {
-            tracing::info!("Successfully fetched {} news items from NewsData.io", news.len());
-            if news.is_empty() {
-                return Err("No news found for your search query.".to_string());
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProviderFailure {
+    pub provider: String,
+    pub error: String,
+}
+
+/// Merged result of a fan-out over all enabled providers. `failures` lists the
+/// providers that errored while the others still contributed items.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct NewsFetch {
+    pub items: Vec<NewsItem>,
+    pub failures: Vec<ProviderFailure>,
+}
+
+pub type Providers = Arc<Vec<Box<dyn NewsProvider>>>;
+
+pub fn enabled_providers(config: &Config) -> Vec<Box<dyn NewsProvider>> {
+    let mut providers: Vec<Box<dyn NewsProvider>> = Vec::new();
+    if let Some(key) = &config.newsdata_api_key {
+        providers.push(Box::new(NewsDataProvider::new(key.clone())));
+    }
+    if let Some(key) = &config.cryptqnews_api_key {
+        providers.push(Box::new(CryptoNewsProvider::new(key.clone())));
+    }
+    if let Some(key) = &config.cryptopanic_api_key {
+        providers.push(Box::new(match &config.cryptopanic_base_url {
+            Some(base_url) => CryptoPanicProvider::with_base_url(key.clone(), base_url.clone()),
+            None => CryptoPanicProvider::new(key.clone()),
+        }));
+    }
+    if !config.feed_urls.is_empty() {
+        providers.push(Box::new(FeedProvider::new(config.feed_urls.clone())));
+    }
+    providers
+}
+
+pub async fn fetch_news(providers: &[Box<dyn NewsProvider>], metrics: &Metrics, query: &str) -> Result<NewsFetch, String> {
+    if providers.is_empty() {
+        return Err("No news providers are configured. Please check your .env file.".to_string());
+    }
+
+    let results = join_all(providers.iter().map(|provider| async move {
+        let started = Instant::now();
+        let result = provider.fetch(query).await;
+        metrics.observe_provider(provider.name(), started.elapsed(), result.is_ok());
+        (provider.name().to_string(), result)
+    }))
+    .await;
+
+    let mut items: Vec<NewsItem> = Vec::new();
+    let mut failures = Vec::new();
+    for (provider, result) in results {
+        match result {
+            Ok(news) => {
+                tracing::info!("Successfully fetched {} news items from {}", news.len(), provider);
+                items.extend(news);
+            }
+            Err(error) => {
+                tracing::error!("Failed to fetch from {}: {}", provider, error);
+                failures.push(ProviderFailure { provider, error });
             }
-            Ok(news)
-        },
-        Err(e) => {
-            tracing::error!("Failed to fetch from NewsData.io: {}", e);
-            Err(format!("Error fetching news: {}", e))
         }
     }
+
+    // No results is an answer as long as one provider gave it
+    if failures.len() == providers.len() {
+        // The errors are logged above; they can name upstream URLs, so
+        // callers that show this to clients only get the providers
+        let names = failures
+            .iter()
+            .map(|f| f.provider.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        return Err(format!("Error fetching news: no source could be reached ({})", names));
+    }
+
+    // The same story is often syndicated by several sources
+    let mut seen = HashSet::new();
+    items.retain(|item| seen.insert(item.url.clone()));
+
+    // Sort by date (newest first)
+    items.sort_by_key(|item| Reverse(item.published_at));
+
+    Ok(NewsFetch { items, failures })
+}
+
+pub struct NewsDataProvider {
+    client: reqwest::Client,
+    api_key: String,
+    base_url: String,
 }
 
-async fn fetch_newsdata(query: &str) -> Result<Vec<NewsItem>, String> {
-    let api_key = match env::var("NEWSDATA_API_KEY") {
-        Ok(key) => key,
-        Err(_) => {
-            return Err("NEWSDATA_API_KEY environment variable is not set".to_string());
+impl NewsDataProvider {
+    pub fn new(api_key: String) -> Self {
+        Self::with_base_url(api_key, "https://newsdata.io/api/1".to_string())
+    }
+
+    pub fn with_base_url(api_key: String, base_url: String) -> Self {
+        NewsDataProvider {
+            client: reqwest::Client::new(),
+            api_key,
+            base_url,
         }
-    };
-    
-    let normalized_query = normalize_query(query);
-    
-    let url = format!(
-        "https://newsdata.io/api/1/news?apikey={}&q={}&language=en&size=10&category=business,technology",
-        api_key, normalized_query
-    );
-    
-    tracing::info!("Fetching from NewsData.io with query: {}", normalized_query);
-    
-    let response = reqwest::get(&url).await.map_err(|e| e.to_string())?;
-    
-    if !response.status().is_success() {
-        let status = response.status();
-        let text = response.text().await.map_err(|e| e.to_string())?;
-        
-        // Special handling for invalid API key
-        if status == 401 && text.contains("The provided API key is not valid") {
-            return Err("Invalid NewsData.io API key. Please check your .env file.".to_string());
+    }
+}
+
+#[async_trait]
+impl NewsProvider for NewsDataProvider {
+    fn name(&self) -> &str {
+        "NewsData.io"
+    }
+
+    async fn fetch(&self, query: &str) -> Result<Vec<NewsItem>, String> {
+        let normalized_query = normalize_query(query);
+
+        let url = format!("{}/news", self.base_url);
+
+        tracing::info!("Fetching from NewsData.io with query: {}", normalized_query);
+
+        let response = self
+            .client
+            .get(&url)
+            .query(&[
+                ("apikey", self.api_key.as_str()),
+                ("q", normalized_query.as_str()),
+                ("language", "en"),
+                ("size", "10"),
+                ("category", "business,technology"),
+            ])
+            .send()
+            .await
+            // The URL carries the API key
+            .map_err(|e| e.without_url().to_string())?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.map_err(|e| e.without_url().to_string())?;
+
+            // Special handling for invalid API key
+            if status == 401 && text.contains("The provided API key is not valid") {
+                return Err("Invalid NewsData.io API key. Please check your .env file.".to_string());
+            }
+
+            return Err(format!("NewsData.io API returned error status {}: {}", status, text));
         }
-        
-        return Err(format!("NewsData.io API returned error status {}: {}", status, text));
+
+        let text = response.text().await.map_err(|e| e.without_url().to_string())?;
+        parse_newsdata_response(&text)
     }
-    
-    let text = response.text().await.map_err(|e| e.to_string())?;
-    parse_newsdata_response(&text)
 }
 
 fn parse_newsdata_response(text: &str) -> Result<Vec<NewsItem>, String> {
@@ -110,16 +207,7 @@ fn parse_newsdata_response(text: &str) -> Result<Vec<NewsItem>, String> {
                     .and_then(|d| d.as_str())
                     .unwrap_or("");
                 
-                // Try parsing with different date formats and convert to UTC
-                let published_at = if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(pub_date) {
-                    dt.with_timezone(&chrono::Utc)
-                } else if let Ok(dt) = chrono::DateTime::parse_from_rfc2822(pub_date) {
-                    dt.with_timezone(&chrono::Utc)
-                } else if let Ok(dt) = chrono::NaiveDateTime::parse_from_str(pub_date, "%Y-%m-%d %H:%M:%S") {
-                    chrono::DateTime::<chrono::Utc>::from_naive_utc_and_offset(dt, chrono::Utc)
-                } else {
-                    return Err(format!("Failed to parse date: {}", pub_date));
-                };
+                let published_at = parse_published_date(pub_date)?;
                 
                 let sentiment = analyze_sentiment(description);
                 
@@ -137,51 +225,334 @@ fn parse_newsdata_response(text: &str) -> Result<Vec<NewsItem>, String> {
     }
     
     // Sort by date (newest first)
-    news_items.sort_by(|a, b| b.published_at.cmp(&a.published_at));
+    news_items.sort_by_key(|item| Reverse(item.published_at));
     
     tracing::info!("Found {} news items from NewsData.io", news_items.len());
     Ok(news_items)
 }
 
-fn normalize_query(query: &str) -> String {
-    let query = query.trim().to_lowercase();
-    
-    // Map common abbreviations to full names
-    let query = match query.as_str() {
-        "btc" => "bitcoin",
-        "eth" | "ether" => "ethereum",
-        "xrp" => "ripple",
-        "ltc" => "litecoin",
-        "doge" => "dogecoin",
-        "ada" => "cardano",
-        "dot" => "polkadot",
-        "sol" => "solana",
-        "link" => "chainlink",
-        "uni" => "uniswap",
-        _ => {
-            // Try to match by removing any whitespace
-            let normalized = query.replace(" ", "");
-            match normalized.as_str() {
-                "btc" => "bitcoin",
-                "eth" | "ether" => "ethereum",
-                "xrp" => "ripple",
-                "ltc" => "litecoin",
-                "doge" => "dogecoin",
-                "ada" => "cardano",
-                "dot" => "polkadot",
-                "sol" => "solana",
-                "link" => "chainlink",
-                "uni" => "uniswap",
-                _ => &query,
-            }
+pub struct CryptoNewsProvider {
+    client: reqwest::Client,
+    api_key: String,
+    base_url: String,
+}
+
+impl CryptoNewsProvider {
+    pub fn new(api_key: String) -> Self {
+        Self::with_base_url(api_key, "https://cryptonews-api.com/api/v1".to_string())
+    }
+
+    pub fn with_base_url(api_key: String, base_url: String) -> Self {
+        CryptoNewsProvider {
+            client: reqwest::Client::new(),
+            api_key,
+            base_url,
+        }
+    }
+}
+
+#[async_trait]
+impl NewsProvider for CryptoNewsProvider {
+    fn name(&self) -> &str {
+        "CryptoNews API"
+    }
+
+    async fn fetch(&self, query: &str) -> Result<Vec<NewsItem>, String> {
+        // Known coins are queried by ticker, anything else goes through the
+        // general feed with a keyword search
+        let request = match coin_ticker(query) {
+            Some(ticker) => self
+                .client
+                .get(&self.base_url)
+                .query(&[("tickers", ticker.to_uppercase())]),
+            None => self
+                .client
+                .get(format!("{}/category", self.base_url))
+                .query(&[("section", "general"), ("search", &canonical_coin(query))]),
+        };
+
+        tracing::info!("Fetching from CryptoNews API with query: {}", query);
+
+        let response = request
+            .query(&[("items", "10"), ("token", self.api_key.as_str())])
+            .send()
+            .await
+            .map_err(|e| e.without_url().to_string())?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.map_err(|e| e.without_url().to_string())?;
+            return Err(format!("CryptoNews API returned error status {}: {}", status, text));
+        }
+
+        let text = response.text().await.map_err(|e| e.without_url().to_string())?;
+        parse_cryptonews_response(&text)
+    }
+}
+
+fn parse_cryptonews_response(text: &str) -> Result<Vec<NewsItem>, String> {
+    let data: serde_json::Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+
+    let results = match data.get("data").and_then(|d| d.as_array()) {
+        Some(results) => results,
+        None => {
+            let message = data.get("message")
+                .and_then(|m| m.as_str())
+                .unwrap_or("response has no data field");
+            return Err(format!("CryptoNews API error: {}", message));
         }
     };
-    
+
+    let mut news_items = Vec::new();
+
+    for item in results {
+        if let (Some(title), Some(news_url), Some(date), Some(source_name)) = (
+            item.get("title").and_then(|t| t.as_str()),
+            item.get("news_url").and_then(|u| u.as_str()),
+            item.get("date").and_then(|d| d.as_str()),
+            item.get("source_name").and_then(|s| s.as_str()),
+        ) {
+            let text = item.get("text")
+                .and_then(|t| t.as_str())
+                .unwrap_or("");
+
+            // The API ships its own sentiment label; fall back to ours when missing
+            let sentiment = match item.get("sentiment").and_then(|s| s.as_str()) {
+                Some("Positive") => "Positive".to_string(),
+                Some("Negative") => "Negative".to_string(),
+                Some("Neutral") => "Neutral".to_string(),
+                _ => analyze_sentiment(text),
+            };
+
+            news_items.push(NewsItem {
+                title: title.to_string(),
+                url: news_url.to_string(),
+                source: source_name.to_string(),
+                published_at: parse_published_date(date)?,
+                summary: text.to_string(),
+                sentiment,
+                api_source: "CryptoNews API".to_string(),
+            });
+        }
+    }
+
+    // Sort by date (newest first)
+    news_items.sort_by_key(|item| Reverse(item.published_at));
+
+    tracing::info!("Found {} news items from CryptoNews API", news_items.len());
+    Ok(news_items)
+}
+
+/// Client for CryptoPanic's `/posts/` endpoint, or any aggregator that
+/// serves the same schema under a different base URL.
+pub struct CryptoPanicProvider {
+    client: reqwest::Client,
+    api_key: String,
+    base_url: String,
+}
+
+impl CryptoPanicProvider {
+    pub fn new(api_key: String) -> Self {
+        Self::with_base_url(api_key, "https://cryptopanic.com/api/v1".to_string())
+    }
+
+    pub fn with_base_url(api_key: String, base_url: String) -> Self {
+        CryptoPanicProvider {
+            client: reqwest::Client::new(),
+            api_key,
+            base_url,
+        }
+    }
+}
+
+#[async_trait]
+impl NewsProvider for CryptoPanicProvider {
+    fn name(&self) -> &str {
+        "CryptoPanic"
+    }
+
+    async fn fetch(&self, query: &str) -> Result<Vec<NewsItem>, String> {
+        let mut request = self
+            .client
+            .get(format!("{}/posts/", self.base_url))
+            .query(&[
+                ("auth_token", self.api_key.as_str()),
+                ("public", "true"),
+                ("kind", "news"),
+                ("metadata", "true"),
+            ]);
+        if let Some(ticker) = coin_ticker(query) {
+            request = request.query(&[("currencies", ticker.to_uppercase())]);
+        }
+
+        tracing::info!("Fetching from CryptoPanic with query: {}", query);
+
+        let response = request.send().await.map_err(|e| e.without_url().to_string())?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.map_err(|e| e.without_url().to_string())?;
+            return Err(format!("CryptoPanic API returned error status {}: {}", status, text));
+        }
+
+        let text = response.text().await.map_err(|e| e.without_url().to_string())?;
+        let news_items = parse_cryptopanic_response(&text)?;
+
+        // Without a currency filter the endpoint returns the general feed, so
+        // keep only posts that mention the term
+        if coin_ticker(query).is_none() {
+            return Ok(news_items
+                .into_iter()
+                .filter(|item| matches_query(item, query))
+                .collect());
+        }
+
+        Ok(news_items)
+    }
+}
+
+fn parse_cryptopanic_response(text: &str) -> Result<Vec<NewsItem>, String> {
+    let data: serde_json::Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+
+    // Errors come back as {"status": "Incomplete", "info": "..."}
+    if let Some(info) = data.get("info").and_then(|i| i.as_str()) {
+        if data.get("results").is_none() {
+            return Err(format!("CryptoPanic API error: {}", info));
+        }
+    }
+
+    let mut news_items = Vec::new();
+
+    if let Some(results) = data.get("results").and_then(|r| r.as_array()) {
+        for item in results {
+            if let (Some(title), Some(url), Some(published_at)) = (
+                item.get("title").and_then(|t| t.as_str()),
+                item.get("url").and_then(|u| u.as_str()),
+                item.get("published_at").and_then(|d| d.as_str()),
+            ) {
+                let source = item.get("source")
+                    .and_then(|s| s.get("title"))
+                    .and_then(|t| t.as_str())
+                    .or_else(|| item.get("domain").and_then(|d| d.as_str()))
+                    .unwrap_or("CryptoPanic");
+
+                let description = item.get("metadata")
+                    .and_then(|m| m.get("description"))
+                    .and_then(|d| d.as_str())
+                    .unwrap_or("");
+
+                // Community votes are a better signal than keywords when present
+                let votes = |kind: &str| {
+                    item.get("votes")
+                        .and_then(|v| v.get(kind))
+                        .and_then(|n| n.as_i64())
+                        .unwrap_or(0)
+                };
+                let positive = votes("positive") + votes("liked");
+                let negative = votes("negative") + votes("disliked");
+                let sentiment = if positive > negative {
+                    "Positive".to_string()
+                } else if negative > positive {
+                    "Negative".to_string()
+                } else {
+                    analyze_sentiment(&format!("{} {}", title, description))
+                };
+
+                news_items.push(NewsItem {
+                    title: title.to_string(),
+                    url: url.to_string(),
+                    source: source.to_string(),
+                    published_at: parse_published_date(published_at)?,
+                    summary: description.to_string(),
+                    sentiment,
+                    api_source: "CryptoPanic".to_string(),
+                });
+            }
+        }
+    }
+
+    // Sort by date (newest first)
+    news_items.sort_by_key(|item| Reverse(item.published_at));
+
+    tracing::info!("Found {} news items from CryptoPanic", news_items.len());
+    Ok(news_items)
+}
+
+/// Whether an item mentions the query, by canonical name or, for known coins,
+/// by ticker as a whole word ("eth" must not match "ethics").
+pub(crate) fn matches_query(item: &NewsItem, query: &str) -> bool {
+    let term = canonical_coin(query);
+    let text = format!("{} {}", item.title, item.summary).to_lowercase();
+    if text.contains(&term) {
+        return true;
+    }
+    match coin_ticker(query) {
+        Some(ticker) => text
+            .split(|c: char| !c.is_alphanumeric())
+            .any(|word| word == ticker),
+        None => false,
+    }
+}
+
+/// Parses the date formats seen across providers and converts them to UTC.
+pub(crate) fn parse_published_date(value: &str) -> Result<DateTime<Utc>, String> {
+    let value = value.trim();
+    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(value) {
+        Ok(dt.with_timezone(&chrono::Utc))
+    } else if let Ok(dt) = chrono::DateTime::parse_from_rfc2822(value) {
+        Ok(dt.with_timezone(&chrono::Utc))
+    } else if let Ok(dt) = chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S") {
+        Ok(chrono::DateTime::<chrono::Utc>::from_naive_utc_and_offset(dt, chrono::Utc))
+    } else {
+        Err(format!("Failed to parse date: {}", value))
+    }
+}
+
+/// Ticker and canonical name of the coins we recognise by abbreviation.
+const KNOWN_COINS: &[(&str, &str)] = &[
+    ("btc", "bitcoin"),
+    ("eth", "ethereum"),
+    ("xrp", "ripple"),
+    ("ltc", "litecoin"),
+    ("doge", "dogecoin"),
+    ("ada", "cardano"),
+    ("dot", "polkadot"),
+    ("sol", "solana"),
+    ("link", "chainlink"),
+    ("uni", "uniswap"),
+];
+
+/// Maps common abbreviations to full names ("btc" -> "bitcoin"); anything
+/// else is returned trimmed and lowercased.
+pub fn canonical_coin(query: &str) -> String {
+    let query = query.trim().to_lowercase();
+
+    // Try to match by removing any whitespace
+    let compact = query.replace(' ', "");
+    let alias = if compact == "ether" { "eth" } else { compact.as_str() };
+
+    KNOWN_COINS
+        .iter()
+        .find(|(ticker, _)| *ticker == alias)
+        .map(|(_, name)| name.to_string())
+        .unwrap_or(query)
+}
+
+/// Ticker symbol for a known coin, whether queried by symbol or by name.
+pub fn coin_ticker(query: &str) -> Option<&'static str> {
+    let coin = canonical_coin(query);
+    KNOWN_COINS
+        .iter()
+        .find(|(_, name)| *name == coin)
+        .map(|(ticker, _)| *ticker)
+}
+
+fn normalize_query(query: &str) -> String {
     // Add "cryptocurrency" to the query to improve results
-    format!("{} cryptocurrency", query)
+    format!("{} cryptocurrency", canonical_coin(query))
 }
 
-fn analyze_sentiment(text: &str) -> String {
+pub(crate) fn analyze_sentiment(text: &str) -> String {
     // Simple sentiment analysis based on keyword matching
     let text = text.to_lowercase();
     let positive_words = ["bullish", "surge", "gain", "rise", "growth", "positive", "up", "high"];
@@ -199,3 +570,172 @@ fn analyze_sentiment(text: &str) -> String {
     }
 }
 
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    pub(crate) struct StaticProvider {
+        pub name: &'static str,
+        pub result: Result<Vec<NewsItem>, String>,
+    }
+
+    #[async_trait]
+    impl NewsProvider for StaticProvider {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        async fn fetch(&self, _query: &str) -> Result<Vec<NewsItem>, String> {
+            self.result.clone()
+        }
+    }
+
+    fn item(url: &str, hour: u32, api_source: &str) -> NewsItem {
+        NewsItem {
+            title: format!("Story {}", url),
+            source: "test".to_string(),
+            url: url.to_string(),
+            published_at: Utc.with_ymd_and_hms(2025, 4, 7, hour, 0, 0).unwrap(),
+            summary: String::new(),
+            sentiment: "Neutral".to_string(),
+            api_source: api_source.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn fetch_news_merges_and_sorts_newest_first() {
+        let providers: Vec<Box<dyn NewsProvider>> = vec![
+            Box::new(StaticProvider { name: "A", result: Ok(vec![item("a1", 9, "A"), item("a2", 12, "A")]) }),
+            Box::new(StaticProvider { name: "B", result: Ok(vec![item("b1", 10, "B"), item("a1", 9, "B")]) }),
+        ];
+
+        let fetch = fetch_news(&providers, &Metrics::default(), "btc").await.unwrap();
+        let urls: Vec<&str> = fetch.items.iter().map(|i| i.url.as_str()).collect();
+        assert_eq!(urls, vec!["a2", "b1", "a1"]);
+        assert!(fetch.failures.is_empty());
+    }
+
+    #[tokio::test]
+    async fn fetch_news_returns_partial_results_when_a_provider_fails() {
+        let providers: Vec<Box<dyn NewsProvider>> = vec![
+            Box::new(StaticProvider { name: "A", result: Ok(vec![item("a1", 9, "A")]) }),
+            Box::new(StaticProvider { name: "B", result: Err("quota exceeded".to_string()) }),
+        ];
+
+        let fetch = fetch_news(&providers, &Metrics::default(), "btc").await.unwrap();
+        assert_eq!(fetch.items.len(), 1);
+        assert_eq!(fetch.failures.len(), 1);
+        assert_eq!(fetch.failures[0].provider, "B");
+        assert_eq!(fetch.failures[0].error, "quota exceeded");
+    }
+
+    #[tokio::test]
+    async fn fetch_news_succeeds_with_no_items() {
+        let providers: Vec<Box<dyn NewsProvider>> = vec![
+            Box::new(StaticProvider { name: "A", result: Ok(Vec::new()) }),
+            Box::new(StaticProvider { name: "B", result: Err("down".to_string()) }),
+        ];
+
+        let fetch = fetch_news(&providers, &Metrics::default(), "btc").await.unwrap();
+        assert!(fetch.items.is_empty());
+        assert_eq!(fetch.failures.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn fetch_news_errors_when_every_provider_fails() {
+        let providers: Vec<Box<dyn NewsProvider>> = vec![
+            Box::new(StaticProvider { name: "A", result: Err("down".to_string()) }),
+        ];
+
+        let err = fetch_news(&providers, &Metrics::default(), "btc").await.unwrap_err();
+        assert!(err.contains("(A)"), "{}", err);
+        assert!(!err.contains("down"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn request_errors_do_not_leak_api_keys() {
+        // Nothing listens on the discard port
+        let base_url = "http://127.0.0.1:9".to_string();
+        let providers: Vec<Box<dyn NewsProvider>> = vec![
+            Box::new(NewsDataProvider::with_base_url("secret-key".to_string(), base_url.clone())),
+            Box::new(CryptoNewsProvider::with_base_url("secret-key".to_string(), base_url.clone())),
+            Box::new(CryptoPanicProvider::with_base_url("secret-key".to_string(), base_url)),
+        ];
+
+        for provider in &providers {
+            let err = provider.fetch("btc").await.unwrap_err();
+            assert!(!err.contains("secret-key"), "{}: {}", provider.name(), err);
+        }
+    }
+
+    #[test]
+    fn parse_newsdata_response_maps_fixture() {
+        let items = parse_newsdata_response(include_str!("../tests/fixtures/newsdata.json")).unwrap();
+
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].source, "theblock");
+        assert_eq!(items[0].published_at, Utc.with_ymd_and_hms(2025, 4, 7, 9, 30, 0).unwrap());
+        assert_eq!(items[0].sentiment, "Positive");
+        assert_eq!(items[1].url, "https://example.com/bitcoin-holds-steady");
+        assert_eq!(items[1].published_at, Utc.with_ymd_and_hms(2025, 4, 7, 9, 15, 0).unwrap());
+        assert!(items.iter().all(|i| i.api_source == "NewsData.io"));
+    }
+
+    #[test]
+    fn parse_newsdata_response_surfaces_api_errors() {
+        let err = parse_newsdata_response(r#"{"status":"error","message":"rate limit"}"#).unwrap_err();
+        assert_eq!(err, "NewsData.io API error: rate limit");
+    }
+
+    #[test]
+    fn parse_cryptonews_response_maps_fixture() {
+        let items = parse_cryptonews_response(include_str!("../tests/fixtures/cryptonews.json")).unwrap();
+
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].title, "Ethereum developers schedule next network upgrade");
+        assert_eq!(items[0].source, "Decrypt");
+        assert_eq!(items[0].published_at, Utc.with_ymd_and_hms(2025, 4, 7, 13, 15, 0).unwrap());
+        assert_eq!(items[0].sentiment, "Positive");
+        // No sentiment in the payload, so keyword analysis kicks in
+        assert_eq!(items[1].sentiment, "Negative");
+        assert!(items.iter().all(|i| i.api_source == "CryptoNews API"));
+    }
+
+    #[test]
+    fn parse_cryptonews_response_surfaces_api_errors() {
+        let err = parse_cryptonews_response(r#"{"message":"Invalid token"}"#).unwrap_err();
+        assert_eq!(err, "CryptoNews API error: Invalid token");
+    }
+
+    #[test]
+    fn parse_cryptopanic_response_maps_fixture() {
+        let items = parse_cryptopanic_response(include_str!("../tests/fixtures/cryptopanic.json")).unwrap();
+
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].url, "https://cryptopanic.com/news/102/Solana-validators-report-outage");
+        assert_eq!(items[0].source, "decrypt.co");
+        assert_eq!(items[0].sentiment, "Negative");
+        assert_eq!(items[1].source, "CoinTelegraph");
+        assert_eq!(items[1].summary, "Weekly volume on Solana exchanges beat Ethereum for the first time.");
+        assert_eq!(items[1].sentiment, "Positive");
+        assert!(items.iter().all(|i| i.api_source == "CryptoPanic"));
+    }
+
+    #[test]
+    fn parse_cryptopanic_response_surfaces_api_errors() {
+        let err = parse_cryptopanic_response(r#"{"status":"Incomplete","info":"Token not found"}"#).unwrap_err();
+        assert_eq!(err, "CryptoPanic API error: Token not found");
+    }
+
+    #[test]
+    fn canonical_coin_resolves_aliases() {
+        assert_eq!(canonical_coin(" BTC "), "bitcoin");
+        assert_eq!(canonical_coin("ether"), "ethereum");
+        assert_eq!(canonical_coin("D O T"), "polkadot");
+        assert_eq!(canonical_coin("DeFi"), "defi");
+        assert_eq!(coin_ticker("Solana"), Some("sol"));
+        assert_eq!(coin_ticker("defi"), None);
+    }
+}
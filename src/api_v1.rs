@@ -0,0 +1,397 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use crate::api::{canonical_coin, coin_ticker, NewsItem};
+use crate::db::ArticleFilter;
+use crate::prices::{self, PricePoint, PriceQuote};
+use crate::trending::{self, Window};
+use crate::{news, AppState};
+
+const DEFAULT_PAGE_SIZE: i64 = 20;
+const MAX_PAGE_SIZE: i64 = 100;
+/// Stored headlines included with a coin.
+const COIN_NEWS: i64 = 10;
+const TRENDING_TERMS: usize = 10;
+
+/// Routes under `/api/v1`. Everything here answers JSON, errors included.
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/news", get(list_news))
+        .route("/coins/:symbol", get(coin_details))
+        .route("/trending", get(trending))
+        .fallback(|| async { ApiError::new(StatusCode::NOT_FOUND, "not_found", "No such API endpoint") })
+}
+
+/// Rendered as an `ApiErrorBody`.
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    code: &'static str,
+    message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ApiErrorBody {
+    pub error: ApiErrorDetail,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ApiErrorDetail {
+    /// Stable, machine-readable, e.g. `invalid_parameter` or `unknown_coin`.
+    pub code: String,
+    pub message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        ApiError { status, code, message: message.into() }
+    }
+
+    fn invalid(message: impl Into<String>) -> Self {
+        ApiError::new(StatusCode::BAD_REQUEST, "invalid_parameter", message)
+    }
+
+    fn storage(e: String) -> Self {
+        tracing::error!("API storage error: {}", e);
+        ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "internal_error", "Failed to read stored news")
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = ApiErrorBody { error: ApiErrorDetail { code: self.code.to_string(), message: self.message } };
+        (self.status, Json(body)).into_response()
+    }
+}
+
+/// Every parameter is taken as a string and validated here, so a bad value
+/// gets a JSON error rather than axum's plain-text rejection.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct NewsParams {
+    /// Coin or search term; matches what it found and article text.
+    pub q: Option<String>,
+    /// Publisher, e.g. `coindesk`.
+    pub source: Option<String>,
+    /// `positive`, `negative` or `neutral`.
+    pub sentiment: Option<String>,
+    /// Earliest publication date: `2025-04-07` or an RFC 3339 timestamp.
+    pub from: Option<String>,
+    /// Latest publication date; a plain date includes the whole day.
+    pub to: Option<String>,
+    /// Page size, 1-100. Defaults to 20.
+    pub limit: Option<String>,
+    /// `next_cursor` of the previous page.
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct NewsPage {
+    pub items: Vec<NewsItem>,
+    /// Pass as `cursor` for the next page; absent on the last one.
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CoinDetails {
+    pub coin: String,
+    pub symbol: Option<String>,
+    pub price: Option<PriceQuote>,
+    pub history: Vec<PricePoint>,
+    pub news: Vec<NewsItem>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TrendingParams {
+    /// `1h`, `24h` or `7d`. Defaults to `24h`.
+    pub window: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TrendingTerm {
+    /// Canonical coin name, or the search term when it is not a known coin.
+    pub term: String,
+    /// Searches in the window, each weighted down as it ages: the weight
+    /// halves every quarter of the window.
+    pub score: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SearchCount {
+    pub term: String,
+    pub searches: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TrendingResponse {
+    pub window: String,
+    /// Most searched recently, highest score first.
+    pub trending: Vec<TrendingTerm>,
+    /// Most searched of all time.
+    pub most_searched: Vec<SearchCount>,
+}
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|value| !value.is_empty())
+}
+
+/// Accepts RFC 3339 timestamps or plain dates; a plain `to` date includes
+/// the whole day.
+fn parse_date(name: &str, value: &str, end_of_day: bool) -> Result<DateTime<Utc>, ApiError> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+        return Ok(date.with_timezone(&Utc));
+    }
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| ApiError::invalid(format!("`{}` must be a date (2025-04-07) or an RFC 3339 timestamp", name)))?;
+    let time = if end_of_day { date.and_hms_micro_opt(23, 59, 59, 999_999) } else { date.and_hms_opt(0, 0, 0) };
+    Ok(time.expect("valid time of day").and_utc())
+}
+
+fn parse_sentiment(value: &str) -> Result<String, ApiError> {
+    ["Positive", "Negative", "Neutral"]
+        .into_iter()
+        .find(|sentiment| sentiment.eq_ignore_ascii_case(value))
+        .map(str::to_string)
+        .ok_or_else(|| ApiError::invalid("`sentiment` must be positive, negative or neutral"))
+}
+
+/// Cursors are opaque to clients: the hex-encoded `published_at` (in
+/// microseconds) and id of the last article on the previous page.
+fn encode_cursor(published_at: DateTime<Utc>, id: i64) -> String {
+    hex::encode(format!("{}:{}", published_at.timestamp_micros(), id))
+}
+
+fn decode_cursor(cursor: &str) -> Result<(DateTime<Utc>, i64), ApiError> {
+    let invalid = || ApiError::new(StatusCode::BAD_REQUEST, "invalid_cursor", "`cursor` is not a cursor from a previous page");
+    let decoded = hex::decode(cursor).ok().and_then(|bytes| String::from_utf8(bytes).ok()).ok_or_else(invalid)?;
+    let (micros, id) = decoded.split_once(':').ok_or_else(invalid)?;
+    let published_at = micros.parse().ok().and_then(DateTime::from_timestamp_micros).ok_or_else(invalid)?;
+    let id = id.parse().map_err(|_| invalid())?;
+    Ok((published_at, id))
+}
+
+impl NewsParams {
+    fn filter(&self) -> Result<ArticleFilter, ApiError> {
+        let limit = match non_empty(&self.limit) {
+            Some(limit) => limit
+                .parse::<i64>()
+                .ok()
+                .filter(|limit| (1..=MAX_PAGE_SIZE).contains(limit))
+                .ok_or_else(|| ApiError::invalid(format!("`limit` must be between 1 and {}", MAX_PAGE_SIZE)))?,
+            None => DEFAULT_PAGE_SIZE,
+        };
+
+        Ok(ArticleFilter {
+            query: non_empty(&self.q).map(str::to_lowercase),
+            source: non_empty(&self.source).map(str::to_string),
+            sentiment: non_empty(&self.sentiment).map(parse_sentiment).transpose()?,
+            from: non_empty(&self.from).map(|from| parse_date("from", from, false)).transpose()?,
+            to: non_empty(&self.to).map(|to| parse_date("to", to, true)).transpose()?,
+            before: non_empty(&self.cursor).map(decode_cursor).transpose()?,
+            limit: Some(limit),
+            ..Default::default()
+        })
+    }
+}
+
+/// `GET /api/v1/news`: stored articles, newest first. A first page with `q`
+/// also fetches live results for it, the same way `/search` does.
+#[utoipa::path(
+    get,
+    path = "/api/v1/news",
+    tag = "news",
+    params(NewsParams),
+    responses(
+        (status = 200, description = "A page of articles", body = NewsPage),
+        (status = 400, description = "A parameter or the cursor is invalid", body = ApiErrorBody),
+    )
+)]
+pub async fn list_news(
+    State(state): State<AppState>,
+    Query(params): Query<NewsParams>,
+) -> Result<Json<NewsPage>, ApiError> {
+    let mut filter = params.filter()?;
+    let limit = filter.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+
+    if let (Some(query), None) = (&filter.query, filter.before) {
+        if let Err(e) = news::cached_fetch(&state, query).await {
+            tracing::warn!("Live fetch for API query {} failed: {}", query, e);
+        }
+    }
+
+    // One extra row tells whether another page follows
+    filter.limit = Some(limit + 1);
+    let mut articles = state.db.list_articles(&filter).await.map_err(ApiError::storage)?;
+    let next_cursor = if articles.len() as i64 > limit {
+        articles.truncate(limit as usize);
+        articles.last().map(|last| encode_cursor(last.news.published_at, last.id))
+    } else {
+        None
+    };
+
+    Ok(Json(NewsPage {
+        items: articles.into_iter().map(|article| article.news).collect(),
+        next_cursor,
+    }))
+}
+
+/// `GET /api/v1/coins/{symbol}`: price, recent candles and stored headlines
+/// for a coin given by ticker or name.
+#[utoipa::path(
+    get,
+    path = "/api/v1/coins/{symbol}",
+    tag = "news",
+    params(("symbol" = String, Path, description = "Ticker or name, e.g. btc or bitcoin")),
+    responses(
+        (status = 200, description = "What is known about the coin", body = CoinDetails),
+        (status = 404, description = "Unknown coin", body = ApiErrorBody),
+    )
+)]
+pub async fn coin_details(
+    State(state): State<AppState>,
+    Path(symbol): Path<String>,
+) -> Result<Json<CoinDetails>, ApiError> {
+    let coin = canonical_coin(&symbol);
+    if coin.is_empty() {
+        return Err(ApiError::invalid("A coin symbol is required"));
+    }
+
+    let price = prices::cached_quote(&state.cache, state.prices.as_ref(), &coin).await;
+    let history = match &price {
+        Some(quote) => prices::price_history(state.db.as_ref(), state.prices.as_ref(), &quote.coin).await,
+        None => Vec::new(),
+    };
+    let filter = ArticleFilter { coin: Some(coin.clone()), limit: Some(COIN_NEWS), ..Default::default() };
+    let news: Vec<NewsItem> = state.db.list_articles(&filter).await
+        .map_err(ApiError::storage)?
+        .into_iter()
+        .map(|article| article.news)
+        .collect();
+
+    let symbol = coin_ticker(&coin).map(str::to_string);
+    if symbol.is_none() && price.is_none() && news.is_empty() {
+        return Err(ApiError::new(StatusCode::NOT_FOUND, "unknown_coin", format!("Nothing is known about {}", coin)));
+    }
+
+    Ok(Json(CoinDetails { coin, symbol, price, history, news }))
+}
+
+/// `GET /api/v1/trending?window=24h`: what is being searched now and what
+/// has been searched most.
+#[utoipa::path(
+    get,
+    path = "/api/v1/trending",
+    tag = "news",
+    params(TrendingParams),
+    responses(
+        (status = 200, description = "Trending and most searched terms, highest first", body = TrendingResponse),
+        (status = 400, description = "Unknown window", body = ApiErrorBody),
+    )
+)]
+pub async fn trending(
+    State(state): State<AppState>,
+    Query(params): Query<TrendingParams>,
+) -> Result<Json<TrendingResponse>, ApiError> {
+    let window = match non_empty(&params.window) {
+        Some(window) => Window::from_param(window)
+            .ok_or_else(|| ApiError::invalid(format!("Unknown window {:?}; use 1h, 24h or 7d", window)))?,
+        None => Window::Day,
+    };
+
+    let trending = trending::trending(&state.cache, window, TRENDING_TERMS).await
+        .into_iter()
+        .map(|(term, score)| TrendingTerm { term, score })
+        .collect();
+    let most_searched = trending::most_searched(&state.cache, TRENDING_TERMS).await
+        .into_iter()
+        .map(|(term, searches)| SearchCount { term, searches })
+        .collect();
+    Ok(Json(TrendingResponse { window: window.as_str().to_string(), trending, most_searched }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::tests::news_item;
+    use serde_json::Value;
+
+    async fn serve(state: AppState) -> String {
+        let app = Router::new().nest("/api/v1", router()).with_state(state);
+        format!("{}/api/v1", crate::spawn_app(app).await)
+    }
+
+    #[tokio::test]
+    async fn news_pages_follow_the_cursor() {
+        let state = AppState::for_tests().await;
+        state.db.store_news_items("btc", &[
+            news_item("https://1", 1, "Positive", "NewsData.io"),
+            news_item("https://2", 2, "Negative", "NewsData.io"),
+            news_item("https://3", 3, "Positive", "NewsData.io"),
+        ]).await.unwrap();
+        state.db.store_news_items("eth", &[news_item("https://4", 4, "Positive", "NewsData.io")]).await.unwrap();
+        let base = serve(state).await;
+        let get = |query: String| {
+            let url = format!("{}/news?{}", base, query);
+            async move { reqwest::get(url).await.unwrap().json::<NewsPage>().await.unwrap() }
+        };
+        let urls = |page: &NewsPage| page.items.iter().map(|item| item.url.clone()).collect::<Vec<_>>();
+
+        let first = get("q=BTC&limit=2".into()).await;
+        assert_eq!(urls(&first), vec!["https://3", "https://2"]);
+        let cursor = first.next_cursor.unwrap();
+        let second = get(format!("q=BTC&limit=2&cursor={}", cursor)).await;
+        assert_eq!(urls(&second), vec!["https://1"]);
+        assert_eq!(second.next_cursor, None);
+
+        let positive = get("sentiment=positive&from=2025-04-02&to=2025-04-03".into()).await;
+        assert_eq!(urls(&positive), vec!["https://3"]);
+    }
+
+    #[tokio::test]
+    async fn errors_are_json() {
+        let base = serve(AppState::for_tests().await).await;
+        let error = |path: &str| {
+            let url = format!("{}{}", base, path);
+            async move {
+                let response = reqwest::get(url).await.unwrap();
+                let status = response.status().as_u16();
+                let body = response.json::<Value>().await.unwrap();
+                (status, body["error"]["code"].as_str().unwrap().to_string())
+            }
+        };
+
+        assert_eq!(error("/news?limit=500").await, (400, "invalid_parameter".to_string()));
+        assert_eq!(error("/news?from=yesterday").await, (400, "invalid_parameter".to_string()));
+        assert_eq!(error("/news?sentiment=bullish").await, (400, "invalid_parameter".to_string()));
+        assert_eq!(error("/news?cursor=zz").await, (400, "invalid_cursor".to_string()));
+        assert_eq!(error("/coins/notacoin").await, (404, "unknown_coin".to_string()));
+        assert_eq!(error("/nope").await, (404, "not_found".to_string()));
+        assert_eq!(error("/trending?window=1y").await, (400, "invalid_parameter".to_string()));
+    }
+
+    #[tokio::test]
+    async fn coins_combine_symbol_and_stored_news() {
+        let state = AppState::for_tests().await;
+        state.db.store_news_items("bitcoin", &[news_item("https://1", 1, "Positive", "NewsData.io")]).await.unwrap();
+        let base = serve(state).await;
+
+        let coin: CoinDetails = reqwest::get(format!("{}/coins/BTC", base)).await.unwrap().json().await.unwrap();
+        assert_eq!(coin.coin, "bitcoin");
+        assert_eq!(coin.symbol.as_deref(), Some("btc"));
+        // The test price provider cannot be reached
+        assert!(coin.price.is_none());
+        assert_eq!(coin.news[0].url, "https://1");
+
+        let trending: TrendingResponse = reqwest::get(format!("{}/trending?window=1h", base)).await.unwrap().json().await.unwrap();
+        assert_eq!(trending.window, "1h");
+        assert!(trending.trending.is_empty());
+    }
+}
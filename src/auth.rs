@@ -1,68 +1,311 @@
 use axum::{
-    extract::ws::{Message, WebSocket, WebSocketUpgrade},
-    response::IntoResponse,
-    extract::State,
-    http::StatusCode,
+    async_trait,
+    extract::{FromRequestParts, State},
+    http::{header, request::Parts, HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Response},
     Json,
-    response::Html,
 };
-use jsonwebtoken::{encode, EncodingKey, Header};
+use chrono::{Duration, Utc};
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
-use tokio::sync::broadcast;
-use futures_util::{SinkExt, StreamExt};
-use crate::{AppState, api};
-use serde_json::json;
-use chrono;
+use sha2::{Digest, Sha256};
+use crate::AppState;
+use crate::db::{RefreshTokenUse, User};
+use utoipa::ToSchema;
 
-const JWT_SECRET: &[u8] = b"your-secret-key"; // In production, use environment variable
+/// Cookies that carry the session for browser requests.
+const TOKEN_COOKIE: &str = "token";
+const REFRESH_COOKIE: &str = "refresh_token";
+
+/// Access tokens cannot be revoked in the database, so they are short-lived;
+/// refresh tokens are single use and stored server side.
+const ACCESS_TOKEN_TTL: Duration = Duration::minutes(15);
+const REFRESH_TOKEN_TTL: Duration = Duration::days(30);
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,
     pub exp: usize,
+    pub iat: usize,
+    /// Unique token id, the key for revoking this token before it expires.
+    pub jti: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct LoginRequest {
     pub username: String,
     pub password: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct RegisterRequest {
     pub username: String,
     pub password: String,
     pub email: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// Body of `/refresh` and `/logout`. Browsers can omit it and rely on the
+/// `refresh_token` cookie instead.
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct RefreshRequest {
+    pub refresh_token: Option<String>,
+}
+
+/// New articles for a coin, as pushed to `/ws` and `/events` subscribers.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct NewsUpdate {
     pub coin: String,
     pub news: Vec<crate::api::NewsItem>,
+    /// When the articles were stored, see `db::FoundNews`.
+    pub found_at: chrono::DateTime<Utc>,
 }
 
-fn create_token(username: &str) -> Result<String, String> {
-    let claims = Claims {
-        sub: username.to_string(),
-        exp: (chrono::Utc::now() + chrono::Duration::hours(24)).timestamp() as usize,
-    };
-    
-    encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(JWT_SECRET),
-    ).map_err(|e| format!("Failed to create token: {}", e))
+/// Returned by `/login` and `/refresh`. The tokens are also set as cookies.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SessionResponse {
+    pub token: String,
+    pub refresh_token: String,
+    /// Seconds until `token` expires.
+    pub expires_in: i64,
+    pub username: String,
+    pub email: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MessageResponse {
+    pub message: String,
+}
+
+/// Error body of the session, watchlist and bookmark endpoints.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ErrorResponse {
+    pub error: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UserProfile {
+    pub id: i64,
+    pub username: String,
+    pub email: String,
+}
+
+/// Signs and verifies access tokens with the configured `JWT_SECRET`.
+pub struct JwtKeys {
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+}
+
+impl JwtKeys {
+    pub fn new(secret: &[u8]) -> Self {
+        JwtKeys {
+            encoding: EncodingKey::from_secret(secret),
+            decoding: DecodingKey::from_secret(secret),
+        }
+    }
+
+    pub fn create_token(&self, username: &str) -> Result<String, String> {
+        let now = Utc::now();
+        let claims = Claims {
+            sub: username.to_string(),
+            exp: (now + ACCESS_TOKEN_TTL).timestamp() as usize,
+            iat: now.timestamp() as usize,
+            jti: random_token(16),
+        };
+
+        encode(&Header::default(), &claims, &self.encoding)
+            .map_err(|e| format!("Failed to create token: {}", e))
+    }
+
+    /// Checks the signature and expiry of a token and returns its claims.
+    /// Revocation is checked separately, see `is_revoked`.
+    pub fn verify_token(&self, token: &str) -> Result<Claims, String> {
+        decode::<Claims>(token, &self.decoding, &Validation::default())
+            .map(|data| data.claims)
+            .map_err(|e| format!("Invalid token: {}", e))
+    }
+}
+
+/// `count` random bytes, hex encoded.
+fn random_token(count: usize) -> String {
+    let bytes: Vec<u8> = (0..count).map(|_| rand::random::<u8>()).collect();
+    hex::encode(bytes)
+}
+
+fn hash_refresh_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Access tokens revoked by `/logout` are denylisted in Redis by `jti`, and
+/// `/logout-all` records a cut-off before which every token of the user is
+/// rejected. `iat` has whole seconds, so the cut-off spares tokens from its
+/// own second: a login right after `/logout-all` must keep working, and the
+/// token that called it is denylisted by `jti`. Both entries expire with the longest-lived access token they
+/// could affect. If Redis is unreachable revocations are not enforced; the
+/// short access token lifetime bounds the exposure.
+async fn is_revoked(state: &AppState, claims: &Claims) -> bool {
+    if state.cache.peek(&format!("revoked_jti:{}", claims.jti)).await.is_some() {
+        return true;
+    }
+    state
+        .cache
+        .peek(&format!("revoked_before:{}", claims.sub))
+        .await
+        .and_then(|cutoff| cutoff.parse::<usize>().ok())
+        .is_some_and(|cutoff| claims.iat < cutoff)
+}
+
+/// The user a request's access token belongs to, with the token's claims.
+/// The token is read from an `Authorization: Bearer` header, falling back to
+/// the `token` cookie. Handlers that take this reject missing, invalid,
+/// expired or revoked tokens with 401.
+pub struct AuthUser {
+    pub user: User,
+    pub claims: Claims,
+}
+
+pub struct AuthError {
+    status: StatusCode,
+    message: &'static str,
+}
+
+impl AuthError {
+    fn unauthorized(message: &'static str) -> Self {
+        AuthError { status: StatusCode::UNAUTHORIZED, message }
+    }
+
+    fn internal(message: &'static str) -> Self {
+        AuthError { status: StatusCode::INTERNAL_SERVER_ERROR, message }
+    }
+}
+
+impl IntoResponse for AuthError {
+    fn into_response(self) -> Response {
+        let body = ErrorResponse { error: self.message.to_string() };
+        let mut response = (self.status, Json(body)).into_response();
+        if self.status == StatusCode::UNAUTHORIZED {
+            response
+                .headers_mut()
+                .insert(header::WWW_AUTHENTICATE, header::HeaderValue::from_static("Bearer"));
+        }
+        response
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for AuthUser {
+    type Rejection = AuthError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let token = bearer_token(&parts.headers)
+            .or_else(|| cookie_value(&parts.headers, TOKEN_COOKIE))
+            .ok_or(AuthError::unauthorized("Missing token"))?;
+
+        let claims = state
+            .jwt
+            .verify_token(&token)
+            .map_err(|_| AuthError::unauthorized("Invalid or expired token"))?;
+
+        if is_revoked(state, &claims).await {
+            return Err(AuthError::unauthorized("Invalid or expired token"));
+        }
+
+        match state.db.find_user(&claims.sub).await {
+            Ok(Some(user)) => Ok(AuthUser { user, claims }),
+            Ok(None) => Err(AuthError::unauthorized("Invalid or expired token")),
+            Err(e) => {
+                tracing::error!("Failed to load user {}: {}", claims.sub, e);
+                Err(AuthError::internal("Failed to load user"))
+            }
+        }
+    }
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<String> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("bearer") || token.trim().is_empty() {
+        return None;
+    }
+    Some(token.trim().to_string())
+}
+
+fn cookie_value(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, value)| *key == name && !value.is_empty())
+        .map(|(_, value)| value.to_string())
+}
+
+fn session_cookie(name: &str, value: &str, max_age: Duration) -> String {
+    format!(
+        "{}={}; Path=/; HttpOnly; SameSite=Lax; Max-Age={}",
+        name,
+        value,
+        max_age.num_seconds()
+    )
+}
+
+/// Issues an access token and a new refresh token for `user` and returns
+/// them as the JSON body plus cookies that `/login` and `/refresh` send.
+async fn issue_session(state: &AppState, user: &User) -> Result<Response, String> {
+    let access_token = state.jwt.create_token(&user.username)?;
+    let refresh_token = random_token(32);
+    state
+        .db
+        .store_refresh_token(user.id, &hash_refresh_token(&refresh_token), Utc::now() + REFRESH_TOKEN_TTL)
+        .await?;
+
+    Ok((
+        StatusCode::OK,
+        [
+            (header::SET_COOKIE, session_cookie(TOKEN_COOKIE, &access_token, ACCESS_TOKEN_TTL)),
+            (header::SET_COOKIE, session_cookie(REFRESH_COOKIE, &refresh_token, REFRESH_TOKEN_TTL)),
+        ],
+        Json(SessionResponse {
+            token: access_token,
+            refresh_token,
+            expires_in: ACCESS_TOKEN_TTL.num_seconds(),
+            username: user.username.clone(),
+            email: user.email.clone(),
+        }),
+    )
+        .into_response())
+}
+
+/// Expires both session cookies.
+fn clear_session(message: &str) -> Response {
+    (
+        StatusCode::OK,
+        [
+            (header::SET_COOKIE, session_cookie(TOKEN_COOKIE, "", Duration::zero())),
+            (header::SET_COOKIE, session_cookie(REFRESH_COOKIE, "", Duration::zero())),
+        ],
+        Json(MessageResponse { message: message.to_string() }),
+    )
+        .into_response()
+}
+
+/// Denylists an access token until it would have expired anyway.
+async fn revoke_access_token(state: &AppState, claims: &Claims) {
+    let remaining = claims.exp.saturating_sub(Utc::now().timestamp() as usize) as u64;
+    if remaining > 0 {
+        state.cache.set_with_ttl(&format!("revoked_jti:{}", claims.jti), "1", remaining).await;
+    }
 }
 
 pub async fn login_page() -> Html<String> {
-    let html = format!(r#"
+    let html = r#"
         <!DOCTYPE html>
         <html>
         <head>
             <title>Login - Crypto News</title>
             <style>
-                body {{ font-family: Arial, sans-serif; max-width: 800px; margin: 0 auto; padding: 20px; }}
-                .nav-container {{
+                body { font-family: Arial, sans-serif; max-width: 800px; margin: 0 auto; padding: 20px; }
+                .nav-container {
                     display: flex;
                     justify-content: space-between;
                     align-items: center;
@@ -71,16 +314,16 @@ pub async fn login_page() -> Html<String> {
                     color: white;
                     border-radius: 4px;
                     margin-bottom: 20px;
-                }}
-                .nav-title {{
+                }
+                .nav-title {
                     font-size: 1.2em;
                     font-weight: bold;
-                }}
-                .nav-buttons {{
+                }
+                .nav-buttons {
                     display: flex;
                     gap: 10px;
-                }}
-                .nav-button {{
+                }
+                .nav-button {
                     padding: 8px 15px;
                     background-color: #4CAF50;
                     color: white;
@@ -89,32 +332,32 @@ pub async fn login_page() -> Html<String> {
                     cursor: pointer;
                     text-decoration: none;
                     font-size: 14px;
-                }}
-                .nav-button:hover {{ background-color: #45a049; }}
-                .form-container {{
+                }
+                .nav-button:hover { background-color: #45a049; }
+                .form-container {
                     max-width: 400px;
                     margin: 0 auto;
                     padding: 20px;
                     background-color: #f9f9f9;
                     border-radius: 4px;
                     box-shadow: 0 1px 3px rgba(0,0,0,0.1);
-                }}
-                .form-group {{
+                }
+                .form-group {
                     margin-bottom: 15px;
-                }}
-                label {{
+                }
+                label {
                     display: block;
                     margin-bottom: 5px;
                     font-weight: bold;
-                }}
-                input {{
+                }
+                input {
                     width: 100%;
                     padding: 8px;
                     border: 1px solid #ddd;
                     border-radius: 4px;
                     box-sizing: border-box;
-                }}
-                button {{
+                }
+                button {
                     width: 100%;
                     padding: 10px;
                     background-color: #4CAF50;
@@ -123,13 +366,13 @@ pub async fn login_page() -> Html<String> {
                     border-radius: 4px;
                     cursor: pointer;
                     font-size: 16px;
-                }}
-                button:hover {{ background-color: #45a049; }}
-                .error-message {{
+                }
+                button:hover { background-color: #45a049; }
+                .error-message {
                     color: #e74c3c;
                     margin-top: 10px;
                     text-align: center;
-                }}
+                }
             </style>
         </head>
         <body>
@@ -159,54 +402,54 @@ pub async fn login_page() -> Html<String> {
                 </p>
             </div>
             <script>
-                document.getElementById('loginForm').addEventListener('submit', async (e) => {{
+                document.getElementById('loginForm').addEventListener('submit', async (e) => {
                     e.preventDefault();
                     
                     const username = document.getElementById('username').value;
                     const password = document.getElementById('password').value;
                     
-                    try {{
-                        const response = await fetch('/login', {{
+                    try {
+                        const response = await fetch('/login', {
                             method: 'POST',
-                            headers: {{
+                            headers: {
                                 'Content-Type': 'application/json',
-                            }},
-                            body: JSON.stringify({{
+                            },
+                            body: JSON.stringify({
                                 username,
                                 password
-                            }})
-                        }});
+                            })
+                        });
                         
                         const data = await response.json();
                         
-                        if (response.ok) {{
+                        if (response.ok) {
                             // Store the token
                             localStorage.setItem('token', data.token);
                             window.location.href = '/';
-                        }} else {{
+                        } else {
                             document.getElementById('errorMessage').textContent = data.error || 'Login failed';
-                        }}
-                    }} catch (error) {{
+                        }
+                    } catch (error) {
                         document.getElementById('errorMessage').textContent = 'An error occurred during login';
-                    }}
-                }});
+                    }
+                });
             </script>
         </body>
         </html>
-    "#);
+    "#.to_string();
 
     Html(html)
 }
 
 pub async fn register_page() -> Html<String> {
-    let html = format!(r#"
+    let html = r#"
         <!DOCTYPE html>
         <html>
         <head>
             <title>Register - Crypto News</title>
             <style>
-                body {{ font-family: Arial, sans-serif; max-width: 800px; margin: 0 auto; padding: 20px; }}
-                .nav-container {{
+                body { font-family: Arial, sans-serif; max-width: 800px; margin: 0 auto; padding: 20px; }
+                .nav-container {
                     display: flex;
                     justify-content: space-between;
                     align-items: center;
@@ -215,16 +458,16 @@ pub async fn register_page() -> Html<String> {
                     color: white;
                     border-radius: 4px;
                     margin-bottom: 20px;
-                }}
-                .nav-title {{
+                }
+                .nav-title {
                     font-size: 1.2em;
                     font-weight: bold;
-                }}
-                .nav-buttons {{
+                }
+                .nav-buttons {
                     display: flex;
                     gap: 10px;
-                }}
-                .nav-button {{
+                }
+                .nav-button {
                     padding: 8px 15px;
                     background-color: #4CAF50;
                     color: white;
@@ -233,32 +476,32 @@ pub async fn register_page() -> Html<String> {
                     cursor: pointer;
                     text-decoration: none;
                     font-size: 14px;
-                }}
-                .nav-button:hover {{ background-color: #45a049; }}
-                .form-container {{
+                }
+                .nav-button:hover { background-color: #45a049; }
+                .form-container {
                     max-width: 400px;
                     margin: 0 auto;
                     padding: 20px;
                     background-color: #f9f9f9;
                     border-radius: 4px;
                     box-shadow: 0 1px 3px rgba(0,0,0,0.1);
-                }}
-                .form-group {{
+                }
+                .form-group {
                     margin-bottom: 15px;
-                }}
-                label {{
+                }
+                label {
                     display: block;
                     margin-bottom: 5px;
                     font-weight: bold;
-                }}
-                input {{
+                }
+                input {
                     width: 100%;
                     padding: 8px;
                     border: 1px solid #ddd;
                     border-radius: 4px;
                     box-sizing: border-box;
-                }}
-                button {{
+                }
+                button {
                     width: 100%;
                     padding: 10px;
                     background-color: #4CAF50;
@@ -267,13 +510,13 @@ pub async fn register_page() -> Html<String> {
                     border-radius: 4px;
                     cursor: pointer;
                     font-size: 16px;
-                }}
-                button:hover {{ background-color: #45a049; }}
-                .error-message {{
+                }
+                button:hover { background-color: #45a049; }
+                .error-message {
                     color: #e74c3c;
                     margin-top: 10px;
                     text-align: center;
-                }}
+                }
             </style>
         </head>
         <body>
@@ -307,275 +550,457 @@ pub async fn register_page() -> Html<String> {
                 </p>
             </div>
             <script>
-                document.getElementById('registerForm').addEventListener('submit', async (e) => {{
+                document.getElementById('registerForm').addEventListener('submit', async (e) => {
                     e.preventDefault();
                     
                     const username = document.getElementById('username').value;
                     const email = document.getElementById('email').value;
                     const password = document.getElementById('password').value;
                     
-                    try {{
-                        const response = await fetch('/register', {{
+                    try {
+                        const response = await fetch('/register', {
                             method: 'POST',
-                            headers: {{
+                            headers: {
                                 'Content-Type': 'application/json',
-                            }},
-                            body: JSON.stringify({{
+                            },
+                            body: JSON.stringify({
                                 username,
                                 email,
                                 password
-                            }})
-                        }});
+                            })
+                        });
                         
                         const data = await response.json();
                         
-                        if (response.ok) {{
+                        if (response.ok) {
                             window.location.href = '/login';
-                        }} else {{
+                        } else {
                             document.getElementById('errorMessage').textContent = data.error || 'Registration failed';
-                        }}
-                    }} catch (error) {{
+                        }
+                    } catch (error) {
                         document.getElementById('errorMessage').textContent = 'An error occurred during registration';
-                    }}
-                }});
+                    }
+                });
             </script>
         </body>
         </html>
-    "#);
+    "#.to_string();
 
     Html(html)
 }
 
+#[utoipa::path(
+    post,
+    path = "/login",
+    tag = "session",
+    request_body = LoginRequest,
+    responses(
+        (status = 200, description = "Signed in", body = SessionResponse),
+        (status = 401, description = "Wrong username or password", body = ErrorResponse),
+    )
+)]
 pub async fn handle_login(
     State(state): State<AppState>,
     Json(credentials): Json<LoginRequest>,
-) -> impl IntoResponse {
+) -> Response {
     match state.db.verify_user(&credentials.username, &credentials.password).await {
-        Ok(user) => {
-            match create_token(&user.username) {
-                Ok(token) => {
-                    (StatusCode::OK, Json(json!({
-                        "token": token,
-                        "username": user.username,
-                        "email": user.email
-                    })))
-                }
-                Err(_) => (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Failed to create token"}))
-                )
-            }
-        }
+        Ok(user) => issue_session(&state, &user).await.unwrap_or_else(|e| {
+            tracing::error!("Failed to start session for {}: {}", user.username, e);
+            AuthError::internal("Failed to create token").into_response()
+        }),
         Err(_) => (
             StatusCode::UNAUTHORIZED,
-            Json(json!({"error": "Invalid credentials"}))
-        )
+            Json(ErrorResponse { error: "Invalid credentials".to_string() })
+        ).into_response()
+    }
+}
+
+/// Exchanges a refresh token for a new access token and a new refresh token.
+/// Presenting an already used refresh token revokes every session of its
+/// owner, since it means the token was copied.
+#[utoipa::path(
+    post,
+    path = "/refresh",
+    tag = "session",
+    request_body(content = Option<RefreshRequest>, description = "Optional; the `refresh_token` cookie is used otherwise"),
+    responses(
+        (status = 200, description = "A new token pair", body = SessionResponse),
+        (status = 401, description = "Missing, expired, revoked or reused refresh token", body = ErrorResponse),
+    )
+)]
+pub async fn handle_refresh(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Option<Json<RefreshRequest>>,
+) -> Response {
+    let Some(token) = body
+        .and_then(|Json(body)| body.refresh_token)
+        .or_else(|| cookie_value(&headers, REFRESH_COOKIE))
+    else {
+        return AuthError::unauthorized("Missing refresh token").into_response();
+    };
+
+    let user_id = match state.db.consume_refresh_token(&hash_refresh_token(&token)).await {
+        Ok(RefreshTokenUse::Valid(user_id)) => user_id,
+        Ok(RefreshTokenUse::Reused(user_id)) => {
+            tracing::warn!("Refresh token reused for user {}; revoking all sessions", user_id);
+            if let Err(e) = state.db.revoke_refresh_tokens(user_id).await {
+                tracing::error!("Failed to revoke sessions for user {}: {}", user_id, e);
+            }
+            return AuthError::unauthorized("Invalid or expired refresh token").into_response();
+        }
+        Ok(RefreshTokenUse::Invalid) => {
+            return AuthError::unauthorized("Invalid or expired refresh token").into_response();
+        }
+        Err(e) => {
+            tracing::error!("Failed to check refresh token: {}", e);
+            return AuthError::internal("Failed to refresh session").into_response();
+        }
+    };
+
+    match state.db.find_user_by_id(user_id).await {
+        Ok(Some(user)) => issue_session(&state, &user).await.unwrap_or_else(|e| {
+            tracing::error!("Failed to refresh session for {}: {}", user.username, e);
+            AuthError::internal("Failed to refresh session").into_response()
+        }),
+        Ok(None) => AuthError::unauthorized("Invalid or expired refresh token").into_response(),
+        Err(e) => {
+            tracing::error!("Failed to load user {}: {}", user_id, e);
+            AuthError::internal("Failed to refresh session").into_response()
+        }
     }
 }
 
+/// Ends the current session: revokes the access token when one is sent and
+/// still valid, and the refresh token from the body or cookie. An expired
+/// access token does not stop the refresh token from being revoked.
+#[utoipa::path(
+    post,
+    path = "/logout",
+    tag = "session",
+    security((), ("bearer_auth" = [])),
+    request_body(content = Option<RefreshRequest>, description = "Optional; the `refresh_token` cookie is used otherwise"),
+    responses(
+        (status = 200, description = "Session ended", body = MessageResponse),
+    )
+)]
+pub async fn handle_logout(
+    State(state): State<AppState>,
+    auth: Option<AuthUser>,
+    headers: HeaderMap,
+    body: Option<Json<RefreshRequest>>,
+) -> Response {
+    if let Some(auth) = &auth {
+        revoke_access_token(&state, &auth.claims).await;
+    }
+
+    if let Some(token) = body
+        .and_then(|Json(body)| body.refresh_token)
+        .or_else(|| cookie_value(&headers, REFRESH_COOKIE))
+    {
+        if let Err(e) = state.db.revoke_refresh_token(&hash_refresh_token(&token)).await {
+            tracing::error!("Failed to revoke refresh token: {}", e);
+            return AuthError::internal("Failed to log out").into_response();
+        }
+    }
+
+    clear_session("Logged out")
+}
+
+/// Ends every session of the user, on all devices.
+#[utoipa::path(
+    post,
+    path = "/logout-all",
+    tag = "session",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Every session ended", body = MessageResponse),
+        (status = 401, description = "Not signed in", body = ErrorResponse),
+    )
+)]
+pub async fn handle_logout_all(State(state): State<AppState>, auth: AuthUser) -> Response {
+    if let Err(e) = state.db.revoke_refresh_tokens(auth.user.id).await {
+        tracing::error!("Failed to revoke sessions for {}: {}", auth.user.username, e);
+        return AuthError::internal("Failed to log out").into_response();
+    }
+
+    state
+        .cache
+        .set_with_ttl(
+            &format!("revoked_before:{}", auth.user.username),
+            &Utc::now().timestamp().to_string(),
+            ACCESS_TOKEN_TTL.num_seconds() as u64,
+        )
+        .await;
+    revoke_access_token(&state, &auth.claims).await;
+
+    clear_session("Logged out of all sessions")
+}
+
+/// The signed-in user's profile.
+#[utoipa::path(
+    get,
+    path = "/me",
+    tag = "session",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The signed-in user", body = UserProfile),
+        (status = 401, description = "Not signed in", body = ErrorResponse),
+    )
+)]
+pub async fn current_user(AuthUser { user, .. }: AuthUser) -> Json<UserProfile> {
+    Json(UserProfile { id: user.id, username: user.username, email: user.email })
+}
+
+#[utoipa::path(
+    post,
+    path = "/register",
+    tag = "session",
+    request_body = RegisterRequest,
+    responses(
+        (status = 200, description = "Account created", body = MessageResponse),
+        (status = 400, description = "The username is taken or the account could not be created", body = ErrorResponse),
+    )
+)]
 pub async fn handle_register(
     State(state): State<AppState>,
     Json(register): Json<RegisterRequest>,
-) -> impl IntoResponse {
+) -> Response {
     match state.db.create_user(&register.username, &register.email, &register.password).await {
-        Ok(()) => (
-            StatusCode::OK,
-            Json(json!({
-                "message": "Registration successful"
-            }))
-        ),
-        Err(e) => (
-            StatusCode::BAD_REQUEST,
-            Json(json!({
-                "error": e
-            }))
-        )
+        Ok(()) => Json(MessageResponse { message: "Registration successful".to_string() }).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })).into_response(),
     }
 }
 
-pub async fn handle_ws(
-    State(state): State<AppState>,
-    ws: WebSocketUpgrade,
-) -> impl IntoResponse {
-    ws.on_upgrade(|socket| handle_socket(socket, state))
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use axum::{routing::{get, post}, Router};
 
-async fn handle_socket(socket: WebSocket, state: AppState) {
-    let (mut sender, mut receiver) = socket.split();
-    let (_tx, _rx) = broadcast::channel::<String>(100);
+    async fn serve(state: AppState) -> String {
+        let app = Router::new()
+            .route("/me", get(current_user))
+            .route("/login", post(handle_login))
+            .route("/refresh", post(handle_refresh))
+            .route("/logout", post(handle_logout))
+            .route("/logout-all", post(handle_logout_all))
+            .with_state(state);
+        crate::spawn_app(app).await
+    }
 
-    let recv_task = tokio::spawn(async move {
-        while let Some(Ok(msg)) = receiver.next().await {
-            if let Message::Text(text) = msg {
-                if let Ok(coin) = serde_json::from_str::<String>(&text) {
-                    let cache_key = format!("news:{}", coin);
-                    
-                    // Try to get from cache first
-                    if let Some(cached_html) = state.cache.get(&cache_key).await {
-                        let _ = sender.send(Message::Text(cached_html)).await;
-                        continue;
-                    }
+    async fn login(client: &reqwest::Client, base: &str) -> serde_json::Value {
+        let response = client
+            .post(format!("{}/login", base))
+            .json(&json!({"username": "satoshi", "password": "hunter2"}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        response.json().await.unwrap()
+    }
 
-                    // If not in cache, fetch from API
-                    match api::fetch_news(&coin).await {
-                        Ok(news) => {
-                            let html = format_news_html(&coin, &news);
-                            let _ = state.cache.set(&cache_key, &html).await;
-                            let _ = sender.send(Message::Text(html)).await;
-                        }
-                        Err(e) => {
-                            tracing::error!("Error fetching news: {:?}", e);
-                            let _ = sender.send(Message::Text("Error fetching news".to_string())).await;
-                        }
-                    }
-                }
+    #[test]
+    fn verify_token_rejects_expired_and_foreign_tokens() {
+        let keys = JwtKeys::new(b"secret");
+        assert_eq!(keys.verify_token(&keys.create_token("satoshi").unwrap()).unwrap().sub, "satoshi");
+
+        let expired = Claims {
+            sub: "satoshi".to_string(),
+            exp: (Utc::now() - Duration::hours(1)).timestamp() as usize,
+            iat: (Utc::now() - Duration::hours(2)).timestamp() as usize,
+            jti: random_token(16),
+        };
+        let expired = encode(&Header::default(), &expired, &EncodingKey::from_secret(b"secret")).unwrap();
+        assert!(keys.verify_token(&expired).is_err());
+
+        let foreign = JwtKeys::new(b"other").create_token("satoshi").unwrap();
+        assert!(keys.verify_token(&foreign).is_err());
+    }
+
+    #[tokio::test]
+    async fn auth_user_accepts_bearer_header_and_cookie() {
+        let state = AppState::for_tests().await;
+        state.db.create_user("satoshi", "satoshi@example.com", "hunter2").await.unwrap();
+        let token = state.jwt.create_token("satoshi").unwrap();
+        let url = format!("{}/me", serve(state).await);
+        let client = reqwest::Client::new();
+
+        let response = client.get(&url).bearer_auth(&token).send().await.unwrap();
+        assert_eq!(response.status(), 200);
+        let user: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(user["username"], "satoshi");
+
+        let response = client.get(&url).header("Cookie", format!("theme=dark; token={}", token)).send().await.unwrap();
+        assert_eq!(response.status(), 200);
+    }
+
+    #[tokio::test]
+    async fn auth_user_rejects_missing_invalid_and_unknown_tokens() {
+        let state = AppState::for_tests().await;
+        let unknown_user = state.jwt.create_token("hal").unwrap();
+        let url = format!("{}/me", serve(state).await);
+        let client = reqwest::Client::new();
+
+        assert_eq!(client.get(&url).send().await.unwrap().status(), 401);
+        assert_eq!(client.get(&url).bearer_auth("not-a-jwt").send().await.unwrap().status(), 401);
+        assert_eq!(client.get(&url).bearer_auth(&unknown_user).send().await.unwrap().status(), 401);
+
+        let forged = JwtKeys::new(b"other").create_token("hal").unwrap();
+        let response = client.get(&url).bearer_auth(forged).send().await.unwrap();
+        assert_eq!(response.status(), 401);
+        assert_eq!(response.headers()["www-authenticate"], "Bearer");
+    }
+
+    #[tokio::test]
+    async fn refresh_rotates_tokens_and_revokes_everything_on_reuse() {
+        let state = AppState::for_tests().await;
+        state.db.create_user("satoshi", "satoshi@example.com", "hunter2").await.unwrap();
+        let base = serve(state).await;
+        let client = reqwest::Client::new();
+
+        let first = login(&client, &base).await;
+        let other_device = login(&client, &base).await;
+        let refresh = |token: &serde_json::Value| {
+            client
+                .post(format!("{}/refresh", base))
+                .json(&json!({"refresh_token": token}))
+                .send()
+        };
+
+        let response = refresh(&first["refresh_token"]).await.unwrap();
+        assert_eq!(response.status(), 200);
+        let second: serde_json::Value = response.json().await.unwrap();
+        assert_ne!(second["refresh_token"], first["refresh_token"]);
+        let me = client.get(format!("{}/me", base)).bearer_auth(second["token"].as_str().unwrap()).send().await.unwrap();
+        assert_eq!(me.status(), 200);
+
+        // Replaying the used token kills the rotated one and other devices too
+        assert_eq!(refresh(&first["refresh_token"]).await.unwrap().status(), 401);
+        assert_eq!(refresh(&second["refresh_token"]).await.unwrap().status(), 401);
+        assert_eq!(refresh(&other_device["refresh_token"]).await.unwrap().status(), 401);
+    }
+
+    #[tokio::test]
+    async fn logout_revokes_the_refresh_token_and_clears_cookies() {
+        let state = AppState::for_tests().await;
+        state.db.create_user("satoshi", "satoshi@example.com", "hunter2").await.unwrap();
+        let base = serve(state).await;
+        let client = reqwest::Client::new();
+
+        let session = login(&client, &base).await;
+        let response = client
+            .post(format!("{}/logout", base))
+            .bearer_auth(session["token"].as_str().unwrap())
+            .header("Cookie", format!("refresh_token={}", session["refresh_token"].as_str().unwrap()))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        let cookies: Vec<_> = response.headers().get_all("set-cookie").iter().map(|v| v.to_str().unwrap().to_string()).collect();
+        assert!(cookies.iter().all(|cookie| cookie.contains("Max-Age=0")));
+
+        let response = client
+            .post(format!("{}/refresh", base))
+            .json(&json!({"refresh_token": session["refresh_token"]}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 401);
+
+        // Nothing left to end, but the cookies are still cleared
+        assert_eq!(client.post(format!("{}/logout", base)).send().await.unwrap().status(), 200);
+    }
+
+    #[tokio::test]
+    async fn logout_without_a_valid_access_token_still_revokes_the_refresh_token() {
+        let state = AppState::for_tests().await;
+        state.db.create_user("satoshi", "satoshi@example.com", "hunter2").await.unwrap();
+        let expired = Claims {
+            sub: "satoshi".to_string(),
+            exp: (Utc::now() - Duration::hours(1)).timestamp() as usize,
+            iat: (Utc::now() - Duration::hours(2)).timestamp() as usize,
+            jti: random_token(16),
+        };
+        let expired = encode(&Header::default(), &expired, &state.jwt.encoding).unwrap();
+        let base = serve(state).await;
+        let client = reqwest::Client::new();
+
+        for access_token in [Some(expired), None] {
+            let session = login(&client, &base).await;
+            let refresh_token = session["refresh_token"].as_str().unwrap();
+            let mut cookie = format!("refresh_token={}", refresh_token);
+            if let Some(access_token) = &access_token {
+                cookie.push_str(&format!("; token={}", access_token));
             }
+
+            let response = client.post(format!("{}/logout", base)).header("Cookie", cookie).send().await.unwrap();
+            assert_eq!(response.status(), 200);
+            let cookies: Vec<_> = response.headers().get_all("set-cookie").iter().map(|v| v.to_str().unwrap().to_string()).collect();
+            assert!(cookies.iter().any(|cookie| cookie.starts_with("refresh_token=;") && cookie.contains("Max-Age=0")));
+
+            let response = client
+                .post(format!("{}/refresh", base))
+                .json(&json!({"refresh_token": refresh_token}))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(response.status(), 401);
         }
-    });
+    }
 
-    recv_task.await.unwrap();
-}
+    #[tokio::test]
+    async fn logout_revokes_the_access_token() {
+        let state = AppState::for_tests().await;
+        state.db.create_user("satoshi", "satoshi@example.com", "hunter2").await.unwrap();
+        let base = serve(state).await;
+        let client = reqwest::Client::new();
 
-pub fn format_news_html(coin: &str, news: &[api::NewsItem]) -> String {
-    let news_items: String = news.iter().map(|item| {
-        let sentiment_class = match item.sentiment.as_str() {
-            "Positive" => "positive",
-            "Negative" => "negative",
-            _ => "neutral"
-        };
+        let session = login(&client, &base).await;
+        let token = session["token"].as_str().unwrap();
+        let me = || client.get(format!("{}/me", base)).bearer_auth(token).send();
+        assert_eq!(me().await.unwrap().status(), 200);
+
+        let response = client.post(format!("{}/logout", base)).bearer_auth(token).send().await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(me().await.unwrap().status(), 401);
+    }
 
-        let sentiment_emoji = match item.sentiment.as_str() {
-            "Positive" => "🟢",
-            "Negative" => "🔴",
-            _ => "⚪"
+    #[tokio::test]
+    async fn logout_all_revokes_every_earlier_access_token() {
+        let state = AppState::for_tests().await;
+        state.db.create_user("satoshi", "satoshi@example.com", "hunter2").await.unwrap();
+        // Another device's session, signed in a minute ago
+        let claims = Claims {
+            sub: "satoshi".to_string(),
+            exp: (Utc::now() + Duration::minutes(14)).timestamp() as usize,
+            iat: (Utc::now() - Duration::minutes(1)).timestamp() as usize,
+            jti: random_token(16),
         };
+        let other = encode(&Header::default(), &claims, &state.jwt.encoding).unwrap();
+        let base = serve(state.clone()).await;
+        let client = reqwest::Client::new();
+        let me = |token: String| client.get(format!("{}/me", base)).bearer_auth(token).send();
 
-        format!(r#"
-            <div class="news-item">
-                <h3>{} {}</h3>
-                <div class="news-meta">
-                    <span class="source">Source: {}</span>
-                    <span class="date">Date: {}</span>
-                    <span class="sentiment {}">Sentiment: {}</span>
-                </div>
-                <p class="summary">{}</p>
-                <a href="{}" target="_blank" class="read-more">Read more</a>
-            </div>
-        "#,
-            sentiment_emoji,
-            item.title,
-            item.source,
-            item.published_at.format("%Y-%m-%d %H:%M").to_string(),
-            sentiment_class,
-            item.sentiment,
-            item.summary,
-            item.url
-        )
-    }).collect();
+        let session = login(&client, &base).await;
+        let token = session["token"].as_str().unwrap().to_string();
+        assert_eq!(me(other.clone()).await.unwrap().status(), 200);
 
-    format!(r#"
-        <html>
-            <head>
-                <title>News for {}</title>
-                <style>
-                    body {{ font-family: sans-serif; padding: 2em; background: #f9f9f9; }}
-                    .nav-container {{
-                        display: flex;
-                        justify-content: space-between;
-                        align-items: center;
-                        padding: 10px 20px;
-                        background-color: #333;
-                        color: white;
-                        border-radius: 4px;
-                        margin-bottom: 20px;
-                    }}
-                    .nav-title {{
-                        font-size: 1.2em;
-                        font-weight: bold;
-                    }}
-                    .nav-buttons {{
-                        display: flex;
-                        gap: 10px;
-                    }}
-                    .nav-button {{
-                        padding: 8px 15px;
-                        background-color: #4CAF50;
-                        color: white;
-                        border: none;
-                        border-radius: 4px;
-                        cursor: pointer;
-                        text-decoration: none;
-                        font-size: 14px;
-                    }}
-                    .nav-button:hover {{ background-color: #45a049; }}
-                    .news-item {{ 
-                        background: white; 
-                        padding: 1.5em; 
-                        margin: 1em 0; 
-                        border-radius: 8px;
-                        box-shadow: 0 2px 4px rgba(0,0,0,0.1);
-                    }}
-                    .news-meta {{
-                        color: #666;
-                        font-size: 0.9em;
-                        margin: 0.5em 0;
-                    }}
-                    .source, .date, .sentiment {{
-                        margin-right: 1em;
-                    }}
-                    .sentiment.positive {{ color: #2ecc71; }}
-                    .sentiment.negative {{ color: #e74c3c; }}
-                    .sentiment.neutral {{ color: #7f8c8d; }}
-                    .summary {{
-                        margin: 1em 0;
-                        line-height: 1.6;
-                    }}
-                    .read-more {{
-                        display: inline-block;
-                        padding: 0.5em 1em;
-                        background: #3498db;
-                        color: white;
-                        text-decoration: none;
-                        border-radius: 4px;
-                        margin-top: 1em;
-                    }}
-                    .read-more:hover {{
-                        background: #2980b9;
-                    }}
-                </style>
-            </head>
-            <body>
-                <div class="nav-container">
-                    <div class="nav-title">Crypto News</div>
-                    <div class="nav-buttons" id="authButtons">
-                        <a href="/register" class="nav-button">Register</a>
-                        <a href="/login" class="nav-button">Login</a>
-                    </div>
-                </div>
-                <h1>News for {}</h1>
-                <div class="news-items">
-                    {}
-                </div>
-                <br>
-                <a href='/' class="read-more">Back to homepage</a>
-                <script>
-                    // Check authentication status
-                    const token = localStorage.getItem('token');
-                    const authButtons = document.getElementById('authButtons');
-                    
-                    if (token) {{
-                        authButtons.innerHTML = `
-                            <span class="nav-button" style="background-color: #666;">Welcome!</span>
-                            <button class="nav-button" onclick="logout()">Logout</button>
-                        `;
-                    }}
-                    
-                    function logout() {{
-                        localStorage.removeItem('token');
-                        window.location.reload();
-                    }}
-                </script>
-            </body>
-        </html>
-    "#, coin, coin, news_items)
-} 
\ No newline at end of file
+        let response = client.post(format!("{}/logout-all", base)).bearer_auth(&token).send().await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(me(token).await.unwrap().status(), 401);
+        assert_eq!(me(other).await.unwrap().status(), 401);
+        let response = client
+            .post(format!("{}/refresh", base))
+            .json(&json!({"refresh_token": session["refresh_token"]}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 401);
+
+        // A token issued right after, within the same second, is not caught
+        let fresh = state.jwt.create_token("satoshi").unwrap();
+        assert_eq!(me(fresh).await.unwrap().status(), 200);
+    }
+
+}
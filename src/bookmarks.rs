@@ -0,0 +1,445 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Redirect, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use crate::auth::{AuthUser, ErrorResponse};
+use crate::db::{Bookmark, BookmarkUpdate};
+use crate::routes::{escape_href, escape_html};
+use crate::AppState;
+
+/// Identifies a stored article by id or by URL.
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ArticleKey {
+    pub id: Option<i64>,
+    pub url: Option<String>,
+}
+
+/// Body of `POST /bookmarks`. `note` and `tags` are optional; leaving one out
+/// keeps the bookmark's current value.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SaveBookmarkRequest {
+    #[serde(flatten)]
+    pub article: ArticleKey,
+    pub note: Option<String>,
+    pub tags: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct BookmarkQuery {
+    /// Only bookmarks carrying this tag.
+    pub tag: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BookmarkList {
+    pub bookmarks: Vec<Bookmark>,
+}
+
+fn error(status: StatusCode, message: &str) -> Response {
+    (status, Json(ErrorResponse { error: message.to_string() })).into_response()
+}
+
+fn storage_error(e: String) -> Response {
+    tracing::error!("Bookmark storage error: {}", e);
+    error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to update bookmarks")
+}
+
+/// Tags are lowercased and trimmed; entries may also be comma-separated.
+fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = tags
+        .iter()
+        .flat_map(|tag| tag.split(','))
+        .map(|tag| tag.trim().to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    normalized.sort();
+    normalized.dedup();
+    normalized
+}
+
+async fn resolve_article(state: &AppState, key: &ArticleKey) -> Result<i64, Response> {
+    if let Some(id) = key.id {
+        return Ok(id);
+    }
+    let Some(url) = key.url.as_deref().filter(|url| !url.trim().is_empty()) else {
+        return Err(error(StatusCode::BAD_REQUEST, "An article id or url is required"));
+    };
+    match state.db.find_article_id(url.trim()).await {
+        Ok(Some(id)) => Ok(id),
+        Ok(None) => Err(error(StatusCode::NOT_FOUND, "Article not found")),
+        Err(e) => Err(storage_error(e)),
+    }
+}
+
+/// Bookmarks an article, or updates the note and tags of an existing
+/// bookmark. Responds with the saved bookmark.
+#[utoipa::path(
+    post,
+    path = "/bookmarks",
+    tag = "bookmarks",
+    security(("bearer_auth" = [])),
+    request_body = SaveBookmarkRequest,
+    responses(
+        (status = 200, description = "The saved bookmark", body = Bookmark),
+        (status = 400, description = "No article id or url given", body = ErrorResponse),
+        (status = 401, description = "Not signed in", body = ErrorResponse),
+        (status = 404, description = "The article is not stored", body = ErrorResponse),
+    )
+)]
+pub async fn save_bookmark(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(request): Json<SaveBookmarkRequest>,
+) -> Response {
+    let id = match resolve_article(&state, &request.article).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+
+    let update = BookmarkUpdate {
+        note: request.note,
+        tags: request.tags.as_deref().map(normalize_tags),
+    };
+    match state.db.save_bookmark(auth.user.id, id, &update).await {
+        Ok(true) => {}
+        Ok(false) => return error(StatusCode::NOT_FOUND, "Article not found"),
+        Err(e) => return storage_error(e),
+    }
+
+    match state.db.list_bookmarks(auth.user.id, None).await {
+        Ok(bookmarks) => match bookmarks.into_iter().find(|bookmark| bookmark.article.id == id) {
+            Some(bookmark) => Json(bookmark).into_response(),
+            None => error(StatusCode::NOT_FOUND, "Article not found"),
+        },
+        Err(e) => storage_error(e),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/bookmarks",
+    tag = "bookmarks",
+    security(("bearer_auth" = [])),
+    params(ArticleKey),
+    responses(
+        (status = 204, description = "Bookmark removed"),
+        (status = 400, description = "No article id or url given", body = ErrorResponse),
+        (status = 401, description = "Not signed in", body = ErrorResponse),
+        (status = 404, description = "The article is not bookmarked", body = ErrorResponse),
+    )
+)]
+pub async fn delete_bookmark(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Query(key): Query<ArticleKey>,
+) -> Response {
+    let id = match resolve_article(&state, &key).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+
+    match state.db.delete_bookmark(auth.user.id, id).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => error(StatusCode::NOT_FOUND, "Article is not bookmarked"),
+        Err(e) => storage_error(e),
+    }
+}
+
+/// JSON listing of the user's bookmarks, optionally filtered by `?tag=`.
+#[utoipa::path(
+    get,
+    path = "/bookmarks.json",
+    tag = "bookmarks",
+    security(("bearer_auth" = [])),
+    params(BookmarkQuery),
+    responses(
+        (status = 200, description = "The user's bookmarks, newest first", body = BookmarkList),
+        (status = 401, description = "Not signed in", body = ErrorResponse),
+    )
+)]
+pub async fn list_bookmarks(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Query(query): Query<BookmarkQuery>,
+) -> Response {
+    let tag = query.tag.map(|tag| tag.trim().to_lowercase()).filter(|tag| !tag.is_empty());
+    match state.db.list_bookmarks(auth.user.id, tag.as_deref()).await {
+        Ok(bookmarks) => Json(BookmarkList { bookmarks }).into_response(),
+        Err(e) => storage_error(e),
+    }
+}
+
+/// The read-later page. Anonymous visitors are sent to the login page.
+pub async fn bookmarks_page(
+    State(state): State<AppState>,
+    auth: Option<AuthUser>,
+    Query(query): Query<BookmarkQuery>,
+) -> Response {
+    let Some(AuthUser { user, .. }) = auth else {
+        return Redirect::to("/login").into_response();
+    };
+
+    let tag = query.tag.map(|tag| tag.trim().to_lowercase()).filter(|tag| !tag.is_empty());
+    match state.db.list_bookmarks(user.id, tag.as_deref()).await {
+        Ok(bookmarks) => Html(format_bookmarks_html(&bookmarks, tag.as_deref())).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to load bookmarks for {}: {}", user.username, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Html("Failed to load bookmarks".to_string())).into_response()
+        }
+    }
+}
+
+fn format_bookmarks_html(bookmarks: &[Bookmark], tag: Option<&str>) -> String {
+    let items_html = if bookmarks.is_empty() {
+        match tag {
+            Some(_) => "<p>No bookmarks with this tag.</p>".to_string(),
+            None => "<p>No bookmarks yet. Use the Bookmark button on search results to save articles here.</p>".to_string(),
+        }
+    } else {
+        bookmarks
+            .iter()
+            .map(|bookmark| {
+                let item = &bookmark.article.news;
+                let tags_html = bookmark
+                    .tags
+                    .iter()
+                    .map(|tag| {
+                        format!(
+                            r#"<a class="tag" href="/bookmarks?tag={}">{}</a>"#,
+                            escape_html(&urlencoding::encode(tag)),
+                            escape_html(tag)
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                format!(
+                    r#"
+                    <div class="bookmark" data-id="{}">
+                        <h3><a href="{}" target="_blank">{}</a></h3>
+                        <div class="bookmark-meta">{} &middot; {} &middot; saved {}</div>
+                        <div class="bookmark-tags">{}</div>
+                        <textarea class="bookmark-note" placeholder="Notes">{}</textarea>
+                        <input type="text" class="bookmark-tag-input" placeholder="Tags, comma separated" value="{}">
+                        <div class="bookmark-actions">
+                            <button onclick="saveBookmark(this)">Save</button>
+                            <button class="remove" onclick="removeBookmark(this)">Remove</button>
+                        </div>
+                    </div>
+                    "#,
+                    bookmark.article.id,
+                    escape_href(&item.url),
+                    escape_html(&item.title),
+                    escape_html(&item.source),
+                    item.published_at.format("%d %b %Y %H:%M"),
+                    bookmark.bookmarked_at.format("%d %b %Y"),
+                    tags_html,
+                    escape_html(bookmark.note.as_deref().unwrap_or("")),
+                    escape_html(&bookmark.tags.join(", "))
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    let filter_html = match tag {
+        Some(tag) => format!(
+            r#"<p>Tagged <strong>{}</strong> &middot; <a href="/bookmarks">Show all</a></p>"#,
+            escape_html(tag)
+        ),
+        None => String::new(),
+    };
+
+    format!(
+        r#"
+        <!DOCTYPE html>
+        <html>
+        <head>
+            <title>Bookmarks - Crypto News</title>
+            <style>
+                body {{ font-family: Arial, sans-serif; max-width: 800px; margin: 0 auto; padding: 20px; }}
+                .nav-container {{
+                    display: flex;
+                    justify-content: space-between;
+                    align-items: center;
+                    padding: 10px 20px;
+                    background-color: #333;
+                    color: white;
+                    border-radius: 4px;
+                    margin-bottom: 20px;
+                }}
+                .nav-title {{ font-size: 1.2em; font-weight: bold; }}
+                .nav-button {{
+                    padding: 8px 15px;
+                    background-color: #4CAF50;
+                    color: white;
+                    border-radius: 4px;
+                    text-decoration: none;
+                    font-size: 14px;
+                }}
+                .bookmark {{
+                    margin: 15px 0;
+                    padding: 15px;
+                    background-color: #f9f9f9;
+                    border-radius: 4px;
+                    box-shadow: 0 1px 3px rgba(0,0,0,0.1);
+                }}
+                .bookmark h3 {{ margin: 0 0 5px 0; }}
+                .bookmark-meta {{ color: #666; font-size: 0.85em; margin-bottom: 5px; }}
+                .tag {{
+                    display: inline-block;
+                    margin: 2px;
+                    padding: 2px 8px;
+                    background-color: #e8f5e9;
+                    border-radius: 10px;
+                    font-size: 0.85em;
+                    color: #2e7d32;
+                    text-decoration: none;
+                }}
+                .bookmark-note, .bookmark-tag-input {{
+                    width: 100%;
+                    margin-top: 8px;
+                    padding: 8px;
+                    border: 1px solid #ddd;
+                    border-radius: 4px;
+                    box-sizing: border-box;
+                    font-family: inherit;
+                }}
+                .bookmark-actions {{ margin-top: 8px; display: flex; gap: 8px; }}
+                button {{
+                    padding: 6px 14px;
+                    background-color: #4CAF50;
+                    color: white;
+                    border: none;
+                    border-radius: 4px;
+                    cursor: pointer;
+                }}
+                button.remove {{ background-color: #e74c3c; }}
+            </style>
+        </head>
+        <body>
+            <div class="nav-container">
+                <div class="nav-title">Crypto News</div>
+                <a href="/" class="nav-button">Search</a>
+            </div>
+            <h1>Bookmarks</h1>
+            {}
+            {}
+            <script>
+                async function saveBookmark(button) {{
+                    const card = button.closest('.bookmark');
+                    await fetch('/bookmarks', {{
+                        method: 'POST',
+                        headers: {{ 'Content-Type': 'application/json' }},
+                        body: JSON.stringify({{
+                            id: Number(card.dataset.id),
+                            note: card.querySelector('.bookmark-note').value,
+                            tags: [card.querySelector('.bookmark-tag-input').value]
+                        }})
+                    }});
+                    window.location.reload();
+                }}
+
+                async function removeBookmark(button) {{
+                    const card = button.closest('.bookmark');
+                    await fetch('/bookmarks?id=' + card.dataset.id, {{ method: 'DELETE' }});
+                    card.remove();
+                }}
+            </script>
+        </body>
+        </html>
+        "#,
+        filter_html, items_html
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::tests::news_item;
+    use axum::{routing::get, Router};
+    use serde_json::json;
+
+    #[test]
+    fn normalize_tags_splits_lowercases_and_dedupes() {
+        let tags = vec!["Macro, ETF ".to_string(), "etf".to_string(), " ".to_string()];
+        assert_eq!(normalize_tags(&tags), vec!["etf", "macro"]);
+    }
+
+    #[test]
+    fn tag_links_are_percent_encoded() {
+        let bookmark = Bookmark {
+            article: crate::db::StoredArticle {
+                id: 1,
+                news: news_item("https://a", 1, "Neutral", "NewsData.io"),
+                first_seen_at: chrono::Utc::now(),
+            },
+            note: None,
+            tags: vec!["r&d #2+".to_string()],
+            bookmarked_at: chrono::Utc::now(),
+        };
+        let html = format_bookmarks_html(&[bookmark], None);
+        assert!(html.contains(r#"href="/bookmarks?tag=r%26d%20%232%2B">r&amp;d #2+</a>"#), "{}", html);
+    }
+
+    #[tokio::test]
+    async fn bookmarks_are_saved_by_url_or_id_and_listed_by_tag() {
+        let state = AppState::for_tests().await;
+        state.db.create_user("satoshi", "satoshi@example.com", "hunter2").await.unwrap();
+        state.db.store_news_items("btc", &[
+            news_item("https://a", 1, "Neutral", "NewsData.io"),
+            news_item("https://b", 2, "Neutral", "NewsData.io"),
+        ]).await.unwrap();
+        let token = state.jwt.create_token("satoshi").unwrap();
+
+        let app = Router::new()
+            .route("/bookmarks", get(bookmarks_page).post(save_bookmark).delete(delete_bookmark))
+            .route("/bookmarks.json", get(list_bookmarks))
+            .with_state(state);
+        let base = crate::spawn_app(app).await;
+        let client = reqwest::Client::new();
+        let save = |body: serde_json::Value| {
+            client.post(format!("{}/bookmarks", base)).bearer_auth(&token).json(&body).send()
+        };
+
+        let response = save(json!({"url": "https://a", "note": "Check ETF flows", "tags": ["ETF", "macro"]})).await.unwrap();
+        assert_eq!(response.status(), 200);
+        let saved: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(saved["note"], "Check ETF flows");
+        assert_eq!(saved["tags"], json!(["etf", "macro"]));
+        let id = saved["id"].as_i64().unwrap();
+
+        // Saving again without a note keeps it; tags are replaced when given
+        let saved: serde_json::Value = save(json!({"id": id, "tags": ["follow-up"]})).await.unwrap().json().await.unwrap();
+        assert_eq!(saved["note"], "Check ETF flows");
+        assert_eq!(saved["tags"], json!(["follow-up"]));
+
+        assert_eq!(save(json!({"url": "https://b"})).await.unwrap().status(), 200);
+        assert_eq!(save(json!({"url": "https://missing"})).await.unwrap().status(), 404);
+        assert_eq!(save(json!({"note": "no article"})).await.unwrap().status(), 400);
+
+        let list = |query: &str| {
+            client.get(format!("{}/bookmarks.json{}", base, query)).bearer_auth(&token).send()
+        };
+        let all: serde_json::Value = list("").await.unwrap().json().await.unwrap();
+        assert_eq!(all["bookmarks"].as_array().unwrap().len(), 2);
+        let tagged: serde_json::Value = list("?tag=Follow-Up").await.unwrap().json().await.unwrap();
+        assert_eq!(tagged["bookmarks"].as_array().unwrap().len(), 1);
+        assert_eq!(tagged["bookmarks"][0]["url"], "https://a");
+
+        let page = client.get(format!("{}/bookmarks", base)).bearer_auth(&token).send().await.unwrap().text().await.unwrap();
+        assert!(page.contains("Check ETF flows"));
+
+        let delete = client.delete(format!("{}/bookmarks?url=https://b", base)).bearer_auth(&token).send().await.unwrap();
+        assert_eq!(delete.status(), 204);
+        let all: serde_json::Value = list("").await.unwrap().json().await.unwrap();
+        assert_eq!(all["bookmarks"].as_array().unwrap().len(), 1);
+
+        assert_eq!(client.get(format!("{}/bookmarks.json", base)).send().await.unwrap().status(), 401);
+    }
+}
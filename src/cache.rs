@@ -1,126 +1,613 @@
+use async_trait::async_trait;
+use redis::aio::ConnectionManager;
 use redis::Client;
-use std::collections::HashMap;
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+use crate::config::Config;
+use crate::metrics::series;
+use prometheus::core::Collector;
+use prometheus::proto::MetricFamily;
+use prometheus::{IntCounterVec, Opts};
 
-pub struct RedisCache {
+/// How long a value found in a lower tier is kept in the tiers above it.
+/// Its remaining TTL below is unknown, so this stays short.
+const BACKFILL_TTL_SECS: u64 = 60;
+/// Most members a sorted set keeps; the lowest scored go first. Members are
+/// search terms, so without a bound any client could grow a set forever.
+const MAX_SET_MEMBERS: usize = 1000;
+/// Longest a Redis call may take, connecting included. A server that stops
+/// answering would otherwise hold up every request reading the cache; past
+/// this the call fails and the tier is skipped.
+const REDIS_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Where cached values live. Errors mean the store could not be reached;
+/// `Cache` treats them as a miss and moves on to the next tier.
+#[async_trait]
+pub trait CacheBackend: Send + Sync {
+    /// Shown on `/stats`.
+    fn name(&self) -> &'static str;
+
+    async fn get(&self, key: &str) -> Result<Option<String>, String>;
+
+    async fn set(&self, key: &str, value: &str, ttl_seconds: u64) -> Result<(), String>;
+
+    /// Adds `by` to `member`'s score in the sorted set `key`, which expires
+    /// `ttl_seconds` after its last update when given. Only the
+    /// `MAX_SET_MEMBERS` highest scored members are kept.
+    async fn incr_score(&self, key: &str, member: &str, by: f64, ttl_seconds: Option<u64>) -> Result<(), String>;
+
+    /// The `count` highest scored members of `key`, highest first.
+    async fn top_scores(&self, key: &str, count: usize) -> Result<Vec<(String, f64)>, String>;
+
+    /// Every member and score of each sorted set in `keys`, in one round trip.
+    async fn all_scores(&self, keys: &[String]) -> Result<Vec<Vec<(String, f64)>>, String>;
+
+    async fn stats(&self) -> Result<BackendStats, String>;
+
+    /// Checks that the store answers, for readiness checks.
+    async fn ping(&self) -> Result<(), String>;
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BackendStats {
+    pub total_keys: usize,
+    pub memory_used: usize,
+}
+
+pub struct RedisBackend {
     client: Client,
-    hits: Arc<AtomicU64>,
-    misses: Arc<AtomicU64>,
+    /// One multiplexed connection shared by every request. It is opened on
+    /// first use and reconnects by itself after Redis goes away.
+    connection: OnceCell<ConnectionManager>,
+}
+
+impl RedisBackend {
+    pub fn new(url: &str) -> Result<Self, String> {
+        let client = Client::open(url).map_err(|e| format!("Invalid Redis URL {}: {}", url, e))?;
+        Ok(RedisBackend { client, connection: OnceCell::new() })
+    }
+
+    /// The shared connection. A failed first connect is retried by the next
+    /// call.
+    async fn connection(&self) -> Result<ConnectionManager, String> {
+        self.connection
+            // No retries with backoff: while Redis is down every call would
+            // wait for them. A command that fails triggers a reconnect anyway.
+            .get_or_try_init(|| ConnectionManager::new_with_backoff(self.client.clone(), 2, 100, 0))
+            .await
+            .cloned()
+            .map_err(|e| format!("Redis is unavailable: {}", e))
+    }
+}
+
+/// Fails `call`, a Redis `command`, once it runs past `REDIS_TIMEOUT`.
+async fn timed<T>(command: &str, call: impl Future<Output = Result<T, String>>) -> Result<T, String> {
+    tokio::time::timeout(REDIS_TIMEOUT, call)
+        .await
+        .map_err(|_| format!("Redis {} timed out after {:?}", command, REDIS_TIMEOUT))?
+}
+
+#[async_trait]
+impl CacheBackend for RedisBackend {
+    fn name(&self) -> &'static str {
+        "redis"
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<String>, String> {
+        timed("GET", async {
+            let mut con = self.connection().await?;
+            redis::cmd("GET")
+                .arg(key)
+                .query_async::<_, Option<String>>(&mut con)
+                .await
+                .map_err(|e| format!("Redis GET failed: {}", e))
+        })
+        .await
+    }
+
+    async fn set(&self, key: &str, value: &str, ttl_seconds: u64) -> Result<(), String> {
+        timed("SET", async {
+            let mut con = self.connection().await?;
+            redis::cmd("SET")
+                .arg(key)
+                .arg(value)
+                .arg("EX")
+                .arg(ttl_seconds.max(1))
+                .query_async::<_, ()>(&mut con)
+                .await
+                .map_err(|e| format!("Redis SET failed: {}", e))
+        })
+        .await
+    }
+
+    async fn incr_score(&self, key: &str, member: &str, by: f64, ttl_seconds: Option<u64>) -> Result<(), String> {
+        timed("ZINCRBY", async {
+            let mut con = self.connection().await?;
+            let mut pipe = redis::pipe();
+            pipe.cmd("ZINCRBY").arg(key).arg(by).arg(member).ignore();
+            pipe.cmd("ZREMRANGEBYRANK").arg(key).arg(0).arg(-(MAX_SET_MEMBERS as i64) - 1).ignore();
+            if let Some(ttl_seconds) = ttl_seconds {
+                pipe.cmd("EXPIRE").arg(key).arg(ttl_seconds.max(1)).ignore();
+            }
+            pipe.query_async::<_, ()>(&mut con)
+                .await
+                .map_err(|e| format!("Redis ZINCRBY failed: {}", e))
+        })
+        .await
+    }
+
+    async fn top_scores(&self, key: &str, count: usize) -> Result<Vec<(String, f64)>, String> {
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+        timed("ZREVRANGE", async {
+            let mut con = self.connection().await?;
+            redis::cmd("ZREVRANGE")
+                .arg(key)
+                .arg(0)
+                .arg(count - 1)
+                .arg("WITHSCORES")
+                .query_async::<_, Vec<(String, f64)>>(&mut con)
+                .await
+                .map_err(|e| format!("Redis ZREVRANGE failed: {}", e))
+        })
+        .await
+    }
+
+    async fn all_scores(&self, keys: &[String]) -> Result<Vec<Vec<(String, f64)>>, String> {
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+        timed("ZRANGE", async {
+            let mut con = self.connection().await?;
+            let mut pipe = redis::pipe();
+            for key in keys {
+                pipe.cmd("ZRANGE").arg(key).arg(0).arg(-1).arg("WITHSCORES");
+            }
+            pipe.query_async::<_, Vec<Vec<(String, f64)>>>(&mut con)
+                .await
+                .map_err(|e| format!("Redis ZRANGE failed: {}", e))
+        })
+        .await
+    }
+
+    async fn stats(&self) -> Result<BackendStats, String> {
+        timed("DBSIZE", async {
+            let mut con = self.connection().await?;
+
+            let total_keys: usize = redis::cmd("DBSIZE")
+                .query_async::<_, usize>(&mut con)
+                .await
+                .map_err(|e| format!("Redis DBSIZE failed: {}", e))?;
+
+            let info: HashMap<String, String> = redis::cmd("INFO")
+                .arg("memory")
+                .query_async::<_, HashMap<String, String>>(&mut con)
+                .await
+                .unwrap_or_default();
+
+            let memory_used = info
+                .get("used_memory")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0);
+
+            Ok(BackendStats { total_keys, memory_used })
+        })
+        .await
+    }
+
+    async fn ping(&self) -> Result<(), String> {
+        timed("PING", async {
+            let mut con = self.connection().await?;
+            redis::cmd("PING")
+                .query_async::<_, String>(&mut con)
+                .await
+                .map(|_| ())
+                .map_err(|e| format!("Redis PING failed: {}", e))
+        })
+        .await
+    }
+}
+
+/// An in-process store holding at most `capacity` values, evicting the
+/// least recently used one first. Sorted sets go when they expire and are
+/// trimmed to `MAX_SET_MEMBERS` like in Redis.
+pub struct MemoryBackend {
+    capacity: usize,
+    store: Mutex<MemoryStore>,
+}
+
+#[derive(Default)]
+struct MemoryStore {
+    entries: HashMap<String, MemoryEntry>,
+    /// Keys by the tick they were last used at, oldest first.
+    recency: BTreeMap<u64, String>,
+    tick: u64,
+    sorted_sets: HashMap<String, SortedSet>,
+}
+
+#[derive(Default)]
+struct SortedSet {
+    scores: HashMap<String, f64>,
+    expires_at: Option<Instant>,
+}
+
+impl SortedSet {
+    fn is_live(&self, now: Instant) -> bool {
+        self.expires_at.is_none_or(|expires_at| expires_at > now)
+    }
+}
+
+struct MemoryEntry {
+    value: String,
+    expires_at: Instant,
+    used_at: u64,
+}
+
+impl MemoryStore {
+    fn touch(&mut self, key: &str) {
+        self.tick += 1;
+        let tick = self.tick;
+        if let Some(entry) = self.entries.get_mut(key) {
+            self.recency.remove(&entry.used_at);
+            entry.used_at = tick;
+            self.recency.insert(tick, key.to_string());
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.recency.remove(&entry.used_at);
+        }
+    }
+
+    fn remove_expired(&mut self, now: Instant) {
+        let expired: Vec<String> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.expires_at <= now)
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in expired {
+            self.remove(&key);
+        }
+        self.sorted_sets.retain(|_, set| set.is_live(now));
+    }
+
+    fn live_scores(&self, key: &str) -> Vec<(String, f64)> {
+        self.sorted_sets
+            .get(key)
+            .filter(|set| set.is_live(Instant::now()))
+            .map(|set| set.scores.iter().map(|(member, score)| (member.clone(), *score)).collect())
+            .unwrap_or_default()
+    }
+}
+
+impl MemoryBackend {
+    pub fn new(capacity: usize) -> Self {
+        MemoryBackend { capacity: capacity.max(1), store: Mutex::new(MemoryStore::default()) }
+    }
+}
+
+#[async_trait]
+impl CacheBackend for MemoryBackend {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<String>, String> {
+        let mut store = self.store.lock().unwrap();
+        let expired = match store.entries.get(key) {
+            None => return Ok(None),
+            Some(entry) => entry.expires_at <= Instant::now(),
+        };
+        if expired {
+            store.remove(key);
+            return Ok(None);
+        }
+        store.touch(key);
+        Ok(store.entries.get(key).map(|entry| entry.value.clone()))
+    }
+
+    async fn set(&self, key: &str, value: &str, ttl_seconds: u64) -> Result<(), String> {
+        let mut store = self.store.lock().unwrap();
+        store.remove(key);
+        let entry = MemoryEntry {
+            value: value.to_string(),
+            expires_at: Instant::now() + Duration::from_secs(ttl_seconds),
+            used_at: 0,
+        };
+        store.entries.insert(key.to_string(), entry);
+        store.touch(key);
+
+        if store.entries.len() > self.capacity {
+            store.remove_expired(Instant::now());
+        }
+        while store.entries.len() > self.capacity {
+            let Some((_, oldest)) = store.recency.pop_first() else {
+                break;
+            };
+            store.entries.remove(&oldest);
+        }
+        Ok(())
+    }
+
+    async fn incr_score(&self, key: &str, member: &str, by: f64, ttl_seconds: Option<u64>) -> Result<(), String> {
+        let now = Instant::now();
+        let mut store = self.store.lock().unwrap();
+        let set = store.sorted_sets.entry(key.to_string()).or_default();
+        if !set.is_live(now) {
+            *set = SortedSet::default();
+        }
+        *set.scores.entry(member.to_string()).or_insert(0.0) += by;
+        set.expires_at = ttl_seconds.map(|ttl_seconds| now + Duration::from_secs(ttl_seconds));
+        while set.scores.len() > MAX_SET_MEMBERS {
+            // The lowest rank in Redis: lowest score, then lowest member
+            let lowest = set
+                .scores
+                .iter()
+                .min_by(|a, b| a.1.total_cmp(b.1).then_with(|| a.0.cmp(b.0)))
+                .map(|(member, _)| member.clone());
+            match lowest {
+                Some(lowest) => set.scores.remove(&lowest),
+                None => break,
+            };
+        }
+        Ok(())
+    }
+
+    async fn top_scores(&self, key: &str, count: usize) -> Result<Vec<(String, f64)>, String> {
+        let store = self.store.lock().unwrap();
+        let mut scores = store.live_scores(key);
+        // Ties are ordered like ZREVRANGE: by member, descending
+        scores.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| b.0.cmp(&a.0)));
+        scores.truncate(count);
+        Ok(scores)
+    }
+
+    async fn all_scores(&self, keys: &[String]) -> Result<Vec<Vec<(String, f64)>>, String> {
+        let store = self.store.lock().unwrap();
+        Ok(keys.iter().map(|key| store.live_scores(key)).collect())
+    }
+
+    async fn stats(&self) -> Result<BackendStats, String> {
+        let mut store = self.store.lock().unwrap();
+        store.remove_expired(Instant::now());
+
+        let entries: usize = store.entries.iter().map(|(key, entry)| key.len() + entry.value.len()).sum();
+        let sets: usize = store
+            .sorted_sets
+            .iter()
+            .map(|(key, set)| key.len() + set.scores.keys().map(|member| member.len() + 8).sum::<usize>())
+            .sum();
+        Ok(BackendStats {
+            total_keys: store.entries.len() + store.sorted_sets.len(),
+            memory_used: entries + sets,
+        })
+    }
+
+    async fn ping(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// The application cache: one or more backends tried in order, fastest
+/// first. Values are written to every tier; reads stop at the first tier
+/// that has the key and copy it into the tiers above. A tier that cannot be
+/// reached is skipped, so with a memory tier in front the cache keeps
+/// working while Redis is down.
+pub struct Cache {
+    tiers: Vec<Box<dyn CacheBackend>>,
+    /// Reads by the tier that answered (1 is the first), its backend and the
+    /// key's prefix, e.g. `news`.
+    hits: IntCounterVec,
+    misses: IntCounterVec,
+}
+
+/// The part of a key before its first `:`, which names what is cached.
+fn key_prefix(key: &str) -> &str {
+    key.split(':').next().unwrap_or(key)
+}
+
+/// A value written with `set_with_soft_ttl`. It is `Stale` once its soft TTL
+/// has passed and disappears at the hard TTL.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Lookup {
+    Fresh(String),
+    Stale(String),
+}
+
+impl Lookup {
+    pub fn into_value(self) -> String {
+        match self {
+            Lookup::Fresh(value) | Lookup::Stale(value) => value,
+        }
+    }
+}
+
+/// Soft-TTL entries are stored as `{fresh until, unix seconds}:{value}`.
+fn encode_entry(value: &str, fresh_until: u64) -> String {
+    format!("{}:{}", fresh_until, value)
+}
+
+fn decode_entry(entry: String, now: u64) -> Option<Lookup> {
+    let (fresh_until, value) = entry.split_once(':')?;
+    let fresh_until: u64 = fresh_until.parse().ok()?;
+    let value = value.to_string();
+    Some(if now < fresh_until { Lookup::Fresh(value) } else { Lookup::Stale(value) })
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
 }
 
 #[derive(Debug)]
 pub struct CacheStats {
-    pub total_keys: usize,
-    pub memory_used: usize,
+    pub tiers: Vec<TierStats>,
     pub hit_rate: f64,
 }
 
-impl RedisCache {
-    pub fn new() -> Self {
-        let client = Client::open("redis://127.0.0.1/").expect("Failed to connect to Redis");
-        RedisCache {
-            client,
-            hits: Arc::new(AtomicU64::new(0)),
-            misses: Arc::new(AtomicU64::new(0)),
+#[derive(Debug)]
+pub struct TierStats {
+    pub name: &'static str,
+    /// Reads this tier answered.
+    pub hits: u64,
+    /// `None` when the tier could not be reached.
+    pub backend: Option<BackendStats>,
+}
+
+impl Cache {
+    pub fn new(tiers: Vec<Box<dyn CacheBackend>>) -> Self {
+        Cache {
+            tiers,
+            hits: IntCounterVec::new(
+                Opts::new("cache_hits_total", "Cache reads answered, by tier, backend and key prefix."),
+                &["tier", "backend", "prefix"],
+            )
+            .unwrap(),
+            misses: IntCounterVec::new(
+                Opts::new("cache_misses_total", "Cache reads no tier could answer, by key prefix."),
+                &["prefix"],
+            )
+            .unwrap(),
         }
     }
 
+    /// `CACHE_BACKEND` picks the tiers: `tiered` (memory in front of Redis,
+    /// the default), `redis` or `memory`.
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let memory = || Box::new(MemoryBackend::new(config.cache_memory_entries)) as Box<dyn CacheBackend>;
+        let redis = || RedisBackend::new(&config.redis_url).map(|redis| Box::new(redis) as Box<dyn CacheBackend>);
+
+        let backends = match config.cache_backend.as_str() {
+            "tiered" => vec![memory(), redis()?],
+            "redis" => vec![redis()?],
+            "memory" => vec![memory()],
+            other => return Err(format!("Unknown CACHE_BACKEND {:?}; use tiered, redis or memory", other)),
+        };
+        Ok(Cache::new(backends))
+    }
+
     pub async fn get(&self, key: &str) -> Option<String> {
-        let mut con = self.client.get_async_connection().await.ok()?;
-        match redis::cmd("GET").arg(key).query_async::<_, Option<String>>(&mut con).await {
-            Ok(Some(value)) => {
-                self.hits.fetch_add(1, Ordering::SeqCst);
+        match self.lookup(key).await {
+            Some((tier, value)) => {
+                let level = (tier + 1).to_string();
+                self.hits.with_label_values(&[&level, self.tiers[tier].name(), key_prefix(key)]).inc();
                 Some(value)
             }
-            Ok(None) => {
-                self.misses.fetch_add(1, Ordering::SeqCst);
+            None => {
+                self.misses.with_label_values(&[key_prefix(key)]).inc();
                 None
             }
-            Err(_) => {
-                self.misses.fetch_add(1, Ordering::SeqCst);
-                None
+        }
+    }
+
+    /// Like `get`, but not counted towards the hit rate. For bookkeeping keys
+    /// such as token revocations rather than cached content.
+    pub async fn peek(&self, key: &str) -> Option<String> {
+        self.lookup(key).await.map(|(_, value)| value)
+    }
+
+    /// The value and the index of the tier it was found in.
+    async fn lookup(&self, key: &str) -> Option<(usize, String)> {
+        for (index, tier) in self.tiers.iter().enumerate() {
+            match tier.get(key).await {
+                Ok(Some(value)) => {
+                    for above in &self.tiers[..index] {
+                        let _ = above.set(key, &value, BACKFILL_TTL_SECS).await;
+                    }
+                    return Some((index, value));
+                }
+                Ok(None) => {}
+                Err(e) => tracing::debug!("Cache tier {} skipped: {}", tier.name(), e),
             }
         }
+        None
     }
 
     pub async fn set(&self, key: &str, value: &str) -> bool {
-        let mut con = match self.client.get_async_connection().await {
-            Ok(con) => con,
-            Err(_) => return false,
-        };
-
-        redis::cmd("SET")
-            .arg(key)
-            .arg(value)
-            .arg("EX")
-            .arg(3600) // 1 hour expiration
-            .query_async::<_, ()>(&mut con)
-            .await
-            .is_ok()
+        self.set_with_ttl(key, value, 3600).await // 1 hour expiration
     }
 
-    pub async fn increment_search_count(&self, term: &str) {
-        let mut con = match self.client.get_async_connection().await {
-            Ok(con) => con,
-            Err(_) => return,
-        };
+    /// True when at least one tier stored the value.
+    pub async fn set_with_ttl(&self, key: &str, value: &str, ttl_seconds: u64) -> bool {
+        let mut stored = false;
+        for tier in &self.tiers {
+            match tier.set(key, value, ttl_seconds).await {
+                Ok(()) => stored = true,
+                Err(e) => tracing::debug!("Cache tier {} skipped: {}", tier.name(), e),
+            }
+        }
+        stored
+    }
 
-        let _: Result<(), redis::RedisError> = redis::cmd("ZINCRBY")
-            .arg("search_counts")
-            .arg(1)
-            .arg(term)
-            .query_async(&mut con)
-            .await;
+    /// Reads an entry written by `set_with_soft_ttl`. Stale entries count as
+    /// hits: the caller serves them while it refreshes.
+    pub async fn get_with_soft_ttl(&self, key: &str) -> Option<Lookup> {
+        let entry = self.get(key).await?;
+        decode_entry(entry, unix_now())
     }
 
-    pub async fn get_top_searches(&self) -> Vec<(String, u64)> {
-        let mut con = match self.client.get_async_connection().await {
-            Ok(con) => con,
-            Err(_) => return Vec::new(),
-        };
+    /// Stores `value` as fresh for `soft_ttl_seconds` and keeps it, stale,
+    /// until `hard_ttl_seconds`.
+    pub async fn set_with_soft_ttl(&self, key: &str, value: &str, soft_ttl_seconds: u64, hard_ttl_seconds: u64) -> bool {
+        let entry = encode_entry(value, unix_now() + soft_ttl_seconds);
+        self.set_with_ttl(key, &entry, hard_ttl_seconds.max(soft_ttl_seconds)).await
+    }
 
-        match redis::cmd("ZREVRANGE")
-            .arg("search_counts")
-            .arg(0)
-            .arg(4)
-            .arg("WITHSCORES")
-            .query_async::<_, Vec<(String, u64)>>(&mut con)
-            .await
-        {
-            Ok(results) => results,
-            Err(_) => Vec::new(),
+    /// Adds to a sorted set in every tier; see `CacheBackend::incr_score`.
+    pub async fn incr_score(&self, key: &str, member: &str, by: f64, ttl_seconds: Option<u64>) {
+        for tier in &self.tiers {
+            if let Err(e) = tier.incr_score(key, member, by, ttl_seconds).await {
+                tracing::debug!("Cache tier {} skipped: {}", tier.name(), e);
+            }
         }
     }
 
-    pub async fn get_stats(&self) -> CacheStats {
-        let mut con = match self.client.get_async_connection().await {
-            Ok(con) => con,
-            Err(_) => return CacheStats {
-                total_keys: 0,
-                memory_used: 0,
-                hit_rate: 0.0,
-            },
-        };
-
-        let total_keys: usize = redis::cmd("DBSIZE")
-            .query_async::<_, usize>(&mut con)
-            .await
-            .unwrap_or(0);
+    /// Scores are read from the slowest tier that answers: it is the one
+    /// shared between servers, while a memory tier only saw this server's.
+    pub async fn top_scores(&self, key: &str, count: usize) -> Vec<(String, f64)> {
+        for tier in self.tiers.iter().rev() {
+            if let Ok(scores) = tier.top_scores(key, count).await {
+                return scores;
+            }
+        }
+        Vec::new()
+    }
 
-        let info: HashMap<String, String> = redis::cmd("INFO")
-            .arg("memory")
-            .query_async::<_, HashMap<String, String>>(&mut con)
-            .await
-            .unwrap_or_default();
+    /// Every member and score of each sorted set in `keys`, from the same
+    /// tier `top_scores` reads.
+    pub async fn all_scores(&self, keys: &[String]) -> Vec<Vec<(String, f64)>> {
+        for tier in self.tiers.iter().rev() {
+            if let Ok(scores) = tier.all_scores(keys).await {
+                return scores;
+            }
+        }
+        vec![Vec::new(); keys.len()]
+    }
 
-        let memory_used = info
-            .get("used_memory")
-            .and_then(|s| s.parse().ok())
-            .unwrap_or(0);
+    pub async fn get_stats(&self) -> CacheStats {
+        let mut tiers = Vec::with_capacity(self.tiers.len());
+        for (index, tier) in self.tiers.iter().enumerate() {
+            let level = (index + 1).to_string();
+            tiers.push(TierStats {
+                name: tier.name(),
+                hits: series(&self.hits)
+                    .filter(|metric| metric.get_label().iter().any(|label| label.get_name() == "tier" && label.get_value() == level))
+                    .map(|metric| metric.get_counter().get_value() as u64)
+                    .sum(),
+                backend: tier.stats().await.ok(),
+            });
+        }
 
-        let hits = self.hits.load(Ordering::SeqCst);
-        let misses = self.misses.load(Ordering::SeqCst);
+        let hits: u64 = tiers.iter().map(|tier| tier.hits).sum();
+        let misses: u64 = series(&self.misses).map(|metric| metric.get_counter().get_value() as u64).sum();
         let total = hits + misses;
         let hit_rate = if total > 0 {
             hits as f64 / total as f64
@@ -128,10 +615,210 @@ impl RedisCache {
             0.0
         };
 
-        CacheStats {
-            total_keys,
-            memory_used,
-            hit_rate,
+        CacheStats { tiers, hit_rate }
+    }
+
+    /// Pings every tier, in lookup order.
+    pub async fn ping(&self) -> Vec<(&'static str, Result<(), String>)> {
+        let mut results = Vec::with_capacity(self.tiers.len());
+        for tier in &self.tiers {
+            results.push((tier.name(), tier.ping().await));
+        }
+        results
+    }
+
+    /// The hit and miss counters, for `/metrics`.
+    pub fn metric_families(&self) -> Vec<MetricFamily> {
+        let mut families = self.hits.collect();
+        families.extend(self.misses.collect());
+        families
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn soft_ttl_entries_turn_stale_at_the_soft_deadline() {
+        let entry = encode_entry(r#"{"items":[]}"#, 1_000);
+
+        assert_eq!(decode_entry(entry.clone(), 999), Some(Lookup::Fresh(r#"{"items":[]}"#.to_string())));
+        assert_eq!(decode_entry(entry, 1_000), Some(Lookup::Stale(r#"{"items":[]}"#.to_string())));
+        // Values written without a soft TTL are not mistaken for one
+        assert_eq!(decode_entry(r#"{"items":[]}"#.to_string(), 0), None);
+    }
+
+    #[tokio::test]
+    async fn memory_backend_evicts_least_recently_used_and_expired_values() {
+        let memory = MemoryBackend::new(2);
+        memory.set("a", "1", 60).await.unwrap();
+        memory.set("b", "2", 60).await.unwrap();
+        assert_eq!(memory.get("a").await.unwrap().as_deref(), Some("1"));
+
+        // "b" is now the least recently used
+        memory.set("c", "3", 60).await.unwrap();
+        assert_eq!(memory.get("b").await.unwrap(), None);
+        assert_eq!(memory.get("a").await.unwrap().as_deref(), Some("1"));
+        assert_eq!(memory.get("c").await.unwrap().as_deref(), Some("3"));
+
+        memory.set("a", "1", 0).await.unwrap();
+        assert_eq!(memory.get("a").await.unwrap(), None);
+        assert_eq!(memory.stats().await.unwrap().total_keys, 1);
+
+        memory.incr_score("counts", "btc", 1.0, Some(0)).await.unwrap();
+        memory.incr_score("totals", "btc", 2.0, None).await.unwrap();
+        let scores = memory.all_scores(&["counts".to_string(), "totals".to_string()]).await.unwrap();
+        assert_eq!(scores, vec![vec![], vec![("btc".to_string(), 2.0)]]);
+    }
+
+    #[tokio::test]
+    async fn memory_sorted_sets_keep_only_the_top_members() {
+        let memory = MemoryBackend::new(10);
+        memory.incr_score("counts", "btc", 5.0, None).await.unwrap();
+        for term in 0..MAX_SET_MEMBERS + 100 {
+            memory.incr_score("counts", &format!("term-{}", term), 1.0, None).await.unwrap();
+        }
+
+        let scores = memory.top_scores("counts", usize::MAX).await.unwrap();
+        assert_eq!(scores.len(), MAX_SET_MEMBERS);
+        assert_eq!(scores[0], ("btc".to_string(), 5.0));
+    }
+
+    #[tokio::test]
+    async fn tiers_backfill_and_report_where_hits_came_from() {
+        let l2 = MemoryBackend::new(100);
+        l2.set("news:btc", "cached", 60).await.unwrap();
+        let cache = Cache::new(vec![Box::new(MemoryBackend::new(100)), Box::new(l2)]);
+
+        assert_eq!(cache.get("news:btc").await.as_deref(), Some("cached"));
+        assert_eq!(cache.get("news:btc").await.as_deref(), Some("cached"));
+        assert_eq!(cache.get("news:eth").await, None);
+
+        let stats = cache.get_stats().await;
+        let hits: Vec<u64> = stats.tiers.iter().map(|tier| tier.hits).collect();
+        assert_eq!(hits, vec![1, 1]);
+        assert!((stats.hit_rate - 2.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn unreachable_redis_falls_back_to_memory() {
+        let redis = RedisBackend::new("redis://127.0.0.1:9/").unwrap();
+        let cache = Cache::new(vec![Box::new(MemoryBackend::new(100)), Box::new(redis)]);
+
+        assert!(cache.set("price:bitcoin", "42").await);
+        assert_eq!(cache.get("price:bitcoin").await.as_deref(), Some("42"));
+        assert_eq!(cache.peek("price:bitcoin").await.as_deref(), Some("42"));
+
+        for term in ["bitcoin", "ethereum", "bitcoin"] {
+            cache.incr_score("search_counts", term, 1.0, None).await;
         }
+        assert_eq!(cache.top_scores("search_counts", 5).await, vec![("bitcoin".to_string(), 2.0), ("ethereum".to_string(), 1.0)]);
+
+        let stats = cache.get_stats().await;
+        assert_eq!(stats.tiers[0].name, "memory");
+        assert!(stats.tiers[0].backend.is_some());
+        assert_eq!(stats.tiers[1].name, "redis");
+        assert!(stats.tiers[1].backend.is_none());
+    }
+
+    #[tokio::test]
+    async fn silent_redis_times_out_and_is_skipped() {
+        // Accepts connections but never answers
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let redis = RedisBackend::new(&format!("redis://{}/", listener.local_addr().unwrap())).unwrap();
+        tokio::spawn(async move {
+            let mut open = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                open.push(socket);
+            }
+        });
+        let cache = Cache::new(vec![Box::new(MemoryBackend::new(100)), Box::new(redis)]);
+
+        let started = Instant::now();
+        assert!(cache.set("price:bitcoin", "42").await);
+        assert_eq!(cache.get("price:bitcoin").await.as_deref(), Some("42"));
+        assert_eq!(cache.get("price:ethereum").await, None);
+        let pings = cache.ping().await;
+        assert!(matches!(&pings[1], ("redis", Err(e)) if e.contains("timed out")));
+        assert!(started.elapsed() < REDIS_TIMEOUT * 5);
+    }
+
+    /// Compares a connection per operation with the shared connection under
+    /// concurrent load. Needs a running Redis:
+    /// `REDIS_URL=redis://127.0.0.1/ cargo test --release redis_connection_benchmark -- --ignored --nocapture`
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore]
+    async fn redis_connection_benchmark() {
+        use std::sync::Arc;
+
+        const TASKS: usize = 50;
+        const OPS_PER_TASK: usize = 200;
+
+        let url = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".to_string());
+        let cache = Arc::new(Cache::new(vec![Box::new(RedisBackend::new(&url).unwrap())]));
+        assert!(cache.set("bench:key", "value").await, "Redis is not reachable at {}", url);
+
+        fn report(label: &str, total: Duration, mut latencies: Vec<Duration>) {
+            latencies.sort();
+            let at = |q: f64| latencies[((latencies.len() - 1) as f64 * q) as usize];
+            println!(
+                "{:<24} {:>6} ops in {:>8.1?}  p50 {:>8.1?}  p99 {:>8.1?}  max {:>8.1?}",
+                label,
+                latencies.len(),
+                total,
+                at(0.5),
+                at(0.99),
+                latencies[latencies.len() - 1],
+            );
+        }
+
+        async fn run<F, Fut>(op: F) -> (Duration, Vec<Duration>)
+        where
+            F: Fn() -> Fut + Clone + Send + 'static,
+            Fut: std::future::Future<Output = ()> + Send,
+        {
+            let started = Instant::now();
+            let tasks: Vec<_> = (0..TASKS)
+                .map(|_| {
+                    let op = op.clone();
+                    tokio::spawn(async move {
+                        let mut latencies = Vec::with_capacity(OPS_PER_TASK);
+                        for _ in 0..OPS_PER_TASK {
+                            let op_started = Instant::now();
+                            op().await;
+                            latencies.push(op_started.elapsed());
+                        }
+                        latencies
+                    })
+                })
+                .collect();
+            let mut latencies = Vec::new();
+            for task in tasks {
+                latencies.extend(task.await.unwrap());
+            }
+            (started.elapsed(), latencies)
+        }
+
+        let client = Client::open(url.as_str()).unwrap();
+        let (total, latencies) = run(move || {
+            let client = client.clone();
+            async move {
+                let mut con = client.get_async_connection().await.unwrap();
+                let _: Option<String> = redis::cmd("GET").arg("bench:key").query_async(&mut con).await.unwrap();
+            }
+        })
+        .await;
+        report("connection per operation", total, latencies);
+
+        let shared = cache.clone();
+        let (total, latencies) = run(move || {
+            let cache = shared.clone();
+            async move {
+                assert!(cache.get("bench:key").await.is_some());
+            }
+        })
+        .await;
+        report("shared connection", total, latencies);
     }
 }
@@ -1,19 +1,65 @@
-use dotenvy::dotenv;
 use std::env;
 
 pub struct Config {
-    pub redis_url: String,
+    pub newsdata_api_key: Option<String>,
+    pub cryptqnews_api_key: Option<String>,
+    pub cryptopanic_api_key: Option<String>,
+    pub cryptopanic_base_url: Option<String>,
+    pub feed_urls: Vec<String>,
+    pub coingecko_base_url: String,
+    pub coingecko_api_key: Option<String>,
     pub database_url: String,
-    pub cryptqnews_api_key: String,
+    pub redis_url: String,
+    pub cache_backend: String,
+    pub cache_memory_entries: usize,
+    pub jwt_secret: Option<String>,
+    pub news_poll_interval_secs: u64,
+    pub news_poll_coins: Vec<String>,
 }
 
 impl Config {
     pub fn from_env() -> Self {
-        dotenv().ok();
+        dotenv::dotenv().ok();
         Self {
-            redis_url: env::var("REDIS_URL").expect("REDIS_URL must be set"),
-            database_url: env::var("DATABASE_URL").expect("DATABASE_URL must be set"),
-            cryptqnews_api_key: env::var("CRYPTQNEWS_API_KEY").expect("CRYPTQNEWS_API_KEY must be set"),
+            newsdata_api_key: optional_var("NEWSDATA_API_KEY"),
+            cryptqnews_api_key: optional_var("CRYPTQNEWS_API_KEY"),
+            cryptopanic_api_key: optional_var("CRYPTOPANIC_API_KEY"),
+            cryptopanic_base_url: optional_var("CRYPTOPANIC_BASE_URL"),
+            feed_urls: list_var("NEWS_FEED_URLS"),
+            coingecko_base_url: optional_var("COINGECKO_BASE_URL")
+                .unwrap_or_else(|| "https://api.coingecko.com/api/v3".to_string()),
+            coingecko_api_key: optional_var("COINGECKO_API_KEY"),
+            database_url: optional_var("DATABASE_URL")
+                .unwrap_or_else(|| "sqlite:data/users.db".to_string()),
+            redis_url: optional_var("REDIS_URL")
+                .unwrap_or_else(|| "redis://127.0.0.1/".to_string()),
+            cache_backend: optional_var("CACHE_BACKEND")
+                .map(|value| value.trim().to_lowercase())
+                .unwrap_or_else(|| "tiered".to_string()),
+            cache_memory_entries: optional_var("CACHE_MEMORY_ENTRIES")
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(10_000),
+            jwt_secret: optional_var("JWT_SECRET"),
+            news_poll_interval_secs: optional_var("NEWS_POLL_INTERVAL_SECS")
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(300),
+            news_poll_coins: list_var("NEWS_POLL_COINS"),
         }
     }
 }
+
+fn optional_var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.trim().is_empty())
+}
+
+fn list_var(name: &str) -> Vec<String> {
+    optional_var(name)
+        .map(|value| {
+            value
+                .split(',')
+                .map(|item| item.trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
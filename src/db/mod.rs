@@ -0,0 +1,241 @@
+mod postgres;
+mod sqlite;
+
+use async_trait::async_trait;
+use bcrypt::{hash, verify, DEFAULT_COST};
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+use crate::api::NewsItem;
+use crate::prices::PricePoint;
+
+pub use postgres::PgStorage;
+pub use sqlite::SqliteStorage;
+
+/// Everything the application persists. SQLite and PostgreSQL implement it;
+/// `connect` picks one from the `DATABASE_URL` scheme.
+#[async_trait]
+pub trait Storage: Send + Sync {
+    /// Runs a trivial query, for readiness checks.
+    async fn ping(&self) -> Result<(), String>;
+
+    async fn insert_user(&self, username: &str, email: &str, password_hash: &str) -> Result<(), String>;
+    async fn find_user(&self, username: &str) -> Result<Option<User>, String>;
+    async fn find_user_by_id(&self, id: i64) -> Result<Option<User>, String>;
+
+    /// Refresh tokens are stored as hashes, never in the clear.
+    async fn store_refresh_token(&self, user_id: i64, token_hash: &str, expires_at: DateTime<Utc>) -> Result<(), String>;
+    /// Marks a refresh token as used. Each token can be exchanged once; a
+    /// second attempt is reported as `Reused` so the caller can treat it as theft.
+    async fn consume_refresh_token(&self, token_hash: &str) -> Result<RefreshTokenUse, String>;
+    /// Revokes one refresh token. Knowing the token is proof enough, so no
+    /// user is needed: logging out works after the access token expired.
+    async fn revoke_refresh_token(&self, token_hash: &str) -> Result<(), String>;
+    async fn revoke_refresh_tokens(&self, user_id: i64) -> Result<(), String>;
+
+    /// Adds a canonical coin name to a user's watchlist. False when it was
+    /// already there.
+    async fn watch_coin(&self, user_id: i64, coin: &str) -> Result<bool, String>;
+    /// False when the coin was not on the watchlist.
+    async fn unwatch_coin(&self, user_id: i64, coin: &str) -> Result<bool, String>;
+    /// Watched coins in the order they were added.
+    async fn watched_coins(&self, user_id: i64) -> Result<Vec<String>, String>;
+    /// Every coin on at least one user's watchlist.
+    async fn all_watched_coins(&self) -> Result<Vec<String>, String>;
+    /// Stored articles found for any of the user's watched coins, newest first.
+    async fn watchlist_feed(&self, user_id: i64, limit: i64) -> Result<Vec<StoredArticle>, String>;
+
+    /// Id of the stored article with this URL.
+    async fn find_article_id(&self, url: &str) -> Result<Option<i64>, String>;
+    /// Bookmarks an article for a user, or updates an existing bookmark.
+    /// False when the article does not exist.
+    async fn save_bookmark(&self, user_id: i64, news_item_id: i64, update: &BookmarkUpdate) -> Result<bool, String>;
+    /// False when the article was not bookmarked.
+    async fn delete_bookmark(&self, user_id: i64, news_item_id: i64) -> Result<bool, String>;
+    /// A user's bookmarks, most recently saved first, optionally only those
+    /// carrying `tag`.
+    async fn list_bookmarks(&self, user_id: i64, tag: Option<&str>) -> Result<Vec<Bookmark>, String>;
+
+    async fn store_price_points(&self, coin: &str, points: &[PricePoint]) -> Result<(), String>;
+    async fn price_points_since(&self, coin: &str, since: DateTime<Utc>) -> Result<Vec<PricePoint>, String>;
+
+    /// Upserts fetched articles, deduplicated by URL, and records which
+    /// (canonical) query found them. Returns the items this query had not
+    /// found before.
+    async fn store_news_items(&self, query: &str, items: &[NewsItem]) -> Result<FoundNews, String>;
+    /// Articles first found for any of `coins` past `after`, oldest first and
+    /// then by coin and article id, for replaying updates a client missed.
+    async fn news_found_since(&self, coins: &[String], after: &FoundCursor, limit: i64) -> Result<Vec<FoundArticle>, String>;
+    /// Stored articles matching every filter that is set, newest first.
+    async fn list_articles(&self, filter: &ArticleFilter) -> Result<Vec<StoredArticle>, String>;
+    /// Full-text search over archived titles and summaries, best matches first.
+    /// Title matches weigh more than summary matches.
+    async fn search_articles(&self, query: &str, limit: i64) -> Result<Vec<ArchiveHit>, String>;
+
+    async fn create_user(&self, username: &str, email: &str, password: &str) -> Result<(), String> {
+        let password_hash = hash(password.as_bytes(), DEFAULT_COST)
+            .map_err(|e| format!("Failed to hash password: {}", e))?;
+
+        self.insert_user(username, email, &password_hash).await
+    }
+
+    async fn verify_user(&self, username: &str, password: &str) -> Result<User, String> {
+        match self.find_user(username).await? {
+            Some(user) => {
+                if verify(password, &user.password_hash).map_err(|e| format!("Failed to verify password: {}", e))? {
+                    Ok(user)
+                } else {
+                    Err("Invalid password".to_string())
+                }
+            }
+            None => Err("User not found".to_string()),
+        }
+    }
+}
+
+/// Opens the database named by `database_url` and applies pending migrations.
+/// `sqlite:` URLs use SQLite, `postgres://` and `postgresql://` use PostgreSQL.
+pub async fn connect(database_url: &str) -> Result<Arc<dyn Storage>, sqlx::Error> {
+    if database_url.starts_with("sqlite:") {
+        Ok(Arc::new(SqliteStorage::connect(database_url).await?))
+    } else if database_url.starts_with("postgres://") || database_url.starts_with("postgresql://") {
+        Ok(Arc::new(PgStorage::connect(database_url).await?))
+    } else {
+        Err(sqlx::Error::Configuration(
+            format!("Unsupported DATABASE_URL scheme: {}", database_url).into(),
+        ))
+    }
+}
+
+/// An archived article matching a full-text search. Lower `rank` is better;
+/// `snippet` is HTML: escaped text with the matched terms wrapped in `<mark>`.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ArchiveHit {
+    #[serde(flatten)]
+    pub article: StoredArticle,
+    pub rank: f64,
+    pub snippet: String,
+}
+
+/// Filters for `Storage::list_articles`. `coin` is matched against the
+/// canonical query that found the article; `query` also accepts articles
+/// whose title or summary match it, with `search_articles` syntax. `before`
+/// is a keyset cursor: only articles after that `(published_at, id)` in
+/// newest-first order. `limit` defaults to 50.
+#[derive(Debug, Default, Clone)]
+pub struct ArticleFilter {
+    pub coin: Option<String>,
+    pub query: Option<String>,
+    pub source: Option<String>,
+    pub api_source: Option<String>,
+    pub sentiment: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub before: Option<(DateTime<Utc>, i64)>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, utoipa::ToSchema)]
+pub struct StoredArticle {
+    pub id: i64,
+    #[serde(flatten)]
+    pub news: NewsItem,
+    pub first_seen_at: DateTime<Utc>,
+}
+
+/// Articles one `store_news_items` call found for the first time for its
+/// query. `found_at` has microsecond precision so it survives a round trip
+/// through either database unchanged.
+#[derive(Debug, Clone)]
+pub struct FoundNews {
+    pub found_at: DateTime<Utc>,
+    pub items: Vec<NewsItem>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FoundArticle {
+    /// Id of the stored article.
+    pub id: i64,
+    pub coin: String,
+    pub found_at: DateTime<Utc>,
+    pub news: NewsItem,
+}
+
+impl FoundArticle {
+    /// Where a replay that has sent this article resumes.
+    pub fn cursor(&self) -> FoundCursor {
+        FoundCursor { found_at: self.found_at, last: Some((self.coin.clone(), self.id)) }
+    }
+}
+
+/// Position in the articles `news_found_since` returns: past every article
+/// found at `found_at`, or with `last` only past that coin and article id
+/// among them, so an update larger than one page is read in several.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FoundCursor {
+    pub found_at: DateTime<Utc>,
+    pub last: Option<(String, i64)>,
+}
+
+/// An article a user saved, with their note and tags.
+#[derive(Debug, Clone, serde::Serialize, utoipa::ToSchema)]
+pub struct Bookmark {
+    #[serde(flatten)]
+    pub article: StoredArticle,
+    pub note: Option<String>,
+    pub tags: Vec<String>,
+    pub bookmarked_at: DateTime<Utc>,
+}
+
+/// Changes applied by `Storage::save_bookmark`. Fields left as `None` keep
+/// their current value; an empty note clears it.
+#[derive(Debug, Default, Clone)]
+pub struct BookmarkUpdate {
+    pub note: Option<String>,
+    pub tags: Option<Vec<String>>,
+}
+
+/// Outcome of `Storage::consume_refresh_token`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefreshTokenUse {
+    /// The token was live and is now used up; holds the owner's user id.
+    Valid(i64),
+    /// The token had already been used or revoked.
+    Reused(i64),
+    /// Unknown or expired.
+    Invalid,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct User {
+    pub id: i64,
+    pub username: String,
+    pub email: String,
+    pub password_hash: String,
+}
+
+/// Delimit the matched terms in snippets from the database. Articles come
+/// from arbitrary feeds, so the text is escaped before these become `<mark>`.
+const MARK_START: &str = "\u{2}";
+const MARK_END: &str = "\u{3}";
+
+/// A snippet delimited with `MARK_START` and `MARK_END` as safe HTML.
+fn highlight(snippet: &str) -> String {
+    crate::routes::escape_html(snippet)
+        .replace(MARK_START, "<mark>")
+        .replace(MARK_END, "</mark>")
+}
+
+/// Full-text terms for a query. Known coins expand to their name and ticker
+/// ("btc" finds "Bitcoin" and vice versa); the bool is true when any term may
+/// match (OR) rather than all of them (AND).
+fn search_terms(query: &str) -> (Vec<String>, bool) {
+    use crate::api::{canonical_coin, coin_ticker};
+
+    if let Some(ticker) = coin_ticker(query) {
+        return (vec![canonical_coin(query), ticker.to_string()], true);
+    }
+    (query.split_whitespace().map(str::to_string).collect(), false)
+}
+
+#[cfg(test)]
+pub(crate) mod tests;
@@ -0,0 +1,642 @@
+use async_trait::async_trait;
+use sqlx::postgres::{PgPool, PgRow, Postgres};
+use sqlx::{QueryBuilder, Row};
+use chrono::{DateTime, SubsecRound, Utc};
+use std::collections::HashMap;
+use crate::api::{canonical_coin, NewsItem};
+use crate::prices::PricePoint;
+use super::{highlight, search_terms, MARK_END, MARK_START, ArchiveHit, ArticleFilter, Bookmark, BookmarkUpdate, FoundArticle, FoundCursor, FoundNews, RefreshTokenUse, Storage, StoredArticle, User};
+
+pub struct PgStorage {
+    pub pool: PgPool,
+}
+
+impl PgStorage {
+    pub async fn connect(database_url: &str) -> Result<Self, sqlx::Error> {
+        let pool = PgPool::connect(database_url).await?;
+        sqlx::migrate!("./migrations/postgres").run(&pool).await?;
+
+        Ok(PgStorage { pool })
+    }
+}
+
+#[async_trait]
+impl Storage for PgStorage {
+    async fn ping(&self) -> Result<(), String> {
+        sqlx::query("SELECT 1")
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("Database error: {}", e))
+    }
+
+    async fn insert_user(&self, username: &str, email: &str, password_hash: &str) -> Result<(), String> {
+        sqlx::query(
+            r#"
+            INSERT INTO users (username, email, password_hash, created_at)
+            VALUES ($1, $2, $3, $4)
+            "#,
+        )
+        .bind(username)
+        .bind(email)
+        .bind(password_hash)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to create user: {}", e))?;
+
+        Ok(())
+    }
+
+    async fn find_user(&self, username: &str) -> Result<Option<User>, String> {
+        let row = sqlx::query("SELECT id, username, email, password_hash FROM users WHERE username = $1")
+            .bind(username)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        row.as_ref().map(user_from_row).transpose()
+    }
+
+    async fn find_user_by_id(&self, id: i64) -> Result<Option<User>, String> {
+        let row = sqlx::query("SELECT id, username, email, password_hash FROM users WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        row.as_ref().map(user_from_row).transpose()
+    }
+
+    async fn store_refresh_token(&self, user_id: i64, token_hash: &str, expires_at: DateTime<Utc>) -> Result<(), String> {
+        sqlx::query(
+            "INSERT INTO refresh_tokens (user_id, token_hash, created_at, expires_at) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(user_id)
+        .bind(token_hash)
+        .bind(Utc::now())
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to store refresh token: {}", e))?;
+
+        Ok(())
+    }
+
+    async fn consume_refresh_token(&self, token_hash: &str) -> Result<RefreshTokenUse, String> {
+        let now = Utc::now();
+        let used: Option<i64> = sqlx::query_scalar(
+            r#"
+            UPDATE refresh_tokens SET revoked_at = $1
+            WHERE token_hash = $2 AND revoked_at IS NULL AND expires_at > $1
+            RETURNING user_id
+            "#,
+        )
+        .bind(now)
+        .bind(token_hash)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+        if let Some(user_id) = used {
+            return Ok(RefreshTokenUse::Valid(user_id));
+        }
+
+        let row = sqlx::query("SELECT user_id, revoked_at FROM refresh_tokens WHERE token_hash = $1")
+            .bind(token_hash)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        match row {
+            Some(row) => {
+                let user_id: i64 = row.try_get("user_id").map_err(|e| format!("Failed to get user_id: {}", e))?;
+                let revoked_at: Option<DateTime<Utc>> = row.try_get("revoked_at").map_err(|e| format!("Failed to get revoked_at: {}", e))?;
+                Ok(match revoked_at {
+                    Some(_) => RefreshTokenUse::Reused(user_id),
+                    None => RefreshTokenUse::Invalid,
+                })
+            }
+            None => Ok(RefreshTokenUse::Invalid),
+        }
+    }
+
+    async fn revoke_refresh_token(&self, token_hash: &str) -> Result<(), String> {
+        sqlx::query("UPDATE refresh_tokens SET revoked_at = $1 WHERE token_hash = $2 AND revoked_at IS NULL")
+            .bind(Utc::now())
+            .bind(token_hash)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to revoke refresh token: {}", e))?;
+
+        Ok(())
+    }
+
+    async fn revoke_refresh_tokens(&self, user_id: i64) -> Result<(), String> {
+        sqlx::query("UPDATE refresh_tokens SET revoked_at = $1 WHERE user_id = $2 AND revoked_at IS NULL")
+            .bind(Utc::now())
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to revoke refresh tokens: {}", e))?;
+
+        Ok(())
+    }
+
+    async fn watch_coin(&self, user_id: i64, coin: &str) -> Result<bool, String> {
+        let result = sqlx::query(
+            "INSERT INTO watchlists (user_id, coin, added_at) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING",
+        )
+        .bind(user_id)
+        .bind(coin)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to update watchlist: {}", e))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn unwatch_coin(&self, user_id: i64, coin: &str) -> Result<bool, String> {
+        let result = sqlx::query("DELETE FROM watchlists WHERE user_id = $1 AND coin = $2")
+            .bind(user_id)
+            .bind(coin)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to update watchlist: {}", e))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn watched_coins(&self, user_id: i64) -> Result<Vec<String>, String> {
+        sqlx::query_scalar("SELECT coin FROM watchlists WHERE user_id = $1 ORDER BY added_at, coin")
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| format!("Database error: {}", e))
+    }
+
+    async fn all_watched_coins(&self) -> Result<Vec<String>, String> {
+        sqlx::query_scalar("SELECT DISTINCT coin FROM watchlists ORDER BY coin")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| format!("Database error: {}", e))
+    }
+
+    async fn watchlist_feed(&self, user_id: i64, limit: i64) -> Result<Vec<StoredArticle>, String> {
+        let rows = sqlx::query(
+            r#"
+            SELECT n.id, n.title, n.source, n.published_at, n.summary, n.url, n.sentiment, n.api_source, n.first_seen_at
+            FROM news_items n
+            WHERE EXISTS (
+                SELECT 1 FROM news_item_queries q
+                JOIN watchlists w ON w.coin = q.query
+                WHERE q.news_item_id = n.id AND w.user_id = $1
+            )
+            ORDER BY n.published_at DESC, n.id DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(user_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+        rows.iter().map(stored_article_from_row).collect()
+    }
+
+    async fn find_article_id(&self, url: &str) -> Result<Option<i64>, String> {
+        sqlx::query_scalar("SELECT id FROM news_items WHERE url = $1")
+            .bind(url)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| format!("Database error: {}", e))
+    }
+
+    async fn save_bookmark(&self, user_id: i64, news_item_id: i64, update: &BookmarkUpdate) -> Result<bool, String> {
+        let now = Utc::now();
+        let mut tx = self.pool.begin().await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        let exists: Option<i64> = sqlx::query_scalar("SELECT id FROM news_items WHERE id = $1")
+            .bind(news_item_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        if exists.is_none() {
+            return Ok(false);
+        }
+
+        sqlx::query(
+            "INSERT INTO bookmarks (user_id, news_item_id, created_at, updated_at) VALUES ($1, $2, $3, $4) ON CONFLICT DO NOTHING",
+        )
+        .bind(user_id)
+        .bind(news_item_id)
+        .bind(now)
+        .bind(now)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to save bookmark: {}", e))?;
+
+        if let Some(note) = &update.note {
+            let note = Some(note.trim()).filter(|note| !note.is_empty());
+            sqlx::query("UPDATE bookmarks SET note = $1, updated_at = $2 WHERE user_id = $3 AND news_item_id = $4")
+                .bind(note)
+                .bind(now)
+                .bind(user_id)
+                .bind(news_item_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| format!("Failed to save bookmark: {}", e))?;
+        }
+
+        if let Some(tags) = &update.tags {
+            sqlx::query("DELETE FROM bookmark_tags WHERE user_id = $1 AND news_item_id = $2")
+                .bind(user_id)
+                .bind(news_item_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| format!("Failed to save bookmark tags: {}", e))?;
+
+            for tag in tags {
+                sqlx::query("INSERT INTO bookmark_tags (user_id, news_item_id, tag) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING")
+                    .bind(user_id)
+                    .bind(news_item_id)
+                    .bind(tag)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|e| format!("Failed to save bookmark tags: {}", e))?;
+            }
+        }
+
+        tx.commit().await.map_err(|e| format!("Database error: {}", e))?;
+        Ok(true)
+    }
+
+    async fn delete_bookmark(&self, user_id: i64, news_item_id: i64) -> Result<bool, String> {
+        let result = sqlx::query("DELETE FROM bookmarks WHERE user_id = $1 AND news_item_id = $2")
+            .bind(user_id)
+            .bind(news_item_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to delete bookmark: {}", e))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn list_bookmarks(&self, user_id: i64, tag: Option<&str>) -> Result<Vec<Bookmark>, String> {
+        let rows = sqlx::query(
+            r#"
+            SELECT n.id, n.title, n.source, n.published_at, n.summary, n.url, n.sentiment, n.api_source, n.first_seen_at,
+                b.note, b.created_at AS bookmarked_at
+            FROM bookmarks b
+            JOIN news_items n ON n.id = b.news_item_id
+            WHERE b.user_id = $1
+                AND ($2::TEXT IS NULL OR EXISTS (
+                    SELECT 1 FROM bookmark_tags t
+                    WHERE t.user_id = b.user_id AND t.news_item_id = b.news_item_id AND t.tag = $2
+                ))
+            ORDER BY b.created_at DESC, n.id DESC
+            "#,
+        )
+        .bind(user_id)
+        .bind(tag)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+        let tag_rows = sqlx::query("SELECT news_item_id, tag FROM bookmark_tags WHERE user_id = $1 ORDER BY tag")
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        let mut tags: HashMap<i64, Vec<String>> = HashMap::new();
+        for row in &tag_rows {
+            let id: i64 = row.try_get("news_item_id").map_err(|e| format!("Failed to get news_item_id: {}", e))?;
+            let tag: String = row.try_get("tag").map_err(|e| format!("Failed to get tag: {}", e))?;
+            tags.entry(id).or_default().push(tag);
+        }
+
+        rows.iter()
+            .map(|row| {
+                let article = stored_article_from_row(row)?;
+                Ok(Bookmark {
+                    tags: tags.remove(&article.id).unwrap_or_default(),
+                    note: row.try_get("note").map_err(|e| format!("Failed to get note: {}", e))?,
+                    bookmarked_at: row.try_get("bookmarked_at").map_err(|e| format!("Failed to get bookmarked_at: {}", e))?,
+                    article,
+                })
+            })
+            .collect()
+    }
+
+    async fn store_price_points(&self, coin: &str, points: &[PricePoint]) -> Result<(), String> {
+        let mut tx = self.pool.begin().await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        for point in points {
+            sqlx::query(
+                r#"
+                INSERT INTO price_history (coin, timestamp, open, high, low, close)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                ON CONFLICT (coin, timestamp) DO UPDATE SET
+                    open = excluded.open,
+                    high = excluded.high,
+                    low = excluded.low,
+                    close = excluded.close
+                "#,
+            )
+            .bind(coin)
+            .bind(point.timestamp)
+            .bind(point.open)
+            .bind(point.high)
+            .bind(point.low)
+            .bind(point.close)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to store price point: {}", e))?;
+        }
+
+        tx.commit().await.map_err(|e| format!("Database error: {}", e))
+    }
+
+    async fn price_points_since(&self, coin: &str, since: DateTime<Utc>) -> Result<Vec<PricePoint>, String> {
+        let rows = sqlx::query(
+            "SELECT timestamp, open, high, low, close FROM price_history WHERE coin = $1 AND timestamp >= $2 ORDER BY timestamp",
+        )
+        .bind(coin)
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(PricePoint {
+                    timestamp: row.try_get("timestamp").map_err(|e| format!("Failed to get timestamp: {}", e))?,
+                    open: row.try_get("open").map_err(|e| format!("Failed to get open: {}", e))?,
+                    high: row.try_get("high").map_err(|e| format!("Failed to get high: {}", e))?,
+                    low: row.try_get("low").map_err(|e| format!("Failed to get low: {}", e))?,
+                    close: row.try_get("close").map_err(|e| format!("Failed to get close: {}", e))?,
+                })
+            })
+            .collect()
+    }
+
+    async fn store_news_items(&self, query: &str, items: &[NewsItem]) -> Result<FoundNews, String> {
+        let query = canonical_coin(query);
+        let now = Utc::now().trunc_subsecs(6);
+        let mut new_items = Vec::new();
+
+        let mut tx = self.pool.begin().await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        for item in items {
+            let id: i64 = sqlx::query_scalar(
+                r#"
+                INSERT INTO news_items (title, source, published_at, summary, url, sentiment, api_source, first_seen_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                ON CONFLICT (url) DO UPDATE SET
+                    title = excluded.title,
+                    source = excluded.source,
+                    published_at = excluded.published_at,
+                    summary = excluded.summary,
+                    sentiment = excluded.sentiment,
+                    api_source = excluded.api_source
+                RETURNING id
+                "#,
+            )
+            .bind(&item.title)
+            .bind(&item.source)
+            .bind(item.published_at)
+            .bind(&item.summary)
+            .bind(&item.url)
+            .bind(&item.sentiment)
+            .bind(&item.api_source)
+            .bind(now)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to store news item: {}", e))?;
+
+            let found = sqlx::query(
+                "INSERT INTO news_item_queries (news_item_id, query, found_at) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING",
+            )
+            .bind(id)
+            .bind(&query)
+            .bind(now)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to store news item query: {}", e))?;
+            if found.rows_affected() > 0 {
+                new_items.push(item.clone());
+            }
+        }
+
+        tx.commit().await.map_err(|e| format!("Database error: {}", e))?;
+        Ok(FoundNews { found_at: now, items: new_items })
+    }
+
+    async fn news_found_since(&self, coins: &[String], after: &FoundCursor, limit: i64) -> Result<Vec<FoundArticle>, String> {
+        if coins.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut builder = QueryBuilder::<Postgres>::new(
+            r#"
+            SELECT n.id, q.query, q.found_at, n.title, n.source, n.published_at, n.summary, n.url, n.sentiment, n.api_source
+            FROM news_item_queries q
+            JOIN news_items n ON n.id = q.news_item_id
+            WHERE (q.found_at > "#,
+        );
+        builder.push_bind(after.found_at);
+        if let Some((coin, id)) = &after.last {
+            builder
+                .push(" OR (q.found_at = ")
+                .push_bind(after.found_at)
+                .push(" AND (q.query, q.news_item_id) > (")
+                .push_bind(coin.clone())
+                .push(", ")
+                .push_bind(*id)
+                .push("))");
+        }
+        builder.push(") AND q.query IN (");
+        let mut separated = builder.separated(", ");
+        for coin in coins {
+            separated.push_bind(canonical_coin(coin));
+        }
+        builder
+            .push(") ORDER BY q.found_at, q.query, q.news_item_id LIMIT ")
+            .push_bind(limit);
+
+        let rows = builder
+            .build()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        rows.iter()
+            .map(|row| {
+                let get_err = |column: &str, e: sqlx::Error| format!("Failed to get {}: {}", column, e);
+                Ok(FoundArticle {
+                    id: row.try_get("id").map_err(|e| get_err("id", e))?,
+                    coin: row.try_get("query").map_err(|e| get_err("query", e))?,
+                    found_at: row.try_get("found_at").map_err(|e| get_err("found_at", e))?,
+                    news: NewsItem {
+                        title: row.try_get("title").map_err(|e| get_err("title", e))?,
+                        source: row.try_get("source").map_err(|e| get_err("source", e))?,
+                        url: row.try_get("url").map_err(|e| get_err("url", e))?,
+                        published_at: row.try_get("published_at").map_err(|e| get_err("published_at", e))?,
+                        summary: row.try_get("summary").map_err(|e| get_err("summary", e))?,
+                        sentiment: row.try_get("sentiment").map_err(|e| get_err("sentiment", e))?,
+                        api_source: row.try_get("api_source").map_err(|e| get_err("api_source", e))?,
+                    },
+                })
+            })
+            .collect()
+    }
+
+    async fn list_articles(&self, filter: &ArticleFilter) -> Result<Vec<StoredArticle>, String> {
+        let mut builder = QueryBuilder::<Postgres>::new(
+            "SELECT n.id, n.title, n.source, n.published_at, n.summary, n.url, n.sentiment, n.api_source, n.first_seen_at FROM news_items n WHERE 1 = 1",
+        );
+
+        if let Some(coin) = &filter.coin {
+            builder
+                .push(" AND EXISTS (SELECT 1 FROM news_item_queries q WHERE q.news_item_id = n.id AND q.query = ")
+                .push_bind(canonical_coin(coin))
+                .push(")");
+        }
+        if let Some(query) = &filter.query {
+            builder
+                .push(" AND (EXISTS (SELECT 1 FROM news_item_queries q WHERE q.news_item_id = n.id AND q.query = ")
+                .push_bind(canonical_coin(query))
+                .push(")");
+            let (terms, any) = search_terms(query);
+            if !terms.is_empty() {
+                builder.push(" OR n.search_vector @@ (");
+                push_tsquery(&mut builder, terms, any);
+                builder.push(")");
+            }
+            builder.push(")");
+        }
+        if let Some(source) = &filter.source {
+            builder.push(" AND n.source = ").push_bind(source.clone());
+        }
+        if let Some(api_source) = &filter.api_source {
+            builder.push(" AND n.api_source = ").push_bind(api_source.clone());
+        }
+        if let Some(sentiment) = &filter.sentiment {
+            builder.push(" AND n.sentiment = ").push_bind(sentiment.clone());
+        }
+        if let Some(from) = filter.from {
+            builder.push(" AND n.published_at >= ").push_bind(from);
+        }
+        if let Some(to) = filter.to {
+            builder.push(" AND n.published_at <= ").push_bind(to);
+        }
+        if let Some((published_at, id)) = filter.before {
+            builder
+                .push(" AND (n.published_at < ")
+                .push_bind(published_at)
+                .push(" OR (n.published_at = ")
+                .push_bind(published_at)
+                .push(" AND n.id < ")
+                .push_bind(id)
+                .push("))");
+        }
+        builder
+            .push(" ORDER BY n.published_at DESC, n.id DESC LIMIT ")
+            .push_bind(filter.limit.unwrap_or(50));
+
+        let rows = builder
+            .build()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        rows.iter().map(stored_article_from_row).collect()
+    }
+
+    async fn search_articles(&self, query: &str, limit: i64) -> Result<Vec<ArchiveHit>, String> {
+        let (terms, any) = search_terms(query);
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Titles (weight A) count ten times as much as summaries (weight B),
+        // like the bm25 weights on SQLite.
+        let mut builder = QueryBuilder::<Postgres>::new("WITH q AS (SELECT ");
+        push_tsquery(&mut builder, terms, any);
+        builder.push(
+            r#" AS query)
+            SELECT n.id, n.title, n.source, n.published_at, n.summary, n.url, n.sentiment, n.api_source, n.first_seen_at,
+                -ts_rank('{0.0, 0.0, 0.1, 1.0}', n.search_vector, q.query)::float8 AS rank,
+                ts_headline('english', n.summary, q.query, "#,
+        );
+        builder
+            .push_bind(format!("StartSel={}, StopSel={}, MaxWords=24, MinWords=8", MARK_START, MARK_END))
+            .push(
+                r#") AS snippet
+            FROM news_items n, q
+            WHERE n.search_vector @@ q.query
+            ORDER BY rank, n.published_at DESC
+            LIMIT "#,
+            )
+            .push_bind(limit);
+
+        let rows = builder
+            .build()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(ArchiveHit {
+                    article: stored_article_from_row(row)?,
+                    rank: row.try_get("rank").map_err(|e| format!("Failed to get rank: {}", e))?,
+                    snippet: highlight(&row.try_get::<String, _>("snippet").map_err(|e| format!("Failed to get snippet: {}", e))?),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Each term goes through plainto_tsquery so user input cannot inject
+/// tsquery syntax.
+fn push_tsquery(builder: &mut QueryBuilder<'_, Postgres>, terms: Vec<String>, any: bool) {
+    for (i, term) in terms.into_iter().enumerate() {
+        if i > 0 {
+            builder.push(if any { " || " } else { " && " });
+        }
+        builder.push("plainto_tsquery('english', ").push_bind(term).push(")");
+    }
+}
+
+fn user_from_row(row: &PgRow) -> Result<User, String> {
+    Ok(User {
+        id: row.try_get("id").map_err(|e| format!("Failed to get id: {}", e))?,
+        username: row.try_get("username").map_err(|e| format!("Failed to get username: {}", e))?,
+        email: row.try_get("email").map_err(|e| format!("Failed to get email: {}", e))?,
+        password_hash: row.try_get("password_hash").map_err(|e| format!("Failed to get password_hash: {}", e))?,
+    })
+}
+
+fn stored_article_from_row(row: &PgRow) -> Result<StoredArticle, String> {
+    let get_err = |column: &str, e: sqlx::Error| format!("Failed to get {}: {}", column, e);
+    Ok(StoredArticle {
+        id: row.try_get("id").map_err(|e| get_err("id", e))?,
+        news: NewsItem {
+            title: row.try_get("title").map_err(|e| get_err("title", e))?,
+            source: row.try_get("source").map_err(|e| get_err("source", e))?,
+            url: row.try_get("url").map_err(|e| get_err("url", e))?,
+            published_at: row.try_get("published_at").map_err(|e| get_err("published_at", e))?,
+            summary: row.try_get("summary").map_err(|e| get_err("summary", e))?,
+            sentiment: row.try_get("sentiment").map_err(|e| get_err("sentiment", e))?,
+            api_source: row.try_get("api_source").map_err(|e| get_err("api_source", e))?,
+        },
+        first_seen_at: row.try_get("first_seen_at").map_err(|e| get_err("first_seen_at", e))?,
+    })
+}
@@ -0,0 +1,729 @@
+use async_trait::async_trait;
+use sqlx::sqlite::{Sqlite, SqliteConnectOptions, SqlitePool, SqliteRow};
+use sqlx::{QueryBuilder, Row};
+use std::fs;
+use std::str::FromStr;
+use chrono::{DateTime, SubsecRound, Utc};
+use std::collections::HashMap;
+use crate::api::{canonical_coin, NewsItem};
+use crate::prices::PricePoint;
+use super::{highlight, search_terms, MARK_END, MARK_START, ArchiveHit, ArticleFilter, Bookmark, BookmarkUpdate, FoundArticle, FoundCursor, FoundNews, RefreshTokenUse, Storage, StoredArticle, User};
+
+pub struct SqliteStorage {
+    pub pool: SqlitePool,
+}
+
+impl SqliteStorage {
+    pub async fn connect(database_url: &str) -> Result<Self, sqlx::Error> {
+        let options = SqliteConnectOptions::from_str(database_url)?.create_if_missing(true);
+
+        if let Some(dir) = options.clone().get_filename().parent() {
+            if !dir.as_os_str().is_empty() && !dir.exists() {
+                fs::create_dir_all(dir).expect("Failed to create data directory");
+            }
+        }
+
+        println!("Connecting to database at: {}", database_url);
+        let pool = SqlitePool::connect_with(options).await?;
+
+        upgrade_legacy_schema(&pool).await?;
+        sqlx::migrate!("./migrations/sqlite").run(&pool).await?;
+
+        Ok(SqliteStorage { pool })
+    }
+}
+
+#[async_trait]
+impl Storage for SqliteStorage {
+    async fn ping(&self) -> Result<(), String> {
+        sqlx::query("SELECT 1")
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("Database error: {}", e))
+    }
+
+    async fn insert_user(&self, username: &str, email: &str, password_hash: &str) -> Result<(), String> {
+        sqlx::query(
+            r#"
+            INSERT INTO users (username, email, password_hash, created_at)
+            VALUES (?, ?, ?, ?)
+            "#,
+        )
+        .bind(username)
+        .bind(email)
+        .bind(password_hash)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to create user: {}", e))?;
+
+        Ok(())
+    }
+
+    async fn find_user(&self, username: &str) -> Result<Option<User>, String> {
+        let row = sqlx::query("SELECT id, username, email, password_hash FROM users WHERE username = ?")
+            .bind(username)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        row.as_ref().map(user_from_row).transpose()
+    }
+
+    async fn find_user_by_id(&self, id: i64) -> Result<Option<User>, String> {
+        let row = sqlx::query("SELECT id, username, email, password_hash FROM users WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        row.as_ref().map(user_from_row).transpose()
+    }
+
+    async fn store_refresh_token(&self, user_id: i64, token_hash: &str, expires_at: DateTime<Utc>) -> Result<(), String> {
+        sqlx::query(
+            "INSERT INTO refresh_tokens (user_id, token_hash, created_at, expires_at) VALUES (?, ?, ?, ?)",
+        )
+        .bind(user_id)
+        .bind(token_hash)
+        .bind(Utc::now())
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to store refresh token: {}", e))?;
+
+        Ok(())
+    }
+
+    async fn consume_refresh_token(&self, token_hash: &str) -> Result<RefreshTokenUse, String> {
+        let now = Utc::now();
+        let used: Option<i64> = sqlx::query_scalar(
+            r#"
+            UPDATE refresh_tokens SET revoked_at = ?
+            WHERE token_hash = ? AND revoked_at IS NULL AND expires_at > ?
+            RETURNING user_id
+            "#,
+        )
+        .bind(now)
+        .bind(token_hash)
+        .bind(now)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+        if let Some(user_id) = used {
+            return Ok(RefreshTokenUse::Valid(user_id));
+        }
+
+        let row = sqlx::query("SELECT user_id, revoked_at FROM refresh_tokens WHERE token_hash = ?")
+            .bind(token_hash)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        match row {
+            Some(row) => {
+                let user_id: i64 = row.try_get("user_id").map_err(|e| format!("Failed to get user_id: {}", e))?;
+                let revoked_at: Option<DateTime<Utc>> = row.try_get("revoked_at").map_err(|e| format!("Failed to get revoked_at: {}", e))?;
+                Ok(match revoked_at {
+                    Some(_) => RefreshTokenUse::Reused(user_id),
+                    None => RefreshTokenUse::Invalid,
+                })
+            }
+            None => Ok(RefreshTokenUse::Invalid),
+        }
+    }
+
+    async fn revoke_refresh_token(&self, token_hash: &str) -> Result<(), String> {
+        sqlx::query("UPDATE refresh_tokens SET revoked_at = ? WHERE token_hash = ? AND revoked_at IS NULL")
+            .bind(Utc::now())
+            .bind(token_hash)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to revoke refresh token: {}", e))?;
+
+        Ok(())
+    }
+
+    async fn revoke_refresh_tokens(&self, user_id: i64) -> Result<(), String> {
+        sqlx::query("UPDATE refresh_tokens SET revoked_at = ? WHERE user_id = ? AND revoked_at IS NULL")
+            .bind(Utc::now())
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to revoke refresh tokens: {}", e))?;
+
+        Ok(())
+    }
+
+    async fn watch_coin(&self, user_id: i64, coin: &str) -> Result<bool, String> {
+        let result = sqlx::query(
+            "INSERT INTO watchlists (user_id, coin, added_at) VALUES (?, ?, ?) ON CONFLICT DO NOTHING",
+        )
+        .bind(user_id)
+        .bind(coin)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to update watchlist: {}", e))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn unwatch_coin(&self, user_id: i64, coin: &str) -> Result<bool, String> {
+        let result = sqlx::query("DELETE FROM watchlists WHERE user_id = ? AND coin = ?")
+            .bind(user_id)
+            .bind(coin)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to update watchlist: {}", e))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn watched_coins(&self, user_id: i64) -> Result<Vec<String>, String> {
+        sqlx::query_scalar("SELECT coin FROM watchlists WHERE user_id = ? ORDER BY added_at, coin")
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| format!("Database error: {}", e))
+    }
+
+    async fn all_watched_coins(&self) -> Result<Vec<String>, String> {
+        sqlx::query_scalar("SELECT DISTINCT coin FROM watchlists ORDER BY coin")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| format!("Database error: {}", e))
+    }
+
+    async fn watchlist_feed(&self, user_id: i64, limit: i64) -> Result<Vec<StoredArticle>, String> {
+        let rows = sqlx::query(
+            r#"
+            SELECT n.id, n.title, n.source, n.published_at, n.summary, n.url, n.sentiment, n.api_source, n.first_seen_at
+            FROM news_items n
+            WHERE EXISTS (
+                SELECT 1 FROM news_item_queries q
+                JOIN watchlists w ON w.coin = q.query
+                WHERE q.news_item_id = n.id AND w.user_id = ?
+            )
+            ORDER BY n.published_at DESC, n.id DESC
+            LIMIT ?
+            "#,
+        )
+        .bind(user_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+        rows.iter().map(stored_article_from_row).collect()
+    }
+
+    async fn find_article_id(&self, url: &str) -> Result<Option<i64>, String> {
+        sqlx::query_scalar("SELECT id FROM news_items WHERE url = ?")
+            .bind(url)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| format!("Database error: {}", e))
+    }
+
+    async fn save_bookmark(&self, user_id: i64, news_item_id: i64, update: &BookmarkUpdate) -> Result<bool, String> {
+        let now = Utc::now();
+        let mut tx = self.pool.begin().await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        let exists: Option<i64> = sqlx::query_scalar("SELECT id FROM news_items WHERE id = ?")
+            .bind(news_item_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        if exists.is_none() {
+            return Ok(false);
+        }
+
+        sqlx::query(
+            "INSERT INTO bookmarks (user_id, news_item_id, created_at, updated_at) VALUES (?, ?, ?, ?) ON CONFLICT DO NOTHING",
+        )
+        .bind(user_id)
+        .bind(news_item_id)
+        .bind(now)
+        .bind(now)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to save bookmark: {}", e))?;
+
+        if let Some(note) = &update.note {
+            let note = Some(note.trim()).filter(|note| !note.is_empty());
+            sqlx::query("UPDATE bookmarks SET note = ?, updated_at = ? WHERE user_id = ? AND news_item_id = ?")
+                .bind(note)
+                .bind(now)
+                .bind(user_id)
+                .bind(news_item_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| format!("Failed to save bookmark: {}", e))?;
+        }
+
+        if let Some(tags) = &update.tags {
+            sqlx::query("DELETE FROM bookmark_tags WHERE user_id = ? AND news_item_id = ?")
+                .bind(user_id)
+                .bind(news_item_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| format!("Failed to save bookmark tags: {}", e))?;
+
+            for tag in tags {
+                sqlx::query("INSERT INTO bookmark_tags (user_id, news_item_id, tag) VALUES (?, ?, ?) ON CONFLICT DO NOTHING")
+                    .bind(user_id)
+                    .bind(news_item_id)
+                    .bind(tag)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|e| format!("Failed to save bookmark tags: {}", e))?;
+            }
+        }
+
+        tx.commit().await.map_err(|e| format!("Database error: {}", e))?;
+        Ok(true)
+    }
+
+    async fn delete_bookmark(&self, user_id: i64, news_item_id: i64) -> Result<bool, String> {
+        let result = sqlx::query("DELETE FROM bookmarks WHERE user_id = ? AND news_item_id = ?")
+            .bind(user_id)
+            .bind(news_item_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to delete bookmark: {}", e))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn list_bookmarks(&self, user_id: i64, tag: Option<&str>) -> Result<Vec<Bookmark>, String> {
+        let rows = sqlx::query(
+            r#"
+            SELECT n.id, n.title, n.source, n.published_at, n.summary, n.url, n.sentiment, n.api_source, n.first_seen_at,
+                b.note, b.created_at AS bookmarked_at
+            FROM bookmarks b
+            JOIN news_items n ON n.id = b.news_item_id
+            WHERE b.user_id = ?
+                AND (? IS NULL OR EXISTS (
+                    SELECT 1 FROM bookmark_tags t
+                    WHERE t.user_id = b.user_id AND t.news_item_id = b.news_item_id AND t.tag = ?
+                ))
+            ORDER BY b.created_at DESC, n.id DESC
+            "#,
+        )
+        .bind(user_id)
+        .bind(tag)
+        .bind(tag)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+        let tag_rows = sqlx::query("SELECT news_item_id, tag FROM bookmark_tags WHERE user_id = ? ORDER BY tag")
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        let mut tags: HashMap<i64, Vec<String>> = HashMap::new();
+        for row in &tag_rows {
+            let id: i64 = row.try_get("news_item_id").map_err(|e| format!("Failed to get news_item_id: {}", e))?;
+            let tag: String = row.try_get("tag").map_err(|e| format!("Failed to get tag: {}", e))?;
+            tags.entry(id).or_default().push(tag);
+        }
+
+        rows.iter()
+            .map(|row| {
+                let article = stored_article_from_row(row)?;
+                Ok(Bookmark {
+                    tags: tags.remove(&article.id).unwrap_or_default(),
+                    note: row.try_get("note").map_err(|e| format!("Failed to get note: {}", e))?,
+                    bookmarked_at: row.try_get("bookmarked_at").map_err(|e| format!("Failed to get bookmarked_at: {}", e))?,
+                    article,
+                })
+            })
+            .collect()
+    }
+
+    async fn store_price_points(&self, coin: &str, points: &[PricePoint]) -> Result<(), String> {
+        let mut tx = self.pool.begin().await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        for point in points {
+            sqlx::query(
+                r#"
+                INSERT INTO price_history (coin, timestamp, open, high, low, close)
+                VALUES (?, ?, ?, ?, ?, ?)
+                ON CONFLICT (coin, timestamp) DO UPDATE SET
+                    open = excluded.open,
+                    high = excluded.high,
+                    low = excluded.low,
+                    close = excluded.close
+                "#,
+            )
+            .bind(coin)
+            .bind(point.timestamp)
+            .bind(point.open)
+            .bind(point.high)
+            .bind(point.low)
+            .bind(point.close)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to store price point: {}", e))?;
+        }
+
+        tx.commit().await.map_err(|e| format!("Database error: {}", e))
+    }
+
+    async fn price_points_since(&self, coin: &str, since: DateTime<Utc>) -> Result<Vec<PricePoint>, String> {
+        let rows = sqlx::query(
+            "SELECT timestamp, open, high, low, close FROM price_history WHERE coin = ? AND timestamp >= ? ORDER BY timestamp",
+        )
+        .bind(coin)
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(PricePoint {
+                    timestamp: row.try_get("timestamp").map_err(|e| format!("Failed to get timestamp: {}", e))?,
+                    open: row.try_get("open").map_err(|e| format!("Failed to get open: {}", e))?,
+                    high: row.try_get("high").map_err(|e| format!("Failed to get high: {}", e))?,
+                    low: row.try_get("low").map_err(|e| format!("Failed to get low: {}", e))?,
+                    close: row.try_get("close").map_err(|e| format!("Failed to get close: {}", e))?,
+                })
+            })
+            .collect()
+    }
+
+    async fn store_news_items(&self, query: &str, items: &[NewsItem]) -> Result<FoundNews, String> {
+        let query = canonical_coin(query);
+        let now = Utc::now().trunc_subsecs(6);
+        let mut new_items = Vec::new();
+
+        let mut tx = self.pool.begin().await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        for item in items {
+            let id: i64 = sqlx::query_scalar(
+                r#"
+                INSERT INTO news_items (title, source, published_at, summary, url, sentiment, api_source, first_seen_at)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+                ON CONFLICT (url) DO UPDATE SET
+                    title = excluded.title,
+                    source = excluded.source,
+                    published_at = excluded.published_at,
+                    summary = excluded.summary,
+                    sentiment = excluded.sentiment,
+                    api_source = excluded.api_source
+                RETURNING id
+                "#,
+            )
+            .bind(&item.title)
+            .bind(&item.source)
+            .bind(item.published_at)
+            .bind(&item.summary)
+            .bind(&item.url)
+            .bind(&item.sentiment)
+            .bind(&item.api_source)
+            .bind(now)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to store news item: {}", e))?;
+
+            let found = sqlx::query("INSERT OR IGNORE INTO news_item_queries (news_item_id, query, found_at) VALUES (?, ?, ?)")
+                .bind(id)
+                .bind(&query)
+                .bind(now)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| format!("Failed to store news item query: {}", e))?;
+            if found.rows_affected() > 0 {
+                new_items.push(item.clone());
+            }
+        }
+
+        tx.commit().await.map_err(|e| format!("Database error: {}", e))?;
+        Ok(FoundNews { found_at: now, items: new_items })
+    }
+
+    async fn news_found_since(&self, coins: &[String], after: &FoundCursor, limit: i64) -> Result<Vec<FoundArticle>, String> {
+        if coins.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut builder = QueryBuilder::<Sqlite>::new(
+            r#"
+            SELECT n.id, q.query, q.found_at, n.title, n.source, n.published_at, n.summary, n.url, n.sentiment, n.api_source
+            FROM news_item_queries q
+            JOIN news_items n ON n.id = q.news_item_id
+            WHERE (q.found_at > "#,
+        );
+        builder.push_bind(after.found_at);
+        if let Some((coin, id)) = &after.last {
+            builder
+                .push(" OR (q.found_at = ")
+                .push_bind(after.found_at)
+                .push(" AND (q.query, q.news_item_id) > (")
+                .push_bind(coin.clone())
+                .push(", ")
+                .push_bind(*id)
+                .push("))");
+        }
+        builder.push(") AND q.query IN (");
+        let mut separated = builder.separated(", ");
+        for coin in coins {
+            separated.push_bind(canonical_coin(coin));
+        }
+        builder
+            .push(") ORDER BY q.found_at, q.query, q.news_item_id LIMIT ")
+            .push_bind(limit);
+
+        let rows = builder
+            .build()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        rows.iter()
+            .map(|row| {
+                let get_err = |column: &str, e: sqlx::Error| format!("Failed to get {}: {}", column, e);
+                Ok(FoundArticle {
+                    id: row.try_get("id").map_err(|e| get_err("id", e))?,
+                    coin: row.try_get("query").map_err(|e| get_err("query", e))?,
+                    found_at: row.try_get("found_at").map_err(|e| get_err("found_at", e))?,
+                    news: NewsItem {
+                        title: row.try_get("title").map_err(|e| get_err("title", e))?,
+                        source: row.try_get("source").map_err(|e| get_err("source", e))?,
+                        url: row.try_get("url").map_err(|e| get_err("url", e))?,
+                        published_at: row.try_get("published_at").map_err(|e| get_err("published_at", e))?,
+                        summary: row.try_get("summary").map_err(|e| get_err("summary", e))?,
+                        sentiment: row.try_get("sentiment").map_err(|e| get_err("sentiment", e))?,
+                        api_source: row.try_get("api_source").map_err(|e| get_err("api_source", e))?,
+                    },
+                })
+            })
+            .collect()
+    }
+
+    async fn list_articles(&self, filter: &ArticleFilter) -> Result<Vec<StoredArticle>, String> {
+        let mut builder = QueryBuilder::<Sqlite>::new(
+            "SELECT n.id, n.title, n.source, n.published_at, n.summary, n.url, n.sentiment, n.api_source, n.first_seen_at FROM news_items n WHERE 1 = 1",
+        );
+
+        if let Some(coin) = &filter.coin {
+            builder
+                .push(" AND EXISTS (SELECT 1 FROM news_item_queries q WHERE q.news_item_id = n.id AND q.query = ")
+                .push_bind(canonical_coin(coin))
+                .push(")");
+        }
+        if let Some(query) = &filter.query {
+            builder
+                .push(" AND (EXISTS (SELECT 1 FROM news_item_queries q WHERE q.news_item_id = n.id AND q.query = ")
+                .push_bind(canonical_coin(query))
+                .push(")");
+            if let Some(expression) = fts_match_expression(query) {
+                builder
+                    .push(" OR n.id IN (SELECT rowid FROM news_items_fts WHERE news_items_fts MATCH ")
+                    .push_bind(expression)
+                    .push(")");
+            }
+            builder.push(")");
+        }
+        if let Some(source) = &filter.source {
+            builder.push(" AND n.source = ").push_bind(source.clone());
+        }
+        if let Some(api_source) = &filter.api_source {
+            builder.push(" AND n.api_source = ").push_bind(api_source.clone());
+        }
+        if let Some(sentiment) = &filter.sentiment {
+            builder.push(" AND n.sentiment = ").push_bind(sentiment.clone());
+        }
+        if let Some(from) = filter.from {
+            builder.push(" AND n.published_at >= ").push_bind(from);
+        }
+        if let Some(to) = filter.to {
+            builder.push(" AND n.published_at <= ").push_bind(to);
+        }
+        if let Some((published_at, id)) = filter.before {
+            builder
+                .push(" AND (n.published_at < ")
+                .push_bind(published_at)
+                .push(" OR (n.published_at = ")
+                .push_bind(published_at)
+                .push(" AND n.id < ")
+                .push_bind(id)
+                .push("))");
+        }
+        builder
+            .push(" ORDER BY n.published_at DESC, n.id DESC LIMIT ")
+            .push_bind(filter.limit.unwrap_or(50));
+
+        let rows = builder
+            .build()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        rows.iter().map(stored_article_from_row).collect()
+    }
+
+    async fn search_articles(&self, query: &str, limit: i64) -> Result<Vec<ArchiveHit>, String> {
+        let Some(expression) = fts_match_expression(query) else {
+            return Ok(Vec::new());
+        };
+
+        let rows = sqlx::query(
+            r#"
+            SELECT n.id, n.title, n.source, n.published_at, n.summary, n.url, n.sentiment, n.api_source, n.first_seen_at,
+                bm25(news_items_fts, 10.0, 1.0) AS rank,
+                snippet(news_items_fts, 1, ?, ?, '…', 24) AS snippet
+            FROM news_items_fts
+            JOIN news_items n ON n.id = news_items_fts.rowid
+            WHERE news_items_fts MATCH ?
+            ORDER BY rank
+            LIMIT ?
+            "#,
+        )
+        .bind(MARK_START)
+        .bind(MARK_END)
+        .bind(expression)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(ArchiveHit {
+                    article: stored_article_from_row(row)?,
+                    rank: row.try_get("rank").map_err(|e| format!("Failed to get rank: {}", e))?,
+                    snippet: highlight(&row.try_get::<String, _>("snippet").map_err(|e| format!("Failed to get snippet: {}", e))?),
+                })
+            })
+            .collect()
+    }
+}
+
+fn user_from_row(row: &SqliteRow) -> Result<User, String> {
+    Ok(User {
+        id: row.try_get("id").map_err(|e| format!("Failed to get id: {}", e))?,
+        username: row.try_get("username").map_err(|e| format!("Failed to get username: {}", e))?,
+        email: row.try_get("email").map_err(|e| format!("Failed to get email: {}", e))?,
+        password_hash: row.try_get("password_hash").map_err(|e| format!("Failed to get password_hash: {}", e))?,
+    })
+}
+
+fn stored_article_from_row(row: &SqliteRow) -> Result<StoredArticle, String> {
+    let get_err = |column: &str, e: sqlx::Error| format!("Failed to get {}: {}", column, e);
+    Ok(StoredArticle {
+        id: row.try_get("id").map_err(|e| get_err("id", e))?,
+        news: NewsItem {
+            title: row.try_get("title").map_err(|e| get_err("title", e))?,
+            source: row.try_get("source").map_err(|e| get_err("source", e))?,
+            url: row.try_get("url").map_err(|e| get_err("url", e))?,
+            published_at: row.try_get("published_at").map_err(|e| get_err("published_at", e))?,
+            summary: row.try_get("summary").map_err(|e| get_err("summary", e))?,
+            sentiment: row.try_get("sentiment").map_err(|e| get_err("sentiment", e))?,
+            api_source: row.try_get("api_source").map_err(|e| get_err("api_source", e))?,
+        },
+        first_seen_at: row.try_get("first_seen_at").map_err(|e| get_err("first_seen_at", e))?,
+    })
+}
+
+/// Databases created before migrations were introduced have a `news_items`
+/// table that predates some columns. Bring it up to the shape the first
+/// migration expects; its `CREATE ... IF NOT EXISTS` statements then no-op.
+async fn upgrade_legacy_schema(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    let tables: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name IN ('_sqlx_migrations', 'news_items')",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    if tables.iter().any(|t| t == "_sqlx_migrations") || !tables.iter().any(|t| t == "news_items") {
+        return Ok(());
+    }
+
+    ensure_column(pool, "news_items", "sentiment", "TEXT NOT NULL DEFAULT 'Neutral'").await?;
+    ensure_column(pool, "news_items", "api_source", "TEXT NOT NULL DEFAULT ''").await?;
+    ensure_column(pool, "news_items", "first_seen_at", "TIMESTAMP NOT NULL DEFAULT '1970-01-01T00:00:00+00:00'").await?;
+    Ok(())
+}
+
+/// Turns free text into an FTS5 match expression. Every term is quoted so
+/// user input cannot inject FTS syntax.
+fn fts_match_expression(query: &str) -> Option<String> {
+    let (terms, any) = search_terms(query);
+    if terms.is_empty() {
+        return None;
+    }
+
+    let quoted = terms
+        .iter()
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect::<Vec<_>>();
+    Some(quoted.join(if any { " OR " } else { " " }))
+}
+
+async fn ensure_column(pool: &SqlitePool, table: &str, column: &str, definition: &str) -> Result<(), sqlx::Error> {
+    let exists = sqlx::query(&format!("PRAGMA table_info({})", table))
+        .fetch_all(pool)
+        .await?
+        .iter()
+        .any(|row| row.try_get::<String, _>("name").is_ok_and(|name| name == column));
+
+    if !exists {
+        sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+            .execute(pool)
+            .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::tests::news_item;
+
+    #[tokio::test]
+    async fn connect_upgrades_a_legacy_news_items_table() {
+        // Shared in memory for as long as `pool` stays open
+        let url = "sqlite:file:crypto_news_legacy?mode=memory&cache=shared";
+
+        // The shape of news_items in databases created by early builds
+        let pool = SqlitePool::connect_with(SqliteConnectOptions::from_str(url).unwrap())
+            .await
+            .unwrap();
+        sqlx::query("CREATE TABLE news_items (id INTEGER PRIMARY KEY AUTOINCREMENT, title TEXT NOT NULL, source TEXT NOT NULL, published_at TEXT NOT NULL, summary TEXT NOT NULL, url TEXT NOT NULL)")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let db = SqliteStorage::connect(url).await.unwrap();
+        db.store_news_items("btc", &[news_item("https://a", 1, "Positive", "NewsData.io")]).await.unwrap();
+        assert_eq!(db.list_articles(&ArticleFilter::default()).await.unwrap()[0].news.sentiment, "Positive");
+    }
+
+    #[tokio::test]
+    async fn bookmarked_articles_cannot_be_deleted() {
+        let db = SqliteStorage::connect("sqlite::memory:").await.unwrap();
+
+        db.create_user("satoshi", "satoshi@example.com", "hunter2").await.unwrap();
+        let user = db.find_user("satoshi").await.unwrap().unwrap().id;
+        db.store_news_items("btc", &[news_item("https://a", 1, "Neutral", "NewsData.io")]).await.unwrap();
+        let id = db.find_article_id("https://a").await.unwrap().unwrap();
+        db.save_bookmark(user, id, &BookmarkUpdate::default()).await.unwrap();
+
+        // Whatever prunes news_items has to leave bookmarked rows alone
+        let delete = || sqlx::query("DELETE FROM news_items WHERE id = ?").bind(id).execute(&db.pool);
+        assert!(delete().await.is_err());
+
+        db.delete_bookmark(user, id).await.unwrap();
+        assert!(delete().await.is_ok());
+    }
+}
@@ -0,0 +1,342 @@
+//! One suite run against every backend. SQLite always runs; the PostgreSQL
+//! variants are ignored by default and need `TEST_POSTGRES_URL` to point at
+//! a database the tests may create schemas in:
+//! `cargo test db::tests::postgres -- --ignored`.
+
+use super::*;
+use chrono::TimeZone;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// A fresh in-memory SQLite database, gone once its last connection closes.
+pub(crate) async fn test_database() -> Arc<dyn Storage> {
+    connect("sqlite::memory:").await.expect("Failed to create test database")
+}
+
+/// A fresh schema in the `TEST_POSTGRES_URL` database, and its name.
+async fn test_postgres() -> (Arc<dyn Storage>, String) {
+    let url = std::env::var("TEST_POSTGRES_URL").expect("TEST_POSTGRES_URL must be set to run the PostgreSQL tests");
+    let schema = format!(
+        "crypto_news_test_{}_{}",
+        Utc::now().timestamp_millis(),
+        COUNTER.fetch_add(1, Ordering::SeqCst)
+    );
+
+    let admin = sqlx::PgPool::connect(&url).await.expect("Failed to connect to TEST_POSTGRES_URL");
+    sqlx::query(&format!("CREATE SCHEMA {}", schema))
+        .execute(&admin)
+        .await
+        .expect("Failed to create test schema");
+    admin.close().await;
+
+    let separator = if url.contains('?') { '&' } else { '?' };
+    let storage = connect(&format!("{}{}options[search_path]={}", url, separator, schema))
+        .await
+        .expect("Failed to create test database");
+    (storage, schema)
+}
+
+async fn drop_postgres(schema: &str) {
+    let url = std::env::var("TEST_POSTGRES_URL").expect("TEST_POSTGRES_URL must be set to run the PostgreSQL tests");
+    let admin = sqlx::PgPool::connect(&url).await.expect("Failed to connect to TEST_POSTGRES_URL");
+    sqlx::query(&format!("DROP SCHEMA {} CASCADE", schema))
+        .execute(&admin)
+        .await
+        .expect("Failed to drop test schema");
+    admin.close().await;
+}
+
+pub(crate) fn news_item(url: &str, day: u32, sentiment: &str, api_source: &str) -> NewsItem {
+    NewsItem {
+        title: format!("Story {}", url),
+        source: "coindesk".to_string(),
+        url: url.to_string(),
+        published_at: Utc.with_ymd_and_hms(2025, 4, day, 12, 0, 0).unwrap(),
+        summary: "Summary".to_string(),
+        sentiment: sentiment.to_string(),
+        api_source: api_source.to_string(),
+    }
+}
+
+/// Declares a `#[tokio::test]` per backend for a suite function.
+macro_rules! storage_tests {
+    ($($name:ident),* $(,)?) => {
+        mod sqlite {
+            $(
+                #[tokio::test]
+                async fn $name() {
+                    super::$name(&*super::test_database().await).await;
+                }
+            )*
+        }
+
+        mod postgres {
+            $(
+                #[tokio::test]
+                #[ignore = "needs TEST_POSTGRES_URL"]
+                async fn $name() {
+                    let (db, schema) = super::test_postgres().await;
+                    // The schema is dropped whether or not the suite passes
+                    let result = futures_util::FutureExt::catch_unwind(std::panic::AssertUnwindSafe(super::$name(&*db))).await;
+                    drop(db);
+                    super::drop_postgres(&schema).await;
+                    if let Err(panic) = result {
+                        std::panic::resume_unwind(panic);
+                    }
+                }
+            )*
+        }
+    };
+}
+
+storage_tests!(
+    users_round_trip,
+    refresh_tokens_are_single_use,
+    watchlist_feed_follows_watched_coins,
+    bookmarks_round_trip,
+    price_points_round_trip,
+    search_articles_ranks_and_highlights_matches,
+    store_news_items_deduplicates_by_url,
+    news_found_since_replays_later_batches,
+    list_articles_applies_filters,
+);
+
+async fn users_round_trip(db: &dyn Storage) {
+    db.ping().await.unwrap();
+    db.create_user("satoshi", "satoshi@example.com", "hunter2").await.unwrap();
+
+    let user = db.verify_user("satoshi", "hunter2").await.unwrap();
+    assert_eq!(user.email, "satoshi@example.com");
+    assert_eq!(db.verify_user("satoshi", "wrong").await.unwrap_err(), "Invalid password");
+    assert_eq!(db.verify_user("hal", "hunter2").await.unwrap_err(), "User not found");
+
+    // Usernames are unique
+    assert!(db.create_user("satoshi", "other@example.com", "pw").await.is_err());
+}
+
+async fn refresh_tokens_are_single_use(db: &dyn Storage) {
+    db.create_user("satoshi", "satoshi@example.com", "hunter2").await.unwrap();
+    let user = db.find_user("satoshi").await.unwrap().unwrap();
+    assert_eq!(db.find_user_by_id(user.id).await.unwrap().unwrap().username, "satoshi");
+
+    let expires_at = Utc::now() + chrono::Duration::days(1);
+    db.store_refresh_token(user.id, "a", expires_at).await.unwrap();
+    db.store_refresh_token(user.id, "b", expires_at).await.unwrap();
+    db.store_refresh_token(user.id, "c", expires_at).await.unwrap();
+    db.store_refresh_token(user.id, "expired", Utc::now() - chrono::Duration::seconds(1)).await.unwrap();
+
+    assert_eq!(db.consume_refresh_token("a").await.unwrap(), RefreshTokenUse::Valid(user.id));
+    assert_eq!(db.consume_refresh_token("a").await.unwrap(), RefreshTokenUse::Reused(user.id));
+    assert_eq!(db.consume_refresh_token("expired").await.unwrap(), RefreshTokenUse::Invalid);
+    assert_eq!(db.consume_refresh_token("unknown").await.unwrap(), RefreshTokenUse::Invalid);
+
+    db.revoke_refresh_token("b").await.unwrap();
+    assert_eq!(db.consume_refresh_token("b").await.unwrap(), RefreshTokenUse::Reused(user.id));
+
+    db.revoke_refresh_tokens(user.id).await.unwrap();
+    assert_eq!(db.consume_refresh_token("c").await.unwrap(), RefreshTokenUse::Reused(user.id));
+}
+
+async fn watchlist_feed_follows_watched_coins(db: &dyn Storage) {
+    db.create_user("satoshi", "satoshi@example.com", "hunter2").await.unwrap();
+    db.create_user("hal", "hal@example.com", "hunter2").await.unwrap();
+    let satoshi = db.find_user("satoshi").await.unwrap().unwrap().id;
+    let hal = db.find_user("hal").await.unwrap().unwrap().id;
+
+    db.store_news_items("btc", &[news_item("https://1", 1, "Neutral", "NewsData.io")]).await.unwrap();
+    db.store_news_items("eth", &[news_item("https://2", 2, "Neutral", "NewsData.io")]).await.unwrap();
+    db.store_news_items("sol", &[news_item("https://3", 3, "Neutral", "NewsData.io")]).await.unwrap();
+
+    assert!(db.watch_coin(satoshi, "bitcoin").await.unwrap());
+    assert!(db.watch_coin(satoshi, "ethereum").await.unwrap());
+    assert!(!db.watch_coin(satoshi, "bitcoin").await.unwrap());
+    assert!(db.watch_coin(hal, "solana").await.unwrap());
+    assert_eq!(db.watched_coins(satoshi).await.unwrap(), vec!["bitcoin", "ethereum"]);
+    assert_eq!(db.all_watched_coins().await.unwrap(), vec!["bitcoin", "ethereum", "solana"]);
+
+    let urls = |articles: Vec<StoredArticle>| articles.into_iter().map(|a| a.news.url).collect::<Vec<_>>();
+    assert_eq!(urls(db.watchlist_feed(satoshi, 10).await.unwrap()), vec!["https://2", "https://1"]);
+    assert_eq!(urls(db.watchlist_feed(satoshi, 1).await.unwrap()), vec!["https://2"]);
+
+    assert!(db.unwatch_coin(satoshi, "ethereum").await.unwrap());
+    assert!(!db.unwatch_coin(satoshi, "ethereum").await.unwrap());
+    assert_eq!(urls(db.watchlist_feed(satoshi, 10).await.unwrap()), vec!["https://1"]);
+    assert_eq!(urls(db.watchlist_feed(hal, 10).await.unwrap()), vec!["https://3"]);
+}
+
+async fn bookmarks_round_trip(db: &dyn Storage) {
+    db.create_user("satoshi", "satoshi@example.com", "hunter2").await.unwrap();
+    let user = db.find_user("satoshi").await.unwrap().unwrap().id;
+    db.store_news_items("btc", &[news_item("https://a", 1, "Neutral", "NewsData.io")]).await.unwrap();
+    let id = db.find_article_id("https://a").await.unwrap().unwrap();
+    assert_eq!(db.find_article_id("https://missing").await.unwrap(), None);
+
+    let update = BookmarkUpdate { note: Some("Read later".into()), tags: Some(vec!["etf".into()]) };
+    assert!(db.save_bookmark(user, id, &update).await.unwrap());
+    assert!(!db.save_bookmark(user, id + 100, &update).await.unwrap());
+
+    let bookmarks = db.list_bookmarks(user, Some("etf")).await.unwrap();
+    assert_eq!(bookmarks.len(), 1);
+    assert_eq!(bookmarks[0].note.as_deref(), Some("Read later"));
+    assert!(db.list_bookmarks(user, Some("macro")).await.unwrap().is_empty());
+
+    let cleared = BookmarkUpdate { note: Some(" ".into()), tags: None };
+    db.save_bookmark(user, id, &cleared).await.unwrap();
+    let bookmarks = db.list_bookmarks(user, None).await.unwrap();
+    assert_eq!(bookmarks[0].note, None);
+    assert_eq!(bookmarks[0].tags, vec!["etf"]);
+
+    assert!(db.delete_bookmark(user, id).await.unwrap());
+    assert!(!db.delete_bookmark(user, id).await.unwrap());
+}
+
+async fn price_points_round_trip(db: &dyn Storage) {
+    let point = |hour: u32, close: f64| PricePoint {
+        timestamp: Utc.with_ymd_and_hms(2025, 4, 7, hour, 0, 0).unwrap(),
+        open: 1.0,
+        high: 2.0,
+        low: 0.5,
+        close,
+    };
+    db.store_price_points("bitcoin", &[point(0, 1.5), point(4, 1.6)]).await.unwrap();
+    db.store_price_points("bitcoin", &[point(4, 1.7), point(8, 1.8)]).await.unwrap();
+    db.store_price_points("ethereum", &[point(8, 9.0)]).await.unwrap();
+
+    let since = Utc.with_ymd_and_hms(2025, 4, 7, 1, 0, 0).unwrap();
+    let points = db.price_points_since("bitcoin", since).await.unwrap();
+    assert_eq!(points, vec![point(4, 1.7), point(8, 1.8)]);
+}
+
+async fn search_articles_ranks_and_highlights_matches(db: &dyn Storage) {
+    let mut title_hit = news_item("https://title", 1, "Neutral", "NewsData.io");
+    title_hit.title = "Lightning network capacity hits record".to_string();
+    let mut summary_hit = news_item("https://summary", 2, "Neutral", "NewsData.io");
+    summary_hit.summary = "Analysts <script>expect</script> the lightning rollout to continue.".to_string();
+    let mut ticker_hit = news_item("https://ticker", 3, "Neutral", "NewsData.io");
+    ticker_hit.title = "BTC dominance climbs".to_string();
+    db.store_news_items("btc", &[title_hit, summary_hit, ticker_hit]).await.unwrap();
+
+    let hits = db.search_articles("lightning", 10).await.unwrap();
+    assert_eq!(hits.len(), 2);
+    assert_eq!(hits[0].article.news.url, "https://title");
+    assert!(hits[0].rank < hits[1].rank);
+    assert!(hits[1].snippet.contains("<mark>lightning</mark>"));
+    // Feed markup never reaches the page around the highlights
+    assert!(!hits[1].snippet.contains("<script>"), "{}", hits[1].snippet);
+
+    let hits = db.search_articles("bitcoin", 10).await.unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].article.news.url, "https://ticker");
+
+    // Updates are reindexed and search syntax in user input is inert
+    let mut renamed = news_item("https://ticker", 3, "Neutral", "NewsData.io");
+    renamed.title = "Ordinals inscriptions slow".to_string();
+    db.store_news_items("btc", &[renamed]).await.unwrap();
+    assert!(db.search_articles("dominance", 10).await.unwrap().is_empty());
+    assert!(db.search_articles("ordinals OR dominance \"", 10).await.unwrap().is_empty());
+    assert_eq!(db.search_articles("ordinals", 10).await.unwrap().len(), 1);
+}
+
+async fn store_news_items_deduplicates_by_url(db: &dyn Storage) {
+    db.store_news_items("btc", &[news_item("https://a", 1, "Neutral", "NewsData.io")]).await.unwrap();
+    let mut updated = news_item("https://a", 1, "Positive", "CryptoPanic");
+    updated.title = "Updated".to_string();
+    db.store_news_items("eth", &[updated]).await.unwrap();
+
+    let all = db.list_articles(&ArticleFilter::default()).await.unwrap();
+    assert_eq!(all.len(), 1);
+    assert_eq!(all[0].news.title, "Updated");
+    assert_eq!(all[0].news.api_source, "CryptoPanic");
+
+    // Both queries that found it are remembered
+    let by_btc = db.list_articles(&ArticleFilter { coin: Some("bitcoin".into()), ..Default::default() }).await.unwrap();
+    let by_eth = db.list_articles(&ArticleFilter { coin: Some("ether".into()), ..Default::default() }).await.unwrap();
+    assert_eq!(by_btc.len(), 1);
+    assert_eq!(by_eth.len(), 1);
+
+    // Only articles a coin had not found before are reported as new for it
+    let batch = [news_item("https://a", 1, "Neutral", "NewsData.io"), news_item("https://b", 2, "Neutral", "NewsData.io")];
+    let found = db.store_news_items("bitcoin", &batch).await.unwrap();
+    assert_eq!(found.items.iter().map(|i| i.url.as_str()).collect::<Vec<_>>(), vec!["https://b"]);
+    assert!(db.store_news_items("btc", &batch).await.unwrap().items.is_empty());
+}
+
+async fn news_found_since_replays_later_batches(db: &dyn Storage) {
+    let first = db.store_news_items("btc", &[news_item("https://1", 1, "Neutral", "NewsData.io")]).await.unwrap();
+    let second = db.store_news_items("btc", &[
+        news_item("https://1", 1, "Neutral", "NewsData.io"),
+        news_item("https://2", 2, "Neutral", "NewsData.io"),
+    ]).await.unwrap();
+    db.store_news_items("sol", &[news_item("https://3", 3, "Neutral", "NewsData.io")]).await.unwrap();
+    let eth = db.store_news_items("eth", &[news_item("https://1", 1, "Neutral", "NewsData.io")]).await.unwrap();
+
+    let coins = vec!["bitcoin".to_string(), "ETH".to_string()];
+    let after = |found_at| FoundCursor { found_at, last: None };
+    let found = db.news_found_since(&coins, &after(first.found_at), 10).await.unwrap();
+    let replayed: Vec<(&str, &str)> = found.iter().map(|f| (f.coin.as_str(), f.news.url.as_str())).collect();
+    assert_eq!(replayed, vec![("bitcoin", "https://2"), ("ethereum", "https://1")]);
+    assert_eq!(found[0].found_at, second.found_at);
+    assert_eq!(found[1].found_at, eth.found_at);
+
+    assert!(db.news_found_since(&coins, &after(eth.found_at), 10).await.unwrap().is_empty());
+    assert_eq!(db.news_found_since(&coins, &after(first.found_at), 1).await.unwrap().len(), 1);
+
+    // A batch larger than the page is read across pages from its last article
+    let batch = db.store_news_items("eth", &[
+        news_item("https://4", 4, "Neutral", "NewsData.io"),
+        news_item("https://5", 5, "Neutral", "NewsData.io"),
+        news_item("https://6", 6, "Neutral", "NewsData.io"),
+    ]).await.unwrap();
+    let mut cursor = after(eth.found_at);
+    let mut urls = Vec::new();
+    loop {
+        let page = db.news_found_since(&coins, &cursor, 2).await.unwrap();
+        let Some(last) = page.last() else { break };
+        assert_eq!(last.found_at, batch.found_at);
+        cursor = last.cursor();
+        urls.extend(page.iter().map(|f| f.news.url.clone()));
+    }
+    urls.sort();
+    assert_eq!(urls, vec!["https://4", "https://5", "https://6"]);
+}
+
+async fn list_articles_applies_filters(db: &dyn Storage) {
+    db.store_news_items("btc", &[
+        news_item("https://1", 1, "Positive", "NewsData.io"),
+        news_item("https://2", 2, "Negative", "NewsData.io"),
+        news_item("https://3", 3, "Positive", "RSS Feeds"),
+    ]).await.unwrap();
+    db.store_news_items("sol", &[news_item("https://4", 4, "Positive", "NewsData.io")]).await.unwrap();
+
+    let urls = |articles: Vec<StoredArticle>| articles.into_iter().map(|a| a.news.url).collect::<Vec<_>>();
+
+    let filter = ArticleFilter { coin: Some("BTC".into()), sentiment: Some("Positive".into()), ..Default::default() };
+    assert_eq!(urls(db.list_articles(&filter).await.unwrap()), vec!["https://3", "https://1"]);
+
+    let filter = ArticleFilter { api_source: Some("NewsData.io".into()), limit: Some(2), ..Default::default() };
+    assert_eq!(urls(db.list_articles(&filter).await.unwrap()), vec!["https://4", "https://2"]);
+
+    let filter = ArticleFilter {
+        from: Some(Utc.with_ymd_and_hms(2025, 4, 2, 0, 0, 0).unwrap()),
+        to: Some(Utc.with_ymd_and_hms(2025, 4, 3, 23, 0, 0).unwrap()),
+        source: Some("coindesk".into()),
+        ..Default::default()
+    };
+    assert_eq!(urls(db.list_articles(&filter).await.unwrap()), vec!["https://3", "https://2"]);
+
+    // Pages continue strictly after the last article of the previous one
+    let page = db.list_articles(&ArticleFilter { limit: Some(2), ..Default::default() }).await.unwrap();
+    let last = page.last().unwrap();
+    let filter = ArticleFilter { before: Some((last.news.published_at, last.id)), ..Default::default() };
+    assert_eq!(urls(db.list_articles(&filter).await.unwrap()), vec!["https://2", "https://1"]);
+
+    // A query matches what it found as well as article text
+    let mut mention = news_item("https://5", 5, "Neutral", "NewsData.io");
+    mention.title = "Bitcoin miners move to Solana".to_string();
+    db.store_news_items("eth", &[mention]).await.unwrap();
+    let filter = ArticleFilter { query: Some("miners".into()), ..Default::default() };
+    assert_eq!(urls(db.list_articles(&filter).await.unwrap()), vec!["https://5"]);
+    let filter = ArticleFilter { query: Some("solana".into()), ..Default::default() };
+    assert_eq!(urls(db.list_articles(&filter).await.unwrap()), vec!["https://5", "https://4"]);
+}
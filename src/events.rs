@@ -0,0 +1,396 @@
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    Json,
+};
+use chrono::{DateTime, Utc};
+use futures_util::stream::{self, Stream};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::{BTreeSet, VecDeque};
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use utoipa::IntoParams;
+use crate::api::canonical_coin;
+use crate::auth::{ErrorResponse, NewsUpdate};
+use crate::db::{FoundArticle, FoundCursor};
+use crate::poller::Subscriptions;
+use crate::AppState;
+
+/// Most stored articles replayed to a reconnecting client.
+const REPLAY_LIMIT: i64 = 500;
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct EventsQuery {
+    /// Comma-separated tickers or names, e.g. `btc,eth`.
+    pub coins: Option<String>,
+}
+
+/// Keeps the poller tracking a stream's coins until the client disconnects.
+struct SubscriptionGuard {
+    subscriptions: Arc<Subscriptions>,
+    coins: Vec<String>,
+}
+
+impl SubscriptionGuard {
+    fn new(subscriptions: Arc<Subscriptions>, coins: Vec<String>) -> Self {
+        for coin in &coins {
+            subscriptions.add(coin);
+        }
+        SubscriptionGuard { subscriptions, coins }
+    }
+}
+
+impl Drop for SubscriptionGuard {
+    fn drop(&mut self) {
+        for coin in &self.coins {
+            self.subscriptions.remove(coin);
+        }
+    }
+}
+
+struct EventStream {
+    state: AppState,
+    updates: broadcast::Receiver<NewsUpdate>,
+    /// Updates to send, each with where the stream stands once it is sent.
+    pending: VecDeque<(NewsUpdate, FoundCursor)>,
+    /// Position of the last update sent; live updates found no later than
+    /// it are duplicates.
+    cursor: Option<FoundCursor>,
+    /// Whether the store may hold more updates after `cursor`. Live updates
+    /// are only read once the replay has caught up.
+    replaying: bool,
+    guard: SubscriptionGuard,
+}
+
+/// Event ids are the update's `found_at` in microseconds, which is what
+/// `Last-Event-ID` resumes from. The parts of an update replayed over several
+/// pages share its id.
+fn event_id(found_at: DateTime<Utc>) -> String {
+    found_at.timestamp_micros().to_string()
+}
+
+fn parse_event_id(id: &str) -> Option<DateTime<Utc>> {
+    id.trim().parse().ok().and_then(DateTime::from_timestamp_micros)
+}
+
+fn news_event(update: &NewsUpdate) -> Event {
+    let data = json!({"coin": update.coin, "items": update.news});
+    Event::default()
+        .event("news")
+        .id(event_id(update.found_at))
+        .data(data.to_string())
+}
+
+/// Regroups stored articles into the updates they were first published as,
+/// each with the cursor just past its last article. When the replay was cut
+/// off by `limit` the last group may be partial, so it is left for the next
+/// page, unless it is the only one: an update larger than a page is then
+/// sent in parts, the next page resuming within it.
+fn group_found(found: Vec<FoundArticle>, limit: i64) -> Vec<(NewsUpdate, FoundCursor)> {
+    let truncated = found.len() as i64 >= limit;
+    let mut updates: Vec<(NewsUpdate, FoundCursor)> = Vec::new();
+
+    for article in found {
+        let cursor = article.cursor();
+        match updates.last_mut() {
+            Some((update, last)) if update.coin == article.coin && update.found_at == article.found_at => {
+                update.news.push(article.news);
+                *last = cursor;
+            }
+            _ => updates.push((NewsUpdate { coin: article.coin, news: vec![article.news], found_at: article.found_at }, cursor)),
+        }
+    }
+
+    if truncated && updates.len() > 1 {
+        updates.pop();
+    }
+    updates
+}
+
+/// One page of the updates stored for `coins` after `after`, and whether a
+/// full page came back, so more may follow.
+async fn replay_page(state: &AppState, coins: &[String], after: &FoundCursor) -> (Vec<(NewsUpdate, FoundCursor)>, bool) {
+    match state.db.news_found_since(coins, after, REPLAY_LIMIT).await {
+        Ok(found) => {
+            let full = found.len() as i64 >= REPLAY_LIMIT;
+            (group_found(found, REPLAY_LIMIT), full)
+        }
+        Err(e) => {
+            tracing::error!("Failed to replay news for event stream: {}", e);
+            (Vec::new(), false)
+        }
+    }
+}
+
+fn news_stream(events: EventStream) -> impl Stream<Item = Result<Event, Infallible>> {
+    stream::unfold(events, |mut events| async move {
+        loop {
+            if let Some((update, cursor)) = events.pending.pop_front() {
+                events.cursor = Some(cursor);
+                let event = news_event(&update);
+                return Some((Ok(event), events));
+            }
+
+            // Page through the store from the advancing cursor until it has
+            // caught up, then switch to live updates
+            if let (true, Some(cursor)) = (events.replaying, &events.cursor) {
+                let (missed, full) = replay_page(&events.state, &events.guard.coins, cursor).await;
+                events.replaying = full;
+                events.pending.extend(missed);
+                continue;
+            }
+
+            match events.updates.recv().await {
+                Ok(update) => {
+                    let wanted = events.guard.coins.contains(&update.coin);
+                    let fresh = events.cursor.as_ref().is_none_or(|cursor| update.found_at > cursor.found_at);
+                    if wanted && fresh {
+                        let cursor = FoundCursor { found_at: update.found_at, last: None };
+                        events.pending.push_back((update, cursor));
+                    }
+                }
+                // Fall back to the store for whatever the channel dropped
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Event stream missed {} news updates; replaying from the store", skipped);
+                    events.state.metrics.broadcast_lagged.with_label_values(&["events"]).inc_by(skipped);
+                    events.replaying = true;
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    })
+}
+
+/// `GET /events?coins=btc,eth`: news for the given coins as Server-Sent
+/// Events, fed by the same broadcast as `/ws`. A client reconnecting with
+/// `Last-Event-ID` first gets what was stored for its coins since then.
+#[utoipa::path(
+    get,
+    path = "/events",
+    tag = "news",
+    params(
+        EventsQuery,
+        ("Last-Event-ID" = Option<String>, Header, description = "Id of the last event received, to resume from"),
+    ),
+    responses(
+        (status = 200, description = "`news` events whose data is a coin and its new items, as in `NewsUpdate`", content_type = "text/event-stream", body = NewsUpdate),
+        (status = 400, description = "No coins given", body = ErrorResponse),
+    )
+)]
+pub async fn news_events(
+    State(state): State<AppState>,
+    Query(query): Query<EventsQuery>,
+    headers: HeaderMap,
+) -> Response {
+    let coins: BTreeSet<String> = query
+        .coins
+        .unwrap_or_default()
+        .split(',')
+        .map(canonical_coin)
+        .filter(|coin| !coin.is_empty())
+        .collect();
+    if coins.is_empty() {
+        let error = ErrorResponse { error: "Pass at least one coin, e.g. /events?coins=btc,eth".to_string() };
+        return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+    }
+    let coins: Vec<String> = coins.into_iter().collect();
+
+    // Subscribe before reading the store so nothing falls in between
+    let updates = state.tx.subscribe();
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_event_id);
+
+    let guard = SubscriptionGuard::new(state.subscriptions.clone(), coins);
+    let events = EventStream {
+        state,
+        updates,
+        pending: VecDeque::new(),
+        cursor: last_event_id.map(|found_at| FoundCursor { found_at, last: None }),
+        replaying: last_event_id.is_some(),
+        guard,
+    };
+
+    Sse::new(news_stream(events))
+        .keep_alive(KeepAlive::new().interval(KEEP_ALIVE_INTERVAL).text("keep-alive"))
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::tests::news_item;
+    use axum::{routing::get, Router};
+
+    /// Reads the next `news` event as (id, data), skipping keep-alive comments.
+    async fn next_event(response: &mut reqwest::Response, buffer: &mut String) -> (String, serde_json::Value) {
+        loop {
+            if let Some(end) = buffer.find("\n\n") {
+                let block: String = buffer.drain(..end + 2).collect();
+                let field = |name: &str| {
+                    block
+                        .lines()
+                        .find_map(|line| line.strip_prefix(name))
+                        .map(|value| value.trim_start().to_string())
+                };
+                if let (Some(id), Some(data)) = (field("id:"), field("data:")) {
+                    return (id, serde_json::from_str(&data).unwrap());
+                }
+                continue;
+            }
+            let chunk = tokio::time::timeout(Duration::from_secs(5), response.chunk())
+                .await
+                .expect("no event from the server")
+                .unwrap()
+                .expect("stream ended");
+            buffer.push_str(std::str::from_utf8(&chunk).unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn events_resume_from_last_event_id_then_stream_live_news() {
+        let state = AppState::for_tests().await;
+        let seen = state.db.store_news_items("btc", &[news_item("https://seen", 1, "Neutral", "NewsData.io")]).await.unwrap();
+        let missed = state.db.store_news_items("btc", &[news_item("https://missed", 2, "Neutral", "NewsData.io")]).await.unwrap();
+        state.db.store_news_items("sol", &[news_item("https://other", 3, "Neutral", "NewsData.io")]).await.unwrap();
+
+        let app = Router::new().route("/events", get(news_events)).with_state(state.clone());
+        let base = format!("{}/events", crate::spawn_app(app).await);
+        let client = reqwest::Client::new();
+
+        assert_eq!(client.get(&base).send().await.unwrap().status(), 400);
+
+        let mut response = client
+            .get(format!("{}?coins=BTC,eth", base))
+            .header("Last-Event-ID", event_id(seen.found_at))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+        let mut buffer = String::new();
+
+        let (id, data) = next_event(&mut response, &mut buffer).await;
+        assert_eq!(id, event_id(missed.found_at));
+        assert_eq!(data["coin"], "bitcoin");
+        assert_eq!(data["items"][0]["url"], "https://missed");
+        assert_eq!(state.subscriptions.coins().len(), 2);
+
+        // A live update repeating the replayed one is skipped
+        let live = news_item("https://live", 4, "Positive", "NewsData.io");
+        state.tx.send(NewsUpdate { coin: "bitcoin".into(), news: vec![], found_at: missed.found_at }).unwrap();
+        state.tx.send(NewsUpdate { coin: "solana".into(), news: vec![live.clone()], found_at: Utc::now() }).unwrap();
+        let found_at = Utc::now();
+        state.tx.send(NewsUpdate { coin: "ethereum".into(), news: vec![live], found_at }).unwrap();
+
+        let (id, data) = next_event(&mut response, &mut buffer).await;
+        assert_eq!(id, event_id(found_at));
+        assert_eq!(data["coin"], "ethereum");
+        assert_eq!(data["items"][0]["url"], "https://live");
+    }
+
+    #[tokio::test]
+    async fn events_replay_every_missed_page_before_live_news() {
+        let state = AppState::for_tests().await;
+        let seen = state.db.store_news_items("btc", &[news_item("https://seen", 1, "Neutral", "NewsData.io")]).await.unwrap();
+        // Two batches that do not fit in one page, and one after them
+        let batch = |name: &str| -> Vec<_> {
+            (0..REPLAY_LIMIT * 3 / 5).map(|i| news_item(&format!("https://{}/{}", name, i), 2, "Neutral", "NewsData.io")).collect()
+        };
+        state.db.store_news_items("btc", &batch("first")).await.unwrap();
+        state.db.store_news_items("btc", &batch("second")).await.unwrap();
+        let last = state.db.store_news_items("btc", &[news_item("https://last", 3, "Neutral", "NewsData.io")]).await.unwrap();
+
+        let app = Router::new().route("/events", get(news_events)).with_state(state.clone());
+        let base = format!("{}/events", crate::spawn_app(app).await);
+
+        let mut response = reqwest::Client::new()
+            .get(format!("{}?coins=btc", base))
+            .header("Last-Event-ID", event_id(seen.found_at))
+            .send()
+            .await
+            .unwrap();
+        let mut buffer = String::new();
+
+        let mut sizes = Vec::new();
+        for _ in 0..3 {
+            let (_, data) = next_event(&mut response, &mut buffer).await;
+            sizes.push(data["items"].as_array().unwrap().len() as i64);
+        }
+        assert_eq!(sizes, vec![REPLAY_LIMIT * 3 / 5, REPLAY_LIMIT * 3 / 5, 1]);
+
+        let found_at = Utc::now();
+        state.tx.send(NewsUpdate { coin: "bitcoin".into(), news: vec![], found_at: last.found_at }).unwrap();
+        state.tx.send(NewsUpdate { coin: "bitcoin".into(), news: vec![], found_at }).unwrap();
+        let (id, _) = next_event(&mut response, &mut buffer).await;
+        assert_eq!(id, event_id(found_at));
+    }
+
+    #[tokio::test]
+    async fn events_replay_a_batch_larger_than_a_page_in_full() {
+        let state = AppState::for_tests().await;
+        let seen = state.db.store_news_items("btc", &[news_item("https://seen", 1, "Neutral", "NewsData.io")]).await.unwrap();
+        let batch: Vec<_> = (0..REPLAY_LIMIT + REPLAY_LIMIT / 2)
+            .map(|i| news_item(&format!("https://big/{}", i), 2, "Neutral", "NewsData.io"))
+            .collect();
+        let big = state.db.store_news_items("btc", &batch).await.unwrap();
+        state.db.store_news_items("btc", &[news_item("https://last", 3, "Neutral", "NewsData.io")]).await.unwrap();
+
+        let app = Router::new().route("/events", get(news_events)).with_state(state.clone());
+        let base = format!("{}/events", crate::spawn_app(app).await);
+
+        let mut response = reqwest::Client::new()
+            .get(format!("{}?coins=btc", base))
+            .header("Last-Event-ID", event_id(seen.found_at))
+            .send()
+            .await
+            .unwrap();
+        let mut buffer = String::new();
+
+        // The batch arrives in parts under its own id, then the next update
+        let mut urls = BTreeSet::new();
+        loop {
+            let (id, data) = next_event(&mut response, &mut buffer).await;
+            let items = data["items"].as_array().unwrap();
+            if id != event_id(big.found_at) {
+                assert_eq!(items[0]["url"], "https://last");
+                break;
+            }
+            urls.extend(items.iter().map(|item| item["url"].as_str().unwrap().to_string()));
+        }
+        assert_eq!(urls.len(), batch.len());
+    }
+
+    #[test]
+    fn truncated_replays_leave_the_last_batch_for_later() {
+        let at = |micros| DateTime::from_timestamp_micros(micros).unwrap();
+        let found = |coin: &str, micros, url| FoundArticle {
+            id: 1,
+            coin: coin.to_string(),
+            found_at: at(micros),
+            news: news_item(url, 1, "Neutral", "NewsData.io"),
+        };
+        let rows = vec![found("bitcoin", 1, "a"), found("bitcoin", 1, "b"), found("ethereum", 1, "c"), found("bitcoin", 2, "d")];
+
+        let updates = group_found(rows.clone(), 10);
+        let sizes: Vec<(&str, usize)> = updates.iter().map(|(u, _)| (u.coin.as_str(), u.news.len())).collect();
+        assert_eq!(sizes, vec![("bitcoin", 2), ("ethereum", 1), ("bitcoin", 1)]);
+
+        assert_eq!(group_found(rows.clone(), 4).len(), 2);
+
+        // A single update filling the page is kept, resuming past its last row
+        let parts = group_found(rows[..2].to_vec(), 2);
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].1, rows[1].cursor());
+        assert_eq!(parse_event_id(&event_id(at(1_700_000_000_123_456))), Some(at(1_700_000_000_123_456)));
+    }
+}
@@ -0,0 +1,358 @@
+use async_trait::async_trait;
+use futures_util::future::join_all;
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::cmp::Reverse;
+use crate::api::{analyze_sentiment, matches_query, parse_published_date, NewsItem, NewsProvider};
+
+/// Reads a fixed list of RSS 2.0 or Atom feeds. Outlets like CoinDesk, The
+/// Block and Decrypt publish these without requiring an API key.
+pub struct FeedProvider {
+    client: reqwest::Client,
+    feed_urls: Vec<String>,
+}
+
+impl FeedProvider {
+    pub fn new(feed_urls: Vec<String>) -> Self {
+        FeedProvider {
+            client: reqwest::Client::new(),
+            feed_urls,
+        }
+    }
+
+    async fn fetch_feed(&self, url: &str) -> Result<Vec<NewsItem>, String> {
+        let response = self.client.get(url).send().await.map_err(|e| e.to_string())?;
+
+        if !response.status().is_success() {
+            return Err(format!("Feed {} returned error status {}", url, response.status()));
+        }
+
+        let text = response.text().await.map_err(|e| e.to_string())?;
+        parse_feed(&text)
+    }
+}
+
+#[async_trait]
+impl NewsProvider for FeedProvider {
+    fn name(&self) -> &str {
+        "RSS Feeds"
+    }
+
+    async fn fetch(&self, query: &str) -> Result<Vec<NewsItem>, String> {
+        tracing::info!("Fetching {} feeds with query: {}", self.feed_urls.len(), query);
+
+        let results = join_all(self.feed_urls.iter().map(|url| self.fetch_feed(url))).await;
+
+        // A single broken feed should not hide the others
+        let mut news_items = Vec::new();
+        let mut errors = Vec::new();
+        for (url, result) in self.feed_urls.iter().zip(results) {
+            match result {
+                Ok(items) => news_items.extend(items),
+                Err(e) => {
+                    tracing::warn!("Failed to read feed {}: {}", url, e);
+                    errors.push(e);
+                }
+            }
+        }
+
+        if !self.feed_urls.is_empty() && errors.len() == self.feed_urls.len() {
+            return Err(errors.join("; "));
+        }
+
+        news_items.retain(|item| matches_query(item, query));
+
+        // Sort by date (newest first)
+        news_items.sort_by_key(|item| Reverse(item.published_at));
+
+        tracing::info!("Found {} news items from RSS feeds", news_items.len());
+        Ok(news_items)
+    }
+}
+
+#[derive(Default)]
+struct Entry {
+    title: String,
+    link: String,
+    summary: String,
+    published: String,
+}
+
+/// Parses an RSS 2.0 `<channel>` or an Atom `<feed>` into news items. The
+/// channel title becomes each item's `source`.
+pub fn parse_feed(xml: &str) -> Result<Vec<NewsItem>, String> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+
+    let mut feed_title = String::new();
+    let mut entries = Vec::new();
+    let mut current: Option<Entry> = None;
+    let mut path: Vec<String> = Vec::new();
+
+    loop {
+        let event = reader.read_event().map_err(|e| format!("Invalid feed XML: {}", e))?;
+        match event {
+            Event::Start(e) => {
+                let name = local_name(&e);
+                if name == "item" || name == "entry" {
+                    current = Some(Entry::default());
+                }
+                if name == "link" {
+                    if let Some(entry) = current.as_mut() {
+                        set_atom_link(entry, &e);
+                    }
+                }
+                path.push(name);
+            }
+            Event::Empty(e) if local_name(&e) == "link" => {
+                if let Some(entry) = current.as_mut() {
+                    set_atom_link(entry, &e);
+                }
+            }
+            Event::Text(e) => {
+                let text = e.unescape().map_err(|e| format!("Invalid feed XML: {}", e))?;
+                handle_text(&path, &text, &mut current, &mut feed_title);
+            }
+            Event::CData(e) => {
+                let text = String::from_utf8_lossy(&e.into_inner()).into_owned();
+                handle_text(&path, &text, &mut current, &mut feed_title);
+            }
+            Event::End(_) => {
+                if let Some(name) = path.pop() {
+                    if name == "item" || name == "entry" {
+                        if let Some(entry) = current.take() {
+                            entries.push(entry);
+                        }
+                    }
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    let mut news_items = Vec::new();
+    for entry in entries {
+        if entry.title.is_empty() || entry.link.is_empty() {
+            continue;
+        }
+
+        let published_at = match parse_published_date(&entry.published) {
+            Ok(published_at) => published_at,
+            Err(e) => {
+                tracing::warn!("Skipping feed item {}: {}", entry.link, e);
+                continue;
+            }
+        };
+
+        let summary = strip_tags(&entry.summary);
+        let sentiment = analyze_sentiment(&summary);
+
+        news_items.push(NewsItem {
+            title: entry.title,
+            url: entry.link,
+            source: feed_title.clone(),
+            published_at,
+            summary,
+            sentiment,
+            api_source: "RSS Feeds".to_string(),
+        });
+    }
+
+    Ok(news_items)
+}
+
+/// Renders items as an RSS 2.0 channel, the reverse of `parse_feed`. Each
+/// item's sentiment becomes its `<category>`.
+pub fn render_rss(title: &str, link: &str, items: &[NewsItem]) -> String {
+    let items = items
+        .iter()
+        .map(|item| {
+            format!(
+                r#"
+    <item>
+      <title>{}</title>
+      <link>{}</link>
+      <guid isPermaLink="true">{}</guid>
+      <pubDate>{}</pubDate>
+      <category>{}</category>
+      <description>{}</description>
+    </item>"#,
+                escape(&item.title),
+                escape(&item.url),
+                escape(&item.url),
+                item.published_at.to_rfc2822(),
+                escape(&item.sentiment),
+                escape(&item.summary),
+            )
+        })
+        .collect::<String>();
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0">
+  <channel>
+    <title>{}</title>
+    <link>{}</link>
+    <description>{}</description>{}
+  </channel>
+</rss>
+"#,
+        escape(title),
+        escape(link),
+        escape(title),
+        items
+    )
+}
+
+fn local_name(e: &BytesStart) -> String {
+    String::from_utf8_lossy(e.local_name().as_ref()).into_owned()
+}
+
+/// Atom carries the article URL in `<link href="..."/>`; only the
+/// `alternate` link (the default when `rel` is absent) points at the story.
+fn set_atom_link(entry: &mut Entry, e: &BytesStart) {
+    let rel = e
+        .try_get_attribute("rel")
+        .ok()
+        .flatten()
+        .and_then(|a| a.unescape_value().ok().map(|v| v.into_owned()));
+    if rel.as_deref().is_some_and(|rel| rel != "alternate") {
+        return;
+    }
+    if let Some(href) = e
+        .try_get_attribute("href")
+        .ok()
+        .flatten()
+        .and_then(|a| a.unescape_value().ok())
+    {
+        entry.link = href.into_owned();
+    }
+}
+
+fn handle_text(path: &[String], text: &str, current: &mut Option<Entry>, feed_title: &mut String) {
+    let Some(name) = path.last() else {
+        return;
+    };
+
+    match current.as_mut() {
+        Some(entry) => match name.as_str() {
+            "title" => entry.title.push_str(text),
+            "link" => entry.link.push_str(text),
+            "description" | "summary" => entry.summary.push_str(text),
+            // Atom feeds without a summary only carry the full content
+            "content" | "encoded" if entry.summary.is_empty() => entry.summary.push_str(text),
+            "pubDate" | "published" | "date" => entry.published = text.to_string(),
+            "updated" if entry.published.is_empty() => entry.published = text.to_string(),
+            _ => {}
+        },
+        None => {
+            if name == "title" && feed_title.is_empty() {
+                feed_title.push_str(text);
+            }
+        }
+    }
+}
+
+fn strip_tags(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Router};
+    use chrono::{TimeZone, Utc};
+
+    const RSS: &str = include_str!("../tests/fixtures/coindesk.rss");
+    const ATOM: &str = include_str!("../tests/fixtures/decrypt.atom");
+
+    #[test]
+    fn parse_feed_reads_rss() {
+        let items = parse_feed(RSS).unwrap();
+
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].title, "Bitcoin climbs past $70K as ETF demand returns");
+        assert_eq!(items[0].url, "https://www.coindesk.com/markets/bitcoin-climbs");
+        assert_eq!(items[0].source, "CoinDesk");
+        assert_eq!(items[0].published_at, Utc.with_ymd_and_hms(2025, 4, 7, 13, 15, 0).unwrap());
+        assert_eq!(items[0].summary, "Spot ETFs saw inflows for a third day.");
+        assert_eq!(items[0].api_source, "RSS Feeds");
+    }
+
+    #[test]
+    fn parse_feed_reads_atom() {
+        let items = parse_feed(ATOM).unwrap();
+
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].url, "https://decrypt.co/ethereum-pectra");
+        assert_eq!(items[0].source, "Decrypt");
+        assert_eq!(items[0].published_at, Utc.with_ymd_and_hms(2025, 4, 7, 8, 0, 0).unwrap());
+        // Falls back to <updated> when <published> is missing
+        assert_eq!(items[1].published_at, Utc.with_ymd_and_hms(2025, 4, 6, 22, 30, 0).unwrap());
+    }
+
+    #[test]
+    fn parse_feed_rejects_malformed_xml() {
+        assert!(parse_feed("<rss><channel><item></channel>").is_err());
+    }
+
+    #[test]
+    fn render_rss_round_trips_through_parse_feed() {
+        let mut items = parse_feed(RSS).unwrap();
+        items[0].title = "BTC <up> & \"away\"".to_string();
+
+        let xml = render_rss("Crypto News - btc & eth", "http://localhost/search?q=btc&mode=live", &items);
+        assert!(xml.contains("<link>http://localhost/search?q=btc&amp;mode=live</link>"));
+
+        let parsed = parse_feed(&xml).unwrap();
+        assert_eq!(parsed.len(), items.len());
+        assert_eq!(parsed[0].title, items[0].title);
+        assert_eq!(parsed[0].url, items[0].url);
+        assert_eq!(parsed[0].published_at, items[0].published_at);
+        assert_eq!(parsed[0].summary, items[0].summary);
+        assert_eq!(parsed[0].source, "Crypto News - btc & eth");
+    }
+
+    #[tokio::test]
+    async fn fetch_filters_items_against_the_query() {
+        let app = Router::new()
+            .route("/coindesk.rss", get(|| async { RSS }))
+            .route("/decrypt.atom", get(|| async { ATOM }));
+        let base = crate::spawn_app(app).await;
+
+        let provider = FeedProvider::new(vec![
+            format!("{}/coindesk.rss", base),
+            format!("{}/decrypt.atom", base),
+            format!("{}/missing.rss", base),
+        ]);
+
+        let items = provider.fetch("BTC").await.unwrap();
+        let titles: Vec<&str> = items.iter().map(|i| i.title.as_str()).collect();
+        assert_eq!(
+            titles,
+            vec![
+                "Bitcoin climbs past $70K as ETF demand returns",
+                "Miners sell BTC reserves",
+            ]
+        );
+
+        let items = provider.fetch("ether").await.unwrap();
+        assert_eq!(items.len(), 2);
+        assert!(items.iter().all(|i| i.source == "Decrypt"));
+    }
+}
@@ -0,0 +1,238 @@
+use axum::{extract::State, http::StatusCode, Json};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+use crate::AppState;
+
+/// How long a single dependency check may take before it counts as failed.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+/// Providers that have not answered successfully for this long are reported
+/// as stale. The poller fetches every five minutes by default.
+const PROVIDER_STALE_AFTER: chrono::Duration = chrono::Duration::hours(1);
+
+/// `ok` when everything works; `degraded` when the service still answers
+/// but with less (no Redis, stale providers); `unavailable` when it cannot
+/// serve requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Ok,
+    Degraded,
+    Unavailable,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Liveness {
+    pub status: Status,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Check {
+    pub status: Status,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CacheTierCheck {
+    /// `memory` or `redis`, in lookup order.
+    pub backend: String,
+    #[serde(flatten)]
+    pub check: Check,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ProviderCheck {
+    pub api_source: String,
+    /// Absent until the provider has answered successfully once.
+    pub last_success: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ProvidersCheck {
+    pub status: Status,
+    pub providers: Vec<ProviderCheck>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Readiness {
+    /// The worst status of the checks below.
+    pub status: Status,
+    pub database: Check,
+    pub cache: Vec<CacheTierCheck>,
+    pub providers: ProvidersCheck,
+}
+
+/// `GET /healthz`: liveness. Answers whenever the server is running, so an
+/// orchestrator restarts the process only when it hangs or dies.
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    tag = "health",
+    responses((status = 200, description = "The server is running", body = Liveness))
+)]
+pub async fn healthz() -> Json<Liveness> {
+    Json(Liveness { status: Status::Ok })
+}
+
+/// `GET /readyz`: readiness. 503 when the database cannot be reached, since
+/// nothing works without it; a missing Redis or stale providers are
+/// reported as `degraded` with 200, because cached and archived news are
+/// still served.
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "health",
+    responses(
+        (status = 200, description = "Ready, possibly degraded", body = Readiness),
+        (status = 503, description = "The database is unavailable", body = Readiness),
+    )
+)]
+pub async fn readyz(State(state): State<AppState>) -> (StatusCode, Json<Readiness>) {
+    let database = check(state.db.ping(), Status::Unavailable).await;
+    let cache = cache_checks(&state).await;
+    let providers = provider_checks(&state, Utc::now());
+
+    let status = [database.status, providers.status]
+        .into_iter()
+        .chain(cache.iter().map(|tier| tier.check.status))
+        .max()
+        .unwrap_or(Status::Ok);
+    let code = if status == Status::Unavailable {
+        StatusCode::SERVICE_UNAVAILABLE
+    } else {
+        StatusCode::OK
+    };
+
+    (code, Json(Readiness { status, database, cache, providers }))
+}
+
+/// Runs one check, reporting `failed` when it errors or times out.
+async fn check(probe: impl Future<Output = Result<(), String>>, failed: Status) -> Check {
+    let started = Instant::now();
+    let result = tokio::time::timeout(CHECK_TIMEOUT, probe)
+        .await
+        .unwrap_or_else(|_| Err(format!("No answer within {:?}", CHECK_TIMEOUT)));
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    match result {
+        Ok(()) => Check { status: Status::Ok, latency_ms, error: None },
+        Err(error) => Check { status: failed, latency_ms, error: Some(error) },
+    }
+}
+
+/// A cache tier that is down only degrades the service: reads fall through
+/// to the next tier or to the providers.
+async fn cache_checks(state: &AppState) -> Vec<CacheTierCheck> {
+    let started = Instant::now();
+    let pings = tokio::time::timeout(CHECK_TIMEOUT, state.cache.ping()).await;
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    match pings {
+        Ok(pings) => pings
+            .into_iter()
+            .map(|(backend, result)| CacheTierCheck {
+                backend: backend.to_string(),
+                check: match result {
+                    Ok(()) => Check { status: Status::Ok, latency_ms, error: None },
+                    Err(error) => Check { status: Status::Degraded, latency_ms, error: Some(error) },
+                },
+            })
+            .collect(),
+        Err(_) => vec![CacheTierCheck {
+            backend: "cache".to_string(),
+            check: Check {
+                status: Status::Degraded,
+                latency_ms,
+                error: Some(format!("No answer within {:?}", CHECK_TIMEOUT)),
+            },
+        }],
+    }
+}
+
+/// Degraded when no provider is configured or none has succeeded within
+/// `PROVIDER_STALE_AFTER`. Right after startup, before the first fetch,
+/// providers are not held against readiness.
+fn provider_checks(state: &AppState, now: DateTime<Utc>) -> ProvidersCheck {
+    let providers: Vec<ProviderCheck> = state
+        .providers
+        .iter()
+        .map(|provider| ProviderCheck {
+            api_source: provider.name().to_string(),
+            last_success: state
+                .metrics
+                .last_provider_success(provider.name())
+                .and_then(|seconds| DateTime::from_timestamp_micros((seconds * 1e6) as i64)),
+        })
+        .collect();
+
+    let last_success = providers.iter().filter_map(|provider| provider.last_success).max();
+    let attempted = state.metrics.provider_calls() > 0;
+    let status = match last_success {
+        _ if providers.is_empty() => Status::Degraded,
+        Some(last_success) if now - last_success <= PROVIDER_STALE_AFTER => Status::Ok,
+        None if !attempted => Status::Ok,
+        _ => Status::Degraded,
+    };
+
+    ProvidersCheck { status, providers }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::tests::StaticProvider;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn readiness_reports_each_dependency() {
+        let mut state = AppState::for_tests().await;
+        state.providers = Arc::new(vec![Box::new(StaticProvider { name: "A", result: Err("down".to_string()) })]);
+
+        // Nothing has been fetched yet
+        let (code, Json(ready)) = readyz(State(state.clone())).await;
+        assert_eq!(code, StatusCode::OK);
+        assert_eq!(ready.status, Status::Ok);
+        assert_eq!(ready.database.status, Status::Ok);
+        assert_eq!(ready.cache[0].backend, "memory");
+        assert_eq!(ready.providers.providers[0].last_success, None);
+
+        // A failed fetch with no success before it
+        state.metrics.observe_provider("A", Duration::from_millis(5), false);
+        let (code, Json(ready)) = readyz(State(state.clone())).await;
+        assert_eq!(code, StatusCode::OK);
+        assert_eq!(ready.status, Status::Degraded);
+        assert_eq!(ready.providers.status, Status::Degraded);
+
+        state.metrics.observe_provider("A", Duration::from_millis(5), true);
+        let providers = provider_checks(&state, Utc::now());
+        assert_eq!(providers.status, Status::Ok);
+        assert!(providers.providers[0].last_success.is_some());
+        let later = provider_checks(&state, Utc::now() + PROVIDER_STALE_AFTER + chrono::Duration::minutes(1));
+        assert_eq!(later.status, Status::Degraded);
+    }
+
+    #[tokio::test]
+    async fn unreachable_dependencies_set_the_status_code() {
+        let state = AppState::for_tests().await;
+        let cache = crate::cache::Cache::new(vec![
+            Box::new(crate::cache::MemoryBackend::new(10)),
+            Box::new(crate::cache::RedisBackend::new("redis://127.0.0.1:9/").unwrap()),
+        ]);
+        let state = AppState { cache: Arc::new(cache), ..state };
+
+        let (code, Json(ready)) = readyz(State(state)).await;
+        assert_eq!(code, StatusCode::OK);
+        assert_eq!(ready.status, Status::Degraded);
+        assert_eq!(ready.cache[1].backend, "redis");
+        assert_eq!(ready.cache[1].check.status, Status::Degraded);
+        assert!(ready.cache[1].check.error.is_some());
+
+        let failing = check(async { Err("connection refused".to_string()) }, Status::Unavailable).await;
+        assert_eq!(failing.status, Status::Unavailable);
+        assert_eq!(healthz().await.status, Status::Ok);
+    }
+}
@@ -1,32 +1,154 @@
 mod api; 
+mod api_v1;
 mod routes; 
 mod cache; 
 mod auth;
+mod bookmarks;
+mod config;
 mod db;
+mod events;
+mod feeds;
+mod health;
+mod metrics;
+mod news;
+mod openapi;
+mod poller;
+mod prices;
+mod singleflight;
+mod trending;
+mod watchlist;
+mod ws;
 
 use axum::{
+    middleware,
     Router,
-    routing::{get, post},
+    routing::{delete, get, post},
     http::{HeaderValue, Method},
 };
 use std::sync::Arc;
-use cache::RedisCache;
+use cache::Cache;
+use singleflight::SingleFlight;
 use tower_http::cors::{CorsLayer, Any};
 use tower_http::trace::TraceLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use tokio::sync::broadcast;
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
 
 use crate::{
-    auth::{login_page, register_page, handle_login, handle_register, NewsUpdate},
+    api::Providers,
+    auth::{login_page, register_page, handle_login, handle_register, JwtKeys, NewsUpdate},
+    config::Config,
+    prices::{CoinGeckoProvider, PriceProvider},
     routes::homepage,
-    db::Database,
+    db::Storage,
 };
 
 #[derive(Clone)]
 pub struct AppState {
-    pub cache: Arc<RedisCache>,
+    pub cache: Arc<Cache>,
     pub tx: broadcast::Sender<NewsUpdate>,
-    pub db: Arc<Database>,
+    pub db: Arc<dyn Storage>,
+    pub providers: Providers,
+    pub prices: Arc<dyn PriceProvider>,
+    pub jwt: Arc<JwtKeys>,
+    pub subscriptions: Arc<poller::Subscriptions>,
+    /// Live news fetches in progress, by search term.
+    pub news_fetches: Arc<SingleFlight<Result<news::CachedNews, String>>>,
+    pub metrics: Arc<metrics::Metrics>,
+}
+
+#[cfg(test)]
+impl AppState {
+    /// State backed by a fresh SQLite database and an in-memory cache, with
+    /// no news providers and a price provider that cannot be reached.
+    pub(crate) async fn for_tests() -> Self {
+        let (tx, _) = broadcast::channel(100);
+        AppState {
+            cache: Arc::new(Cache::new(vec![Box::new(cache::MemoryBackend::new(1_000))])),
+            tx,
+            db: db::tests::test_database().await,
+            providers: Arc::new(Vec::new()),
+            prices: Arc::new(CoinGeckoProvider::new("http://127.0.0.1:9".to_string(), None)),
+            jwt: Arc::new(JwtKeys::new(b"test-secret")),
+            subscriptions: Arc::new(poller::Subscriptions::default()),
+            news_fetches: Arc::new(SingleFlight::default()),
+            metrics: Arc::new(metrics::Metrics::default()),
+        }
+    }
+}
+
+/// Serves `router` on a free local port and returns its base URL, e.g.
+/// `http://127.0.0.1:41234`.
+#[cfg(test)]
+pub(crate) async fn spawn_app(router: Router) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+    base
+}
+
+/// Every route of the server, shared by `main` and the tests.
+pub(crate) fn app(state: AppState) -> Router {
+    let cors = CorsLayer::new()
+        .allow_origin(HeaderValue::from_static("*"))
+        .allow_methods([Method::GET, Method::POST, Method::DELETE])
+        .allow_headers(Any);
+
+    Router::new()
+        .route("/", get(homepage))
+        .route("/login", get(login_page).post(handle_login))
+        .route("/register", get(register_page).post(handle_register))
+        .route("/refresh", post(auth::handle_refresh))
+        .route("/logout", post(auth::handle_logout))
+        .route("/logout-all", post(auth::handle_logout_all))
+        .route("/me", get(auth::current_user))
+        .route("/watchlist", get(watchlist::list_watchlist).post(watchlist::add_to_watchlist))
+        .route("/watchlist/:coin", delete(watchlist::remove_from_watchlist))
+        .route(
+            "/bookmarks",
+            get(bookmarks::bookmarks_page)
+                .post(bookmarks::save_bookmark)
+                .delete(bookmarks::delete_bookmark),
+        )
+        .route("/bookmarks.json", get(bookmarks::list_bookmarks))
+        .route("/ws", get(ws::handle_ws))
+        .route("/events", get(events::news_events))
+        .route("/search", get(routes::handle_search))
+        .route("/search", post(routes::handle_search_post))
+        .route("/stats", get(routes::cache_stats))
+        .route("/metrics", get(metrics::metrics))
+        .route("/healthz", get(health::healthz))
+        .route("/readyz", get(health::readyz))
+        .nest("/api/v1", api_v1::router())
+        .merge(SwaggerUi::new("/api/docs").url("/api/openapi.json", openapi::ApiDoc::openapi()))
+        .layer(middleware::from_fn_with_state(state.clone(), metrics::track_requests))
+        .layer(cors)
+        .layer(TraceLayer::new_for_http())
+        .with_state(state)
+}
+
+/// Attempts at opening the database at startup, one, two, four... seconds
+/// apart, so a database that starts just after the server does not stop it.
+const DB_CONNECT_ATTEMPTS: u32 = 5;
+
+async fn connect_database(database_url: &str) -> Result<Arc<dyn Storage>, sqlx::Error> {
+    let mut delay = std::time::Duration::from_secs(1);
+    let mut attempt = 1;
+    loop {
+        match db::connect(database_url).await {
+            Err(e) if attempt < DB_CONNECT_ATTEMPTS => {
+                tracing::warn!(
+                    "Database unavailable (attempt {} of {}): {}; retrying in {:?}",
+                    attempt, DB_CONNECT_ATTEMPTS, e, delay
+                );
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
 }
 
 #[tokio::main]
@@ -38,34 +160,66 @@ async fn main() {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    dotenv::dotenv().ok();
+    let config = Config::from_env();
+
+    let db = match connect_database(&config.database_url).await {
+        Ok(db) => db,
+        Err(e) => {
+            tracing::error!("Failed to initialize database: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // Apply pending migrations and exit, e.g. as a deploy step before rollout
+    if std::env::args().any(|arg| arg == "--migrate-only") {
+        println!("Database migrations applied");
+        return;
+    }
+
+    let providers: Providers = Arc::new(api::enabled_providers(&config));
+    if providers.is_empty() {
+        tracing::warn!("No news providers are configured; searches will fail");
+    }
 
-    let cache = Arc::new(RedisCache::new());
+    let prices: Arc<dyn PriceProvider> = Arc::new(CoinGeckoProvider::new(
+        config.coingecko_base_url.clone(),
+        config.coingecko_api_key.clone(),
+    ));
+
+    let jwt = match &config.jwt_secret {
+        Some(secret) => JwtKeys::new(secret.as_bytes()),
+        None => {
+            tracing::warn!("JWT_SECRET is not set; using a random secret, so sessions end on restart");
+            JwtKeys::new(&rand::random::<[u8; 32]>())
+        }
+    };
+
+    let cache = Arc::new(Cache::from_config(&config).expect("Failed to configure the cache"));
     let (tx, _) = broadcast::channel(100);
-    let db = Arc::new(Database::new().await.expect("Failed to initialize database"));
     
     let state = AppState {
         cache: cache.clone(),
         tx: tx.clone(),
         db: db.clone(),
+        providers,
+        prices,
+        jwt: Arc::new(jwt),
+        subscriptions: Arc::new(poller::Subscriptions::default()),
+        news_fetches: Arc::new(SingleFlight::default()),
+        metrics: Arc::new(metrics::Metrics::default()),
     };
 
-    let cors = CorsLayer::new()
-        .allow_origin(HeaderValue::from_static("*"))
-        .allow_methods([Method::GET, Method::POST])
-        .allow_headers(Any);
+    if config.news_poll_interval_secs > 0 {
+        tokio::spawn(poller::run(
+            state.clone(),
+            std::time::Duration::from_secs(config.news_poll_interval_secs),
+            config.news_poll_coins.clone(),
+        ));
+    } else {
+        tracing::info!("News polling is disabled");
+    }
 
-    let app = Router::new()
-        .route("/", get(homepage))
-        .route("/login", get(login_page).post(handle_login))
-        .route("/register", get(register_page).post(handle_register))
-        .route("/ws", get(auth::handle_ws))
-        .route("/search", get(routes::handle_search))
-        .route("/search", post(routes::handle_search_post))
-        .route("/stats", get(routes::cache_stats))
-        .layer(cors)
-        .layer(TraceLayer::new_for_http())
-        .with_state(state);
+    let app = app(state);
 
     let listener = tokio::net::TcpListener::bind("127.0.0.1:8000").await.unwrap();
     println!("Server running at http://127.0.0.1:8000");
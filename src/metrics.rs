@@ -0,0 +1,221 @@
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::header,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use prometheus::core::Collector;
+use prometheus::proto::{Metric, MetricFamily};
+use prometheus::{Encoder, GaugeVec, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry, TextEncoder};
+use std::time::{Duration, Instant};
+use crate::AppState;
+
+/// Everything `/metrics` reports apart from the cache, which keeps its own
+/// counters.
+pub struct Metrics {
+    registry: Registry,
+    pub http_requests: IntCounterVec,
+    pub http_duration: HistogramVec,
+    pub provider_duration: HistogramVec,
+    pub provider_errors: IntCounterVec,
+    /// Unix time of each provider's last successful fetch.
+    pub provider_last_success: GaugeVec,
+    pub ws_connections: IntGauge,
+    pub broadcast_lagged: IntCounterVec,
+    /// Set from the channel length on every scrape.
+    broadcast_queued: IntGauge,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        let registry = Registry::new();
+        let metrics = Metrics {
+            http_requests: IntCounterVec::new(
+                Opts::new("http_requests_total", "HTTP requests by method, route and status."),
+                &["method", "route", "status"],
+            )
+            .unwrap(),
+            http_duration: HistogramVec::new(
+                HistogramOpts::new("http_request_duration_seconds", "HTTP request latency by method and route."),
+                &["method", "route"],
+            )
+            .unwrap(),
+            provider_duration: HistogramVec::new(
+                HistogramOpts::new("news_provider_request_duration_seconds", "Latency of upstream news provider calls."),
+                &["api_source"],
+            )
+            .unwrap(),
+            provider_errors: IntCounterVec::new(
+                Opts::new("news_provider_errors_total", "Failed upstream news provider calls."),
+                &["api_source"],
+            )
+            .unwrap(),
+            provider_last_success: GaugeVec::new(
+                Opts::new(
+                    "news_provider_last_success_timestamp_seconds",
+                    "Unix time of the last successful call to each news provider.",
+                ),
+                &["api_source"],
+            )
+            .unwrap(),
+            ws_connections: IntGauge::new("websocket_connections", "Open WebSocket connections.").unwrap(),
+            broadcast_lagged: IntCounterVec::new(
+                Opts::new(
+                    "news_broadcast_lagged_updates_total",
+                    "News updates dropped because a subscriber fell behind the broadcast channel.",
+                ),
+                &["consumer"],
+            )
+            .unwrap(),
+            broadcast_queued: IntGauge::new(
+                "news_broadcast_queued_updates",
+                "News updates the slowest subscriber has yet to receive.",
+            )
+            .unwrap(),
+            registry,
+        };
+
+        let collectors: [Box<dyn Collector>; 8] = [
+            Box::new(metrics.http_requests.clone()),
+            Box::new(metrics.http_duration.clone()),
+            Box::new(metrics.provider_duration.clone()),
+            Box::new(metrics.provider_errors.clone()),
+            Box::new(metrics.provider_last_success.clone()),
+            Box::new(metrics.ws_connections.clone()),
+            Box::new(metrics.broadcast_lagged.clone()),
+            Box::new(metrics.broadcast_queued.clone()),
+        ];
+        for collector in collectors {
+            metrics.registry.register(collector).expect("metric names are unique");
+        }
+        metrics
+    }
+}
+
+impl Metrics {
+    /// Records one upstream call to `api_source`.
+    pub fn observe_provider(&self, api_source: &str, elapsed: Duration, ok: bool) {
+        self.provider_duration.with_label_values(&[api_source]).observe(elapsed.as_secs_f64());
+        if ok {
+            let now = chrono::Utc::now().timestamp_micros() as f64 / 1e6;
+            self.provider_last_success.with_label_values(&[api_source]).set(now);
+        } else {
+            self.provider_errors.with_label_values(&[api_source]).inc();
+        }
+    }
+
+    /// Unix time of `api_source`'s last successful call, if it ever had one.
+    pub fn last_provider_success(&self, api_source: &str) -> Option<f64> {
+        series(&self.provider_last_success)
+            .find(|metric| metric.get_label()[0].get_value() == api_source)
+            .map(|metric| metric.get_gauge().get_value())
+    }
+
+    /// Upstream calls made to any provider.
+    pub fn provider_calls(&self) -> u64 {
+        series(&self.provider_duration).map(|metric| metric.get_histogram().get_sample_count()).sum()
+    }
+
+    /// Every registered family, plus `extra`, in text exposition format.
+    /// Families without series are left out, as `Registry::gather` does.
+    pub fn render(&self, extra: Vec<MetricFamily>) -> String {
+        let mut families = self.registry.gather();
+        families.extend(extra.into_iter().filter(|family| !family.get_metric().is_empty()));
+        let mut out = Vec::new();
+        TextEncoder::new().encode(&families, &mut out).expect("text encoding does not fail");
+        String::from_utf8(out).expect("text exposition is UTF-8")
+    }
+}
+
+/// The current series of a metric vector.
+pub fn series(collector: &impl Collector) -> impl Iterator<Item = Metric> {
+    collector.collect().into_iter().flat_map(|mut family| family.take_metric())
+}
+
+/// Counts an open WebSocket connection until dropped.
+pub struct ConnectionGuard<'a>(&'a Metrics);
+
+impl<'a> ConnectionGuard<'a> {
+    pub fn new(metrics: &'a Metrics) -> Self {
+        metrics.ws_connections.inc();
+        ConnectionGuard(metrics)
+    }
+}
+
+impl Drop for ConnectionGuard<'_> {
+    fn drop(&mut self) {
+        self.0.ws_connections.dec();
+    }
+}
+
+/// Middleware recording request counts and latency by route template, so
+/// `/watchlist/btc` and `/watchlist/eth` share one series.
+pub async fn track_requests(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let started = Instant::now();
+    let response = next.run(request).await;
+
+    let status = response.status().as_u16().to_string();
+    state.metrics.http_requests.with_label_values(&[&method, &route, &status]).inc();
+    state.metrics.http_duration.with_label_values(&[&method, &route]).observe(started.elapsed().as_secs_f64());
+    response
+}
+
+/// `GET /metrics`: Prometheus text exposition format.
+pub async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
+    state.metrics.broadcast_queued.set(state.tx.len() as i64);
+    let out = state.metrics.render(state.cache.metric_families());
+
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")], out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn families_render_in_text_format() {
+        let metrics = Metrics::default();
+        metrics.http_requests.with_label_values(&["GET", "/search", "200"]).inc_by(2);
+        metrics.observe_provider("NewsData.io", Duration::from_millis(30), true);
+        metrics.observe_provider("Say \"hi\"", Duration::from_secs(20), false);
+
+        let out = metrics.render(Vec::new());
+
+        assert!(out.contains("# TYPE http_requests_total counter\n"));
+        assert!(out.contains("http_requests_total{method=\"GET\",route=\"/search\",status=\"200\"} 2\n"));
+        assert!(out.contains("news_provider_request_duration_seconds_bucket{api_source=\"NewsData.io\",le=\"0.025\"} 0\n"));
+        assert!(out.contains("news_provider_request_duration_seconds_bucket{api_source=\"NewsData.io\",le=\"0.05\"} 1\n"));
+        assert!(out.contains("news_provider_request_duration_seconds_bucket{api_source=\"Say \\\"hi\\\"\",le=\"+Inf\"} 1\n"));
+        assert!(out.contains("news_provider_errors_total{api_source=\"Say \\\"hi\\\"\"} 1\n"));
+        assert!(out.contains("websocket_connections 0\n"));
+        assert!(out.contains("news_provider_last_success_timestamp_seconds{api_source=\"NewsData.io\"} 1"));
+    }
+
+    #[tokio::test]
+    async fn requests_are_counted_by_route_template() {
+        let state = AppState::for_tests().await;
+        let base = crate::spawn_app(crate::app(state)).await;
+        let client = reqwest::Client::new();
+
+        for coin in ["btc", "eth"] {
+            let response = client.delete(format!("{}/watchlist/{}", base, coin)).send().await.unwrap();
+            assert_eq!(response.status(), 401);
+        }
+        client.get(format!("{}/api/v1/coins/btc", base)).send().await.unwrap();
+
+        let response = client.get(format!("{}/metrics", base)).send().await.unwrap();
+        assert_eq!(response.headers()["content-type"], "text/plain; version=0.0.4; charset=utf-8");
+        let body = response.text().await.unwrap();
+        assert!(body.contains("http_requests_total{method=\"DELETE\",route=\"/watchlist/:coin\",status=\"401\"} 2\n"), "{}", body);
+        assert!(body.contains("http_request_duration_seconds_count{method=\"GET\",route=\"/api/v1/coins/:symbol\"} 1\n"));
+        assert!(body.contains("cache_misses_total{prefix=\"price\"} 1\n"));
+        assert!(body.contains("news_broadcast_queued_updates 0\n"));
+    }
+}
@@ -0,0 +1,151 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::{api, AppState};
+use crate::api::{canonical_coin, NewsFetch};
+use crate::auth::NewsUpdate;
+use crate::cache::Lookup;
+
+/// Fetches from every enabled provider and archives the result, so articles
+/// outlive the Redis entry that first served them. Articles the query had not
+/// found before are published to WebSocket subscribers. A failed write is
+/// logged but does not fail the request.
+pub async fn fetch_and_store(state: &AppState, query: &str) -> Result<NewsFetch, String> {
+    let fetch = api::fetch_news(&state.providers, &state.metrics, query).await?;
+
+    match state.db.store_news_items(query, &fetch.items).await {
+        Ok(found) if !found.items.is_empty() => {
+            // Sending only fails when nobody is subscribed
+            let _ = state.tx.send(NewsUpdate { coin: canonical_coin(query), news: found.items, found_at: found.found_at });
+        }
+        Ok(_) => {}
+        Err(e) => tracing::error!("Failed to persist news for {}: {}", query, e),
+    }
+
+    Ok(fetch)
+}
+
+/// A live fetch as it is cached: the merged items plus when and for what
+/// they were fetched. Pages, feeds and sockets all render from this, so
+/// every output for a term shows the same results.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedNews {
+    pub query: String,
+    pub fetched_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub fetch: NewsFetch,
+}
+
+/// Cached results are served without refetching for this long...
+const FRESH_SECS: u64 = 5 * 60;
+/// ...and, while a background refresh runs, for up to this long.
+const STALE_SECS: u64 = 60 * 60;
+
+/// Live results for a normalized search term, cached in Redis. Within
+/// `FRESH_SECS` of a fetch the cached results are returned as they are;
+/// after that they are still returned at once, but a refresh is started for
+/// the next request. Only one upstream fetch per term runs at a time, however
+/// many requests miss the cache together.
+pub async fn cached_fetch(state: &AppState, term: &str) -> Result<CachedNews, String> {
+    let cache_key = format!("news:{}", term);
+
+    let cached = state.cache.get_with_soft_ttl(&cache_key).await.and_then(|lookup| {
+        let stale = matches!(lookup, Lookup::Stale(_));
+        let news = serde_json::from_str::<CachedNews>(&lookup.into_value()).ok()?;
+        Some((news, stale))
+    });
+
+    match cached {
+        Some((news, false)) => Ok(news),
+        Some((news, true)) => {
+            let state = state.clone();
+            let term = term.to_string();
+            tokio::spawn(async move {
+                if let Err(e) = coalesced_refresh(&state, &term).await {
+                    tracing::warn!("Background refresh of {} failed: {}", term, e);
+                }
+            });
+            Ok(news)
+        }
+        None => coalesced_refresh(state, term).await,
+    }
+}
+
+async fn coalesced_refresh(state: &AppState, term: &str) -> Result<CachedNews, String> {
+    let refresh = refresh(state.clone(), term.to_string());
+    state.news_fetches.run(term, refresh).await
+}
+
+async fn refresh(state: AppState, term: String) -> Result<CachedNews, String> {
+    let fetch = fetch_and_store(&state, &term).await?;
+    let news = CachedNews { query: term.clone(), fetched_at: Utc::now(), fetch };
+    if let Ok(json) = serde_json::to_string(&news) {
+        state.cache.set_with_soft_ttl(&format!("news:{}", term), &json, FRESH_SECS, STALE_SECS).await;
+    }
+    Ok(news)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{NewsItem, NewsProvider};
+    use crate::db::tests::news_item;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    /// Counts upstream calls; each one takes a while to answer.
+    struct CountingProvider {
+        calls: Arc<AtomicU32>,
+    }
+
+    #[async_trait]
+    impl NewsProvider for CountingProvider {
+        fn name(&self) -> &str {
+            "Counting"
+        }
+
+        async fn fetch(&self, query: &str) -> Result<Vec<NewsItem>, String> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            Ok(vec![news_item(&format!("https://example.com/{}", query), 1, "Neutral", "Counting")])
+        }
+    }
+
+    #[tokio::test]
+    async fn concurrent_misses_share_one_upstream_fetch() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let mut state = AppState::for_tests().await;
+        state.providers = Arc::new(vec![Box::new(CountingProvider { calls: calls.clone() })]);
+
+        let searches = (0..10).map(|_| cached_fetch(&state, "bitcoin"));
+        let results = futures_util::future::join_all(searches).await;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(results.iter().all(|news| news.as_ref().unwrap().fetch.items.len() == 1));
+
+        cached_fetch(&state, "ethereum").await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn stale_results_are_served_while_refreshing() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let mut state = AppState::for_tests().await;
+        state.providers = Arc::new(vec![Box::new(CountingProvider { calls: calls.clone() })]);
+
+        let stale = CachedNews { query: "bitcoin".into(), fetched_at: Utc::now(), fetch: NewsFetch::default() };
+        let json = serde_json::to_string(&stale).unwrap();
+        state.cache.set_with_soft_ttl("news:bitcoin", &json, 0, STALE_SECS).await;
+
+        let news = cached_fetch(&state, "bitcoin").await.unwrap();
+        assert!(news.fetch.items.is_empty());
+
+        // The refresh runs in the background and replaces the cached entry
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        let news = cached_fetch(&state, "bitcoin").await.unwrap();
+        assert_eq!(news.fetch.items.len(), 1);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,142 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Mutex;
+use std::time::Duration;
+use crate::api::canonical_coin;
+use crate::{news, AppState};
+
+/// Coins that open WebSocket connections are subscribed to, counted per
+/// connection so the poller keeps tracking a coin until the last one leaves.
+#[derive(Default)]
+pub struct Subscriptions {
+    counts: Mutex<HashMap<String, usize>>,
+}
+
+impl Subscriptions {
+    pub fn add(&self, coin: &str) {
+        *self.counts.lock().unwrap().entry(coin.to_string()).or_insert(0) += 1;
+    }
+
+    pub fn remove(&self, coin: &str) {
+        let mut counts = self.counts.lock().unwrap();
+        if let Some(count) = counts.get_mut(coin) {
+            *count -= 1;
+            if *count == 0 {
+                counts.remove(coin);
+            }
+        }
+    }
+
+    pub fn coins(&self) -> Vec<String> {
+        self.counts.lock().unwrap().keys().cloned().collect()
+    }
+}
+
+/// Polls the providers for every tracked coin each `interval`. New articles
+/// reach subscribers through `news::fetch_and_store`.
+pub async fn run(state: AppState, interval: Duration, always: Vec<String>) {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        ticker.tick().await;
+        poll_once(&state, &always).await;
+    }
+}
+
+/// Configured coins, every watched coin and every coin a WebSocket client
+/// is subscribed to, by canonical name.
+pub async fn tracked_coins(state: &AppState, always: &[String]) -> Vec<String> {
+    let mut coins: BTreeSet<String> = always.iter().map(|coin| canonical_coin(coin)).collect();
+
+    match state.db.all_watched_coins().await {
+        Ok(watched) => coins.extend(watched),
+        Err(e) => tracing::error!("Failed to load watched coins: {}", e),
+    }
+    coins.extend(state.subscriptions.coins());
+    coins.retain(|coin| !coin.is_empty());
+
+    coins.into_iter().collect()
+}
+
+pub async fn poll_once(state: &AppState, always: &[String]) {
+    for coin in tracked_coins(state, always).await {
+        if let Err(e) = news::fetch_and_store(state, &coin).await {
+            tracing::warn!("Polling news for {} failed: {}", coin, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{NewsItem, NewsProvider};
+    use async_trait::async_trait;
+    use chrono::{TimeZone, Utc};
+    use std::sync::Arc;
+
+    /// Returns one more article on every call.
+    struct GrowingProvider {
+        calls: Mutex<u32>,
+    }
+
+    #[async_trait]
+    impl NewsProvider for GrowingProvider {
+        fn name(&self) -> &str {
+            "Growing"
+        }
+
+        async fn fetch(&self, query: &str) -> Result<Vec<NewsItem>, String> {
+            let mut calls = self.calls.lock().unwrap();
+            *calls += 1;
+            Ok((1..=*calls)
+                .map(|n| NewsItem {
+                    title: format!("{} story {}", query, n),
+                    source: "test".to_string(),
+                    url: format!("https://example.com/{}/{}", query, n),
+                    published_at: Utc.with_ymd_and_hms(2025, 4, 7, n, 0, 0).unwrap(),
+                    summary: String::new(),
+                    sentiment: "Neutral".to_string(),
+                    api_source: "Growing".to_string(),
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn poll_publishes_only_new_items() {
+        let mut state = AppState::for_tests().await;
+        state.providers = Arc::new(vec![Box::new(GrowingProvider { calls: Mutex::new(0) })]);
+        let mut updates = state.tx.subscribe();
+
+        state.subscriptions.add("bitcoin");
+        poll_once(&state, &[]).await;
+        let update = updates.try_recv().unwrap();
+        assert_eq!(update.coin, "bitcoin");
+        assert_eq!(update.news.len(), 1);
+
+        poll_once(&state, &[]).await;
+        let update = updates.try_recv().unwrap();
+        let urls: Vec<&str> = update.news.iter().map(|item| item.url.as_str()).collect();
+        assert_eq!(urls, vec!["https://example.com/bitcoin/2"]);
+        assert!(updates.try_recv().is_err());
+
+        // Nothing is tracked once the last subscriber leaves
+        state.subscriptions.remove("bitcoin");
+        poll_once(&state, &[]).await;
+        assert!(updates.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn tracked_coins_merge_config_watchlists_and_subscriptions() {
+        let state = AppState::for_tests().await;
+        state.db.create_user("satoshi", "satoshi@example.com", "hunter2").await.unwrap();
+        let user = state.db.find_user("satoshi").await.unwrap().unwrap().id;
+        state.db.watch_coin(user, "solana").await.unwrap();
+        state.subscriptions.add("ethereum");
+        state.subscriptions.add("ethereum");
+        state.subscriptions.remove("ethereum");
+
+        let coins = tracked_coins(&state, &["BTC".to_string(), "sol".to_string()]).await;
+        assert_eq!(coins, vec!["bitcoin", "ethereum", "solana"]);
+    }
+}
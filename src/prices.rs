@@ -0,0 +1,368 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use crate::api::canonical_coin;
+use crate::cache::Cache;
+use crate::db::Storage;
+use utoipa::ToSchema;
+
+/// Prices move quickly, so quotes are only cached briefly.
+const QUOTE_TTL_SECONDS: u64 = 60;
+
+pub const HISTORY_DAYS: u32 = 7;
+const HISTORY_REFRESH: Duration = Duration::hours(4);
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, ToSchema)]
+pub struct PriceQuote {
+    pub coin: String,
+    pub price_usd: f64,
+    pub change_24h: Option<f64>,
+    pub market_cap_usd: Option<f64>,
+    pub fetched_at: DateTime<Utc>,
+}
+
+/// One OHLC candle in USD.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, ToSchema)]
+pub struct PricePoint {
+    pub timestamp: DateTime<Utc>,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+}
+
+#[async_trait]
+pub trait PriceProvider: Send + Sync {
+    fn name(&self) -> &str;
+    /// Spot quote for a canonical coin id such as "bitcoin". `Ok(None)` means
+    /// the provider does not list the coin.
+    async fn quote(&self, coin: &str) -> Result<Option<PriceQuote>, String>;
+    /// OHLC candles covering the last `days` days, oldest first.
+    async fn history(&self, coin: &str, days: u32) -> Result<Vec<PricePoint>, String>;
+}
+
+/// Client for CoinGecko's `/simple/price` and `/coins/{id}/ohlc` endpoints. The base URL is
+/// configurable so tests and self-hosted mirrors can stand in for it.
+pub struct CoinGeckoProvider {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+}
+
+impl CoinGeckoProvider {
+    pub fn new(base_url: String, api_key: Option<String>) -> Self {
+        CoinGeckoProvider {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+        }
+    }
+}
+
+#[async_trait]
+impl PriceProvider for CoinGeckoProvider {
+    fn name(&self) -> &str {
+        "CoinGecko"
+    }
+
+    async fn quote(&self, coin: &str) -> Result<Option<PriceQuote>, String> {
+        let mut request = self
+            .client
+            .get(format!("{}/simple/price", self.base_url))
+            .query(&[
+                ("ids", coin),
+                ("vs_currencies", "usd"),
+                ("include_market_cap", "true"),
+                ("include_24hr_change", "true"),
+            ]);
+        if let Some(key) = &self.api_key {
+            request = request.header("x-cg-demo-api-key", key);
+        }
+
+        let response = request.send().await.map_err(|e| e.to_string())?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.map_err(|e| e.to_string())?;
+            return Err(format!("CoinGecko API returned error status {}: {}", status, text));
+        }
+
+        let text = response.text().await.map_err(|e| e.to_string())?;
+        parse_simple_price(&text, coin)
+    }
+
+    async fn history(&self, coin: &str, days: u32) -> Result<Vec<PricePoint>, String> {
+        let mut request = self
+            .client
+            .get(format!("{}/coins/{}/ohlc", self.base_url, coin))
+            .query(&[("vs_currency", "usd"), ("days", &days.to_string())]);
+        if let Some(key) = &self.api_key {
+            request = request.header("x-cg-demo-api-key", key);
+        }
+
+        let response = request.send().await.map_err(|e| e.to_string())?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.map_err(|e| e.to_string())?;
+            return Err(format!("CoinGecko API returned error status {}: {}", status, text));
+        }
+
+        let text = response.text().await.map_err(|e| e.to_string())?;
+        parse_ohlc(&text)
+    }
+}
+
+/// CoinGecko returns candles as `[timestamp_ms, open, high, low, close]`.
+fn parse_ohlc(text: &str) -> Result<Vec<PricePoint>, String> {
+    let rows: Vec<[f64; 5]> = serde_json::from_str(text).map_err(|e| e.to_string())?;
+
+    let mut points = rows
+        .into_iter()
+        .filter_map(|[timestamp, open, high, low, close]| {
+            DateTime::<Utc>::from_timestamp_millis(timestamp as i64).map(|timestamp| PricePoint {
+                timestamp,
+                open,
+                high,
+                low,
+                close,
+            })
+        })
+        .collect::<Vec<_>>();
+    points.sort_by_key(|point| point.timestamp);
+    Ok(points)
+}
+
+fn parse_simple_price(text: &str, coin: &str) -> Result<Option<PriceQuote>, String> {
+    let data: serde_json::Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+
+    let Some(entry) = data.get(coin) else {
+        return Ok(None);
+    };
+
+    let price_usd = entry
+        .get("usd")
+        .and_then(|p| p.as_f64())
+        .ok_or_else(|| format!("CoinGecko response for {} has no USD price", coin))?;
+
+    Ok(Some(PriceQuote {
+        coin: coin.to_string(),
+        price_usd,
+        change_24h: entry.get("usd_24h_change").and_then(|c| c.as_f64()),
+        market_cap_usd: entry.get("usd_market_cap").and_then(|m| m.as_f64()),
+        fetched_at: Utc::now(),
+    }))
+}
+
+/// Spot quote for a search term, served from Redis when a recent one exists.
+/// Terms the provider does not list ("defi", "etf") are cached as such too,
+/// so they are not looked up on every search. Failures are logged and
+/// rendered as a missing price rather than an error page.
+pub async fn cached_quote(
+    cache: &Cache,
+    provider: &dyn PriceProvider,
+    query: &str,
+) -> Option<PriceQuote> {
+    let coin = canonical_coin(query);
+    let cache_key = format!("price:{}", coin);
+
+    // `null` when the provider does not list the coin
+    if let Some(cached) = cache.get(&cache_key).await {
+        if let Ok(quote) = serde_json::from_str::<Option<PriceQuote>>(&cached) {
+            return quote;
+        }
+    }
+
+    match provider.quote(&coin).await {
+        Ok(quote) => {
+            if let Ok(json) = serde_json::to_string(&quote) {
+                cache.set_with_ttl(&cache_key, &json, QUOTE_TTL_SECONDS).await;
+            }
+            quote
+        }
+        Err(e) => {
+            tracing::error!("Failed to fetch price for {} from {}: {}", coin, provider.name(), e);
+            None
+        }
+    }
+}
+
+/// The last `HISTORY_DAYS` of candles for a coin. Candles are stored in the database
+/// and the provider is only asked again once the newest one is older than
+/// `HISTORY_REFRESH`, which matches CoinGecko's 4-hour granularity for 7 days.
+pub async fn price_history(
+    db: &dyn Storage,
+    provider: &dyn PriceProvider,
+    coin: &str,
+) -> Vec<PricePoint> {
+    let since = Utc::now() - Duration::days(HISTORY_DAYS as i64);
+
+    let stored = db.price_points_since(coin, since).await.unwrap_or_else(|e| {
+        tracing::error!("Failed to load price history for {}: {}", coin, e);
+        Vec::new()
+    });
+    let is_fresh = stored
+        .last()
+        .is_some_and(|point| Utc::now() - point.timestamp < HISTORY_REFRESH);
+    if is_fresh {
+        return stored;
+    }
+
+    match provider.history(coin, HISTORY_DAYS).await {
+        Ok(points) => {
+            if let Err(e) = db.store_price_points(coin, &points).await {
+                tracing::error!("Failed to store price history for {}: {}", coin, e);
+            }
+            points.into_iter().filter(|point| point.timestamp >= since).collect()
+        }
+        Err(e) => {
+            tracing::error!("Failed to fetch price history for {} from {}: {}", coin, provider.name(), e);
+            stored
+        }
+    }
+}
+
+/// Close of the last candle at or before `at`.
+pub fn price_at(points: &[PricePoint], at: DateTime<Utc>) -> Option<f64> {
+    points
+        .iter()
+        .take_while(|point| point.timestamp <= at)
+        .last()
+        .map(|point| point.close)
+}
+
+/// "$80,123.45", with more precision for coins priced under a dollar.
+pub fn format_usd(value: f64) -> String {
+    if value.abs() < 1.0 {
+        return format!("${:.4}", value);
+    }
+
+    let formatted = format!("{:.2}", value.abs());
+    let (whole, cents) = formatted.split_once('.').unwrap_or((&formatted, "00"));
+    let mut grouped = String::new();
+    for (i, digit) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+
+    let sign = if value < 0.0 { "-" } else { "" };
+    format!("{}${}.{}", sign, grouped, cents)
+}
+
+/// "$1.52T", "$640.10B", "$12.00M".
+pub fn format_market_cap(value: f64) -> String {
+    if value >= 1e12 {
+        format!("${:.2}T", value / 1e12)
+    } else if value >= 1e9 {
+        format!("${:.2}B", value / 1e9)
+    } else if value >= 1e6 {
+        format!("${:.2}M", value / 1e6)
+    } else {
+        format_usd(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::Query, routing::get, Router};
+    use std::collections::HashMap;
+
+    async fn mock_coingecko() -> String {
+        let app = Router::new().route(
+            "/api/v3/simple/price",
+            get(|Query(params): Query<HashMap<String, String>>| async move {
+                match params.get("ids").map(String::as_str) {
+                    Some("bitcoin") => r#"{"bitcoin":{"usd":80123.45,"usd_market_cap":1587000000000.0,"usd_24h_change":-1.234}}"#,
+                    _ => "{}",
+                }
+            }),
+        )
+        .route(
+            "/api/v3/coins/bitcoin/ohlc",
+            get(|| async { include_str!("../tests/fixtures/coingecko_ohlc.json") }),
+        );
+        format!("{}/api/v3", crate::spawn_app(app).await)
+    }
+
+    #[tokio::test]
+    async fn coingecko_quote_reads_price_change_and_market_cap() {
+        let provider = CoinGeckoProvider::new(mock_coingecko().await, None);
+
+        let quote = provider.quote("bitcoin").await.unwrap().unwrap();
+        assert_eq!(quote.coin, "bitcoin");
+        assert_eq!(quote.price_usd, 80123.45);
+        assert_eq!(quote.change_24h, Some(-1.234));
+        assert_eq!(quote.market_cap_usd, Some(1587000000000.0));
+
+        assert_eq!(provider.quote("defi").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn coingecko_history_reads_ohlc_candles() {
+        let provider = CoinGeckoProvider::new(mock_coingecko().await, None);
+
+        let points = provider.history("bitcoin", 7).await.unwrap();
+        assert_eq!(points.len(), 4);
+        assert_eq!(points[0].timestamp, DateTime::<Utc>::from_timestamp(1743552000, 0).unwrap());
+        assert_eq!(points[0].close, 82100.0);
+        assert_eq!(points[3].high, 84050.5);
+    }
+
+    #[tokio::test]
+    async fn unknown_coins_are_cached_too() {
+        use crate::cache::MemoryBackend;
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        /// Lists nothing and counts how often it was asked.
+        struct EmptyProvider(AtomicU32);
+
+        #[async_trait]
+        impl PriceProvider for EmptyProvider {
+            fn name(&self) -> &str {
+                "Empty"
+            }
+
+            async fn quote(&self, _coin: &str) -> Result<Option<PriceQuote>, String> {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                Ok(None)
+            }
+
+            async fn history(&self, _coin: &str, _days: u32) -> Result<Vec<PricePoint>, String> {
+                Ok(Vec::new())
+            }
+        }
+
+        let cache = Cache::new(vec![Box::new(MemoryBackend::new(10))]);
+        let provider = EmptyProvider(AtomicU32::new(0));
+        assert_eq!(cached_quote(&cache, &provider, "defi").await, None);
+        assert_eq!(cached_quote(&cache, &provider, "defi").await, None);
+        assert_eq!(provider.0.load(Ordering::SeqCst), 1);
+
+        // Real quotes still round-trip through the cache
+        let bitcoin = CoinGeckoProvider::new(mock_coingecko().await, None);
+        let quote = cached_quote(&cache, &bitcoin, "btc").await.unwrap();
+        assert_eq!(cached_quote(&cache, &provider, "btc").await, Some(quote));
+    }
+
+    #[test]
+    fn price_at_uses_the_last_candle_before_the_instant() {
+        let points = parse_ohlc(include_str!("../tests/fixtures/coingecko_ohlc.json")).unwrap();
+
+        let before = DateTime::<Utc>::from_timestamp(1743500000, 0).unwrap();
+        let between = DateTime::<Utc>::from_timestamp(1743570000, 0).unwrap();
+        assert_eq!(price_at(&points, before), None);
+        assert_eq!(price_at(&points, between), Some(82950.0));
+    }
+
+    #[test]
+    fn formats_prices_for_display() {
+        assert_eq!(format_usd(80123.456), "$80,123.46");
+        assert_eq!(format_usd(999.5), "$999.50");
+        assert_eq!(format_usd(0.12345), "$0.1235");
+        assert_eq!(format_market_cap(1587000000000.0), "$1.59T");
+        assert_eq!(format_market_cap(640100000000.0), "$640.10B");
+    }
+}
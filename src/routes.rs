@@ -1,19 +1,77 @@
 use axum::{
     extract::{Query, State},
-    response::Html,
-    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+    http::{header, HeaderMap, StatusCode},
+    Json,
 };
-use serde::Deserialize;
-use crate::{AppState, api};
-use crate::api::NewsItem;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::{AppState, feeds, news, prices, trending, watchlist};
+use crate::trending::Window;
+use crate::auth::{AuthUser, ErrorResponse};
+use crate::api::{NewsItem, ProviderFailure};
+use crate::db::ArchiveHit;
+use crate::prices::{PricePoint, PriceQuote};
 
 #[derive(Debug, Deserialize)]
 pub struct SearchQuery {
     pub q: String,
+    pub mode: Option<String>,
+    /// `html` (the default), `json` or `rss`.
+    pub format: Option<String>,
 }
 
-pub async fn homepage(State(state): State<AppState>) -> Html<String> {
-    let top_searches = state.cache.get_top_searches().await;
+/// Where `/search` looks for results. `combined` (the default) adds archived
+/// articles to the live results, so older or niche stories still turn up when
+/// they have fallen out of the upstream APIs' result windows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchMode {
+    Live,
+    Archive,
+    Combined,
+}
+
+impl SearchMode {
+    fn from_param(mode: Option<&str>) -> Self {
+        match mode.map(|m| m.trim().to_lowercase()).as_deref() {
+            Some("live") => SearchMode::Live,
+            Some("archive") => SearchMode::Archive,
+            _ => SearchMode::Combined,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            SearchMode::Live => "live",
+            SearchMode::Archive => "archive",
+            SearchMode::Combined => "combined",
+        }
+    }
+}
+
+const ARCHIVE_RESULTS: i64 = 20;
+const TOP_SEARCHES: usize = 5;
+/// The window "Trending Now" on the homepage covers.
+const TRENDING_WINDOW: Window = Window::Day;
+
+pub async fn homepage(State(state): State<AppState>, auth: Option<AuthUser>) -> Html<String> {
+    let trending_now = trending::trending(&state.cache, TRENDING_WINDOW, TOP_SEARCHES).await;
+    let most_searched = trending::most_searched(&state.cache, TOP_SEARCHES).await;
+
+    let watchlist_html = match auth {
+        Some(AuthUser { user, .. }) => {
+            let coins = state.db.watched_coins(user.id).await.unwrap_or_else(|e| {
+                tracing::error!("Failed to load watchlist for {}: {}", user.username, e);
+                Vec::new()
+            });
+            let feed = state.db.watchlist_feed(user.id, watchlist::FEED_SIZE).await.unwrap_or_else(|e| {
+                tracing::error!("Failed to load watchlist feed for {}: {}", user.username, e);
+                Vec::new()
+            });
+            watchlist::render_watchlist(&coins, &feed)
+        }
+        None => String::new(),
+    };
     
     let html = format!(r#"
         <!DOCTYPE html>
@@ -132,6 +190,23 @@ pub async fn homepage(State(state): State<AppState>) -> Html<String> {
                 .suggestion-item:hover {{
                     background-color: #e0e0e0;
                 }}
+                .watch-chips {{ margin-bottom: 10px; }}
+                .watch-chip {{
+                    display: inline-block;
+                    margin: 5px;
+                    padding: 5px 10px;
+                    background-color: #e8f5e9;
+                    border-radius: 15px;
+                }}
+                .watch-remove {{
+                    padding: 0 4px;
+                    font-size: 14px;
+                    background: none;
+                    color: #666;
+                }}
+                .watch-remove:hover {{ background: none; color: #e74c3c; }}
+                .watch-form input[type="text"] {{ width: 50%; }}
+                .watch-meta {{ color: #666; font-size: 0.85em; margin-top: 5px; }}
                 </style>
             </head>
             <body>
@@ -182,14 +257,21 @@ pub async fn homepage(State(state): State<AppState>) -> Html<String> {
                 </div>
             </div>
             
+            {}
+
+            <div class="top-searches">
+                <h2>Trending Now</h2>
+                {}
+            </div>
+
             <div class="top-searches">
-                <h2>Top Searches</h2>
+                <h2>Most Searched</h2>
                 {}
             </div>
             
             <script>
                 // Check authentication status
-                const token = localStorage.getItem('token');
+                let token = localStorage.getItem('token');
                 const authButtons = document.getElementById('authButtons');
                 const userWelcome = document.getElementById('userWelcome');
                 const userFeatures = document.getElementById('userFeatures');
@@ -198,26 +280,58 @@ pub async fn homepage(State(state): State<AppState>) -> Html<String> {
                     // User is logged in
                     authButtons.innerHTML = `
                         <span class="nav-button" style="background-color: #666;">Welcome!</span>
-                        <button class="nav-button" onclick="logout()">Logout</button>
+                        <a href="/bookmarks" class="nav-button">Bookmarks</a>
+                        <button class="nav-button" onclick="logout('/logout')">Logout</button>
+                        <button class="nav-button" onclick="logout('/logout-all')">Logout everywhere</button>
                     `;
                     
                     // Show user-specific content
                     userWelcome.style.display = 'block';
                     userFeatures.style.display = 'flex';
                     
-                    // Try to get username from token
-                    try {{
-                        const tokenParts = token.split('.');
-                        const payload = JSON.parse(atob(tokenParts[1]));
-                        document.getElementById('username').textContent = payload.sub;
-                    }} catch (e) {{
-                        console.error('Error parsing token:', e);
-                    }}
+                    loadUser()
+                        .then(user => {{
+                            if (user) {{
+                                document.getElementById('username').textContent = user.username;
+                            }}
+                        }})
+                        .catch(e => console.error('Error loading user:', e));
                 }}
                 
-                function logout() {{
+                // Access tokens are short-lived; on 401 trade the refresh
+                // token cookie for a new one, and log out if that fails too
+                async function loadUser(retried) {{
+                    const response = await fetch('/me', {{ headers: {{ 'Authorization': 'Bearer ' + token }} }});
+                    if (response.status !== 401) {{
+                        return response.ok ? response.json() : null;
+                    }}
+                    if (!retried) {{
+                        const refresh = await fetch('/refresh', {{ method: 'POST' }});
+                        if (refresh.ok) {{
+                            token = (await refresh.json()).token;
+                            localStorage.setItem('token', token);
+                            // The page was rendered without the session cookie
+                            if (!document.getElementById('watchlist')) {{
+                                window.location.reload();
+                            }}
+                            return loadUser(true);
+                        }}
+                    }}
                     localStorage.removeItem('token');
                     window.location.reload();
+                    return null;
+                }}
+                
+                async function logout(endpoint) {{
+                    try {{
+                        await fetch(endpoint, {{
+                            method: 'POST',
+                            headers: {{ 'Authorization': 'Bearer ' + token }}
+                        }});
+                    }} finally {{
+                        localStorage.removeItem('token');
+                        window.location.reload();
+                    }}
                 }}
                 
                 function searchFor(term) {{
@@ -237,59 +351,211 @@ pub async fn homepage(State(state): State<AppState>) -> Html<String> {
             </body>
         </html>
     "#, 
-    if top_searches.is_empty() {
+    watchlist_html,
+    search_list_html(trending_now.iter().map(|(term, _)| (term.as_str(), String::new()))),
+    search_list_html(most_searched.iter().map(|(term, count)| (term.as_str(), format!(" - {} searches", count)))));
+
+    Html(html)
+}
+
+/// One row per term, each followed by its note.
+fn search_list_html<'a>(terms: impl Iterator<Item = (&'a str, String)>) -> String {
+    let rows: Vec<String> = terms
+        .map(|(term, note)| format!(r#"<div class="search-item">{}{}</div>"#, escape_html(term), note))
+        .collect();
+    if rows.is_empty() {
         "<p>No searches yet</p>".to_string()
     } else {
-        top_searches.iter()
-            .map(|(term, count)| {
-                format!(
-                    r#"<div class="search-item">{} - {} searches</div>"#,
-                    term, count
-                )
-            })
-            .collect::<Vec<_>>()
-            .join("\n")
-    });
+        rows.join("\n")
+    }
+}
 
-    Html(html)
+/// Output format for `/search`. Every format renders the same cached fetch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchFormat {
+    Html,
+    Json,
+    Rss,
+}
+
+impl SearchFormat {
+    fn from_param(format: Option<&str>) -> Self {
+        match format.map(|f| f.trim().to_lowercase()).as_deref() {
+            Some("json") => SearchFormat::Json,
+            Some("rss") => SearchFormat::Rss,
+            _ => SearchFormat::Html,
+        }
+    }
+}
+
+/// Shown for a provider that failed, in place of its error.
+const PROVIDER_FAILED: &str = "The source could not be reached";
+/// The JSON and RSS answer when no provider could be reached.
+const FETCH_FAILED: &str = "Could not fetch news, please try again later";
+
+/// `/search?format=json`: the live fetch with its metadata, and the archived
+/// articles the page would show under it.
+#[derive(Debug, Serialize)]
+pub struct SearchResults {
+    pub query: String,
+    pub mode: &'static str,
+    /// When the live results were fetched; absent in `archive` mode.
+    pub fetched_at: Option<DateTime<Utc>>,
+    pub items: Vec<NewsItem>,
+    pub failures: Vec<ProviderFailure>,
+    pub archive: Vec<ArchiveHit>,
+}
+
+impl SearchResults {
+    fn is_empty(&self) -> bool {
+        self.items.is_empty() && self.archive.is_empty()
+    }
+}
+
+/// Live results come from `news::cached_fetch`, so the cache holds the
+/// fetched items rather than a page and each format is rendered per request.
+async fn search_results(state: &AppState, search_term: &str, mode: SearchMode) -> (SearchResults, Option<String>) {
+    let mut live_error = None;
+    let cached = if mode == SearchMode::Archive {
+        None
+    } else {
+        match news::cached_fetch(state, search_term).await {
+            Ok(cached) => Some(cached),
+            Err(e) => {
+                // Log the error for debugging
+                tracing::error!("Error fetching news: {:?}", e);
+                live_error = Some(e);
+                None
+            }
+        }
+    };
+    let fetched_at = cached.as_ref().map(|cached| cached.fetched_at);
+    let fetch = cached.map(|cached| cached.fetch).unwrap_or_default();
+
+    let archive = if mode == SearchMode::Live {
+        Vec::new()
+    } else {
+        match state.db.search_articles(search_term, ARCHIVE_RESULTS).await {
+            // Live results already show these
+            Ok(hits) => hits
+                .into_iter()
+                .filter(|hit| !fetch.items.iter().any(|item| item.url == hit.article.news.url))
+                .collect(),
+            Err(e) => {
+                tracing::error!("Error searching the archive: {}", e);
+                Vec::new()
+            }
+        }
+    };
+
+    let results = SearchResults {
+        query: search_term.to_string(),
+        mode: mode.as_str(),
+        fetched_at,
+        items: fetch.items,
+        // The errors can carry upstream URLs with API keys; they were logged
+        // when the fetch failed
+        failures: fetch
+            .failures
+            .into_iter()
+            .map(|failure| ProviderFailure { provider: failure.provider, error: PROVIDER_FAILED.to_string() })
+            .collect(),
+        archive,
+    };
+    (results, live_error)
 }
 
 pub async fn handle_search(
     State(state): State<AppState>,
     Query(query): Query<SearchQuery>,
-) -> Result<Html<String>, StatusCode> {
+    headers: HeaderMap,
+) -> Response {
     // Normalize the search query
     let search_term = query.q.trim().to_lowercase();
+    let format = SearchFormat::from_param(query.format.as_deref());
     
     // Check if the search term is empty
     if search_term.is_empty() {
-        return Ok(Html(format_error_html("Please enter a search term")));
+        let error = "Please enter a search term";
+        return match format {
+            SearchFormat::Html => Html(format_error_html(error)).into_response(),
+            SearchFormat::Json => (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: error.to_string() })).into_response(),
+            SearchFormat::Rss => (StatusCode::BAD_REQUEST, error).into_response(),
+        };
     }
-    
-    let cache_key = format!("news:{}", search_term);
-    
-    // Try to get from cache first
-    if let Some(cached_html) = state.cache.get(&cache_key).await {
-        state.cache.increment_search_count(&search_term).await;
-        return Ok(Html(cached_html));
+
+    let mode = SearchMode::from_param(query.mode.as_deref());
+    let (results, live_error) = search_results(&state, &search_term, mode).await;
+
+    if results.is_empty() {
+        return match (format, live_error) {
+            (SearchFormat::Html, error) => {
+                let error = error.unwrap_or_else(|| "No news found for your search query.".to_string());
+                Html(format_error_html(&error)).into_response()
+            }
+            // An empty list or feed is a valid answer; a failed fetch is not
+            (SearchFormat::Json, Some(_)) => {
+                (StatusCode::BAD_GATEWAY, Json(ErrorResponse { error: FETCH_FAILED.to_string() })).into_response()
+            }
+            (SearchFormat::Rss, Some(_)) => (StatusCode::BAD_GATEWAY, FETCH_FAILED).into_response(),
+            (SearchFormat::Json, None) => Json(results).into_response(),
+            (SearchFormat::Rss, None) => rss_response(&headers, &results),
+        };
     }
 
-    // If not in cache, fetch from API
-    match api::fetch_news(&search_term).await {
-        Ok(news) => {
-            let html = format_news_html(&news, &search_term);
-            state.cache.set(&cache_key, &html).await;
-            state.cache.increment_search_count(&search_term).await;
-            Ok(Html(html))
-        }
-        Err(e) => {
-            // Log the error for debugging
-            tracing::error!("Error fetching news: {:?}", e);
-            Ok(Html(format_error_html(&format!("Error fetching news: {}", e))))
+    trending::record_search(&state.cache, &search_term).await;
+    match format {
+        SearchFormat::Html => {
+            let quote = prices::cached_quote(&state.cache, state.prices.as_ref(), &search_term).await;
+            let history = match &quote {
+                Some(quote) => prices::price_history(state.db.as_ref(), state.prices.as_ref(), &quote.coin).await,
+                None => Vec::new(),
+            };
+            Html(format_news_html(
+                &results.items,
+                &results.failures,
+                &results.archive,
+                quote.as_ref(),
+                &history,
+                &search_term,
+                mode,
+            ))
+            .into_response()
         }
+        SearchFormat::Json => Json(results).into_response(),
+        SearchFormat::Rss => rss_response(&headers, &results),
     }
 }
 
+/// Live items followed by archived ones, linking back to the search page on
+/// the host the feed was requested from.
+fn rss_response(headers: &HeaderMap, results: &SearchResults) -> Response {
+    let host = headers
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .unwrap_or("localhost");
+    let link = reqwest::Url::parse_with_params(
+        &format!("http://{}/search", host),
+        &[("q", results.query.as_str()), ("mode", results.mode)],
+    )
+    .map(String::from)
+    .unwrap_or_default();
+
+    let items: Vec<NewsItem> = results
+        .items
+        .iter()
+        .cloned()
+        .chain(results.archive.iter().map(|hit| hit.article.news.clone()))
+        .collect();
+    let title = format!("Crypto News - {}", results.query);
+
+    (
+        [(header::CONTENT_TYPE, "application/rss+xml; charset=utf-8")],
+        feeds::render_rss(&title, &link, &items),
+    )
+        .into_response()
+}
+
 fn format_error_html(error: &str) -> String {
     format!(
         r#"
@@ -398,6 +664,7 @@ fn format_error_html(error: &str) -> String {
                             <ul>
                                 <li><a href="https://newsdata.io/" target="_blank">NewsData.io</a></li>
                                 <li><a href="https://cryptonews-api.com/" target="_blank">CryptoNews API</a></li>
+                                <li><a href="https://cryptopanic.com/developers/api/" target="_blank">CryptoPanic</a></li>
                             </ul>
                         </li>
                         <li>Try searching for a different cryptocurrency</li>
@@ -407,21 +674,45 @@ fn format_error_html(error: &str) -> String {
         </body>
         </html>
         "#,
-        error
+        escape_html(error)
     )
 }
 
 #[axum::debug_handler]
 pub async fn handle_search_post(
     State(state): State<AppState>,
+    headers: HeaderMap,
     axum::extract::Form(query): axum::extract::Form<SearchQuery>,
-) -> Result<Html<String>, StatusCode> {
-    handle_search(State(state), Query(query)).await
+) -> Response {
+    handle_search(State(state), Query(query), headers).await
 }
 
 pub async fn cache_stats(State(state): State<AppState>) -> Html<String> {
     let stats = state.cache.get_stats().await;
-    
+
+    // Tiers are listed in lookup order, so hits on the first were the fastest
+    let tiers_html = stats.tiers.iter()
+        .enumerate()
+        .map(|(index, tier)| {
+            let backend_html = match &tier.backend {
+                Some(backend) => format!(
+                    "{} keys, {:.2} MB",
+                    backend.total_keys,
+                    backend.memory_used as f64 / 1024.0 / 1024.0
+                ),
+                None => r#"<span class="unavailable">unavailable</span>"#.to_string(),
+            };
+            format!(
+                r#"<div class="stat-item"><strong>L{} {}:</strong> {} hits &middot; {}</div>"#,
+                index + 1,
+                tier.name,
+                tier.hits,
+                backend_html
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
     let html = format!(r#"
         <!DOCTYPE html>
         <html>
@@ -464,6 +755,7 @@ pub async fn cache_stats(State(state): State<AppState>) -> Html<String> {
                     border-radius: 4px; 
                     margin-top: 20px; 
                 }}
+                .unavailable {{ color: #c62828; }}
                 .stat-item {{ 
                     margin: 10px 0; 
                     padding: 10px; 
@@ -483,30 +775,32 @@ pub async fn cache_stats(State(state): State<AppState>) -> Html<String> {
             </div>
             <h1>Cache Statistics</h1>
             <div class="stats-container">
-                <div class="stat-item">
-                    <strong>Total Keys:</strong> {}
-                </div>
-                <div class="stat-item">
-                    <strong>Total Memory Used:</strong> {:.2} MB
-                </div>
                 <div class="stat-item">
                     <strong>Hit Rate:</strong> {:.2}%
                 </div>
+                {}
             </div>
             <br>
             <a href="/" style="color: #4CAF50; text-decoration: none;">Back to Homepage</a>
                             </body>
         </html>
     "#, 
-        stats.total_keys,
-        stats.memory_used as f64 / 1024.0 / 1024.0,
-        stats.hit_rate * 100.0
+        stats.hit_rate * 100.0,
+        tiers_html
     );
 
     Html(html)
 }
 
-fn format_news_html(news_items: &[NewsItem], query: &str) -> String {
+fn format_news_html(
+    news_items: &[NewsItem],
+    failures: &[ProviderFailure],
+    archive: &[ArchiveHit],
+    quote: Option<&PriceQuote>,
+    history: &[PricePoint],
+    query: &str,
+    mode: SearchMode,
+) -> String {
     // Get the display name for the cryptocurrency
     let display_name = match query.to_lowercase().as_str() {
         "btc" | "bitcoin" => "BITCOIN",
@@ -521,7 +815,7 @@ fn format_news_html(news_items: &[NewsItem], query: &str) -> String {
         "uni" | "uniswap" => "UNISWAP",
         _ => {
             // Try to match by removing any whitespace
-            let normalized = query.to_lowercase().replace("", "");
+            let normalized = query.to_lowercase().replace(" ", "");
             match normalized.as_str() {
                 "btc" | "bitcoin" => "BITCOIN",
                 "eth" | "ethereum" | "ether" => "ETHEREUM",
@@ -538,9 +832,57 @@ fn format_news_html(news_items: &[NewsItem], query: &str) -> String {
         }
     }.to_string();
     
-    let current_price = get_crypto_price(query);
+    let price_html = match quote {
+        Some(quote) => {
+            let change_html = match quote.change_24h {
+                Some(change) => format!(
+                    r#"<span class="{}">{:+.2}% (24h)</span>"#,
+                    if change >= 0.0 { "price-up" } else { "price-down" },
+                    change
+                ),
+                None => String::new(),
+            };
+            let market_cap_html = match quote.market_cap_usd {
+                Some(market_cap) => format!(
+                    r#"<div class="crypto-market-cap">Market Cap: {}</div>"#,
+                    prices::format_market_cap(market_cap)
+                ),
+                None => String::new(),
+            };
+            format!(
+                r#"
+                <div class="crypto-price">Current Price: {} {}</div>
+                {}
+                "#,
+                prices::format_usd(quote.price_usd),
+                change_html,
+                market_cap_html
+            )
+        }
+        None => r#"<div class="crypto-price">Current Price: N/A</div>"#.to_string(),
+    };
+
+    let failures_html = if failures.is_empty() {
+        String::new()
+    } else {
+        let failed = failures.iter()
+            .map(|failure| format!("<li><strong>{}</strong>: {}</li>", escape_html(&failure.provider), escape_html(&failure.error)))
+            .collect::<Vec<String>>()
+            .join("\n");
+        format!(
+            r#"
+            <div class="provider-failures">
+                <p>Some news sources could not be reached, so these results may be incomplete:</p>
+                <ul>{}</ul>
+            </div>
+            "#,
+            failed
+        )
+    };
     
-    let news_html = if news_items.is_empty() {
+    let news_html = if news_items.is_empty() && !archive.is_empty() {
+        String::new()
+    } else if news_items.is_empty() {
         r#"
         <div class="no-news">
             <h2>No news found</h2>
@@ -561,6 +903,19 @@ fn format_news_html(news_items: &[NewsItem], query: &str) -> String {
             };
             
             let formatted_date = item.published_at.format("%a, %d %b %Y %H:%M:%S %z").to_string();
+
+            // How the price has moved since the story broke
+            let price_move_html = match (prices::price_at(history, item.published_at), history.last()) {
+                (Some(then), Some(latest)) if then > 0.0 => {
+                    let change = (latest.close - then) / then * 100.0;
+                    format!(
+                        r#"<span class="news-price-move {}">Price since: {:+.2}%</span>"#,
+                        if change >= 0.0 { "price-up" } else { "price-down" },
+                        change
+                    )
+                }
+                _ => String::new(),
+            };
             
             format!(
                 r#"
@@ -573,20 +928,97 @@ fn format_news_html(news_items: &[NewsItem], query: &str) -> String {
                         <span class="news-source">{}</span>
                         <span class="news-date">{}</span>
                         <span class="news-api">Source: {}</span>
+                        {}
+                        {}
                     </div>
                     <p class="news-summary">{}</p>
                 </div>
                 "#,
                 sentiment_class,
-                item.url,
-                item.title,
-                item.source,
+                escape_href(&item.url),
+                escape_html(&item.title),
+                escape_html(&item.source),
                 formatted_date,
-                item.api_source,
-                item.summary
+                escape_html(&item.api_source),
+                price_move_html,
+                bookmark_button(&item.url),
+                escape_html(&item.summary)
             )
         }).collect::<Vec<String>>().join("\n")
     };
+
+    let news_section_html = if news_html.is_empty() {
+        String::new()
+    } else {
+        format!(
+            r#"
+            <div class="news-container">
+                <h2>Latest News</h2>
+                {}
+            </div>
+            "#,
+            news_html
+        )
+    };
+
+    let archive_html = if archive.is_empty() {
+        String::new()
+    } else {
+        let hits = archive.iter().map(|hit| {
+            let item = &hit.article.news;
+            format!(
+                r#"
+                <div class="news-item">
+                    <h3 class="news-title">
+                        <a href="{}" target="_blank">{}</a>
+                    </h3>
+                    <div class="news-meta">
+                        <span class="news-source">{}</span>
+                        <span class="news-date">{}</span>
+                        <span class="news-api">Source: {}</span>
+                        {}
+                    </div>
+                    <p class="news-summary">{}</p>
+                </div>
+                "#,
+                escape_href(&item.url),
+                escape_html(&item.title),
+                escape_html(&item.source),
+                item.published_at.format("%a, %d %b %Y %H:%M:%S %z"),
+                escape_html(&item.api_source),
+                bookmark_button(&item.url),
+                // The snippet is already escaped, see `ArchiveHit`
+                if hit.snippet.is_empty() { escape_html(&item.summary) } else { hit.snippet.clone() }
+            )
+        }).collect::<Vec<String>>().join("\n");
+
+        format!(
+            r#"
+            <div class="news-container archive-container">
+                <h2>From the Archive</h2>
+                {}
+            </div>
+            "#,
+            hits
+        )
+    };
+
+    let mode_options = [SearchMode::Combined, SearchMode::Live, SearchMode::Archive]
+        .iter()
+        .map(|option| {
+            format!(
+                r#"<option value="{}"{}>{}</option>"#,
+                option.as_str(),
+                if *option == mode { " selected" } else { "" },
+                match option {
+                    SearchMode::Combined => "Live + archive",
+                    SearchMode::Live => "Live only",
+                    SearchMode::Archive => "Archive only",
+                }
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("");
     
     format!(
         r#"
@@ -652,6 +1084,18 @@ fn format_news_html(news_items: &[NewsItem], query: &str) -> String {
                     font-weight: bold;
                     color: #28a745;
                 }}
+                .crypto-market-cap {{
+                    color: #666;
+                    font-size: 14px;
+                }}
+                .price-up {{
+                    font-size: 16px;
+                    color: #28a745;
+                }}
+                .price-down {{
+                    font-size: 16px;
+                    color: #dc3545;
+                }}
                 .news-container {{
                     background-color: white;
                     padding: 20px;
@@ -690,6 +1134,11 @@ fn format_news_html(news_items: &[NewsItem], query: &str) -> String {
                     color: #333;
                     line-height: 1.5;
                 }}
+                .bookmark-button {{
+                    margin-left: auto;
+                    padding: 2px 10px;
+                    font-size: 13px;
+                }}
                 .sentiment-indicator {{
                     display: inline-block;
                     width: 12px;
@@ -724,6 +1173,39 @@ fn format_news_html(news_items: &[NewsItem], query: &str) -> String {
                 .no-news li {{
                     margin-bottom: 8px;
                 }}
+                .provider-failures {{
+                    background-color: #fff3cd;
+                    color: #856404;
+                    padding: 10px 20px;
+                    border-radius: 8px;
+                    margin-bottom: 20px;
+                }}
+                select {{
+                    padding: 10px;
+                    border: 1px solid #ddd;
+                    border-radius: 4px;
+                    font-size: 16px;
+                }}
+                .archive-container {{
+                    margin-top: 20px;
+                }}
+                .news-summary mark {{
+                    background-color: #fff3cd;
+                }}
+                .price-chart {{
+                    background-color: white;
+                    padding: 20px;
+                    border-radius: 8px;
+                    box-shadow: 0 2px 4px rgba(0,0,0,0.1);
+                    margin-bottom: 20px;
+                }}
+                .price-chart h3 {{
+                    margin-top: 0;
+                    color: #333;
+                }}
+                .news-price-move {{
+                    font-size: 14px;
+                }}
                 .back-link {{
                     display: inline-block;
                     margin-top: 20px;
@@ -739,62 +1221,368 @@ fn format_news_html(news_items: &[NewsItem], query: &str) -> String {
             <div class="search-container">
                 <form class="search-form" action="/search" method="get">
                     <input type="text" name="q" placeholder="Search for cryptocurrency news (e.g., BTC, ETH, Bitcoin)" value="{}">
+                    <select name="mode">{}</select>
                     <button type="submit">Search</button>
                 </form>
             </div>
             
             <div class="crypto-data">
                 <div class="crypto-symbol">Crypto Data: {}</div>
-                <div class="crypto-price">Current Price: {}</div>
+                <div class="crypto-quote">
+                    {}
+                </div>
             </div>
             
-            <div class="news-container">
-                <h2>Latest News</h2>
-                {}
-            </div>
+            {}
+            {}
+            {}
+            {}
             
             <a href="/" class="back-link">Back to Search</a>
+            <script>
+                async function bookmark(button) {{
+                    const response = await fetch('/bookmarks', {{
+                        method: 'POST',
+                        headers: {{ 'Content-Type': 'application/json' }},
+                        body: JSON.stringify({{ url: button.dataset.url }})
+                    }});
+                    if (response.status === 401) {{
+                        alert('Log in to bookmark articles');
+                    }} else if (response.ok) {{
+                        button.textContent = 'Bookmarked';
+                        button.disabled = true;
+                    }}
+                }}
+            </script>
         </body>
         </html>
         "#,
-        display_name,
-        query,
-        display_name,
-        current_price,
-        news_html
+        escape_html(&display_name),
+        escape_html(query),
+        mode_options,
+        escape_html(&display_name),
+        price_html,
+        render_price_chart(history, news_items),
+        failures_html,
+        news_section_html,
+        archive_html
     )
 }
 
-fn get_crypto_price(query: &str) -> String {
-    // This is a placeholder function that returns a mock price
-    // In a real application, you would fetch the actual price from a cryptocurrency API
-    match query.to_lowercase().as_str() {
-        "btc" | "bitcoin" => "$80,000.00".to_string(),
-        "eth" | "ethereum" | "ether" => "$3,500.00".to_string(),
-        "xrp" | "ripple" => "$0.52".to_string(),
-        "ltc" | "litecoin" => "$68.45".to_string(),
-        "doge" | "dogecoin" => "$0.12".to_string(),
-        "ada" | "cardano" => "$0.45".to_string(),
-        "dot" | "polkadot" => "$7.23".to_string(),
-        "sol" | "solana" => "$98.76".to_string(),
-        "link" | "chainlink" => "$15.34".to_string(),
-        "uni" | "uniswap" => "$5.67".to_string(),
-        _ => {
-            // Try to match by removing any whitespace and converting to lowercase
-            let normalized = query.to_lowercase().replace(" ", "");
-            match normalized.as_str() {
-                "btc" | "bitcoin" => "$80,000.00".to_string(),
-                "eth" | "ethereum" | "ether" => "$3,500.00".to_string(),
-                "xrp" | "ripple" => "$0.52".to_string(),
-                "ltc" | "litecoin" => "$68.45".to_string(),
-                "doge" | "dogecoin" => "$0.12".to_string(),
-                "ada" | "cardano" => "$0.45".to_string(),
-                "dot" | "polkadot" => "$7.23".to_string(),
-                "sol" | "solana" => "$98.76".to_string(),
-                "link" | "chainlink" => "$15.34".to_string(),
-                "uni" | "uniswap" => "$5.67".to_string(),
-                _ => "N/A".to_string(),
+fn render_price_chart(history: &[PricePoint], news_items: &[NewsItem]) -> String {
+    if history.len() < 2 {
+        return String::new();
+    }
+
+    format!(
+        r#"
+            <div class="price-chart">
+                <h3>{}-day price</h3>
+                {}
+            </div>
+        "#,
+        prices::HISTORY_DAYS,
+        render_sparkline(history, news_items)
+    )
+}
+
+const SPARKLINE_WIDTH: f64 = 600.0;
+const SPARKLINE_HEIGHT: f64 = 120.0;
+const SPARKLINE_PADDING: f64 = 6.0;
+
+/// Inline SVG of the closing prices with a marker for every headline
+/// published inside the charted window. Hovering a marker shows the headline
+/// through the SVG `<title>` element, so no JavaScript is needed.
+fn render_sparkline(history: &[PricePoint], news_items: &[NewsItem]) -> String {
+    let (Some(first), Some(last)) = (history.first(), history.last()) else {
+        return String::new();
+    };
+
+    let start = first.timestamp.timestamp() as f64;
+    let end = last.timestamp.timestamp() as f64;
+    let span = (end - start).max(1.0);
+    let min = history.iter().map(|p| p.close).fold(f64::INFINITY, f64::min);
+    let max = history.iter().map(|p| p.close).fold(f64::NEG_INFINITY, f64::max);
+    let range = if max > min { max - min } else { 1.0 };
+
+    let x_at = |seconds: f64| {
+        SPARKLINE_PADDING + (seconds - start) / span * (SPARKLINE_WIDTH - 2.0 * SPARKLINE_PADDING)
+    };
+    let y_at = |price: f64| {
+        SPARKLINE_HEIGHT - SPARKLINE_PADDING
+            - (price - min) / range * (SPARKLINE_HEIGHT - 2.0 * SPARKLINE_PADDING)
+    };
+
+    let line = history
+        .iter()
+        .map(|p| format!("{:.1},{:.1}", x_at(p.timestamp.timestamp() as f64), y_at(p.close)))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let markers = news_items
+        .iter()
+        .filter(|item| item.published_at >= first.timestamp && item.published_at <= last.timestamp)
+        .filter_map(|item| {
+            let price = prices::price_at(history, item.published_at)?;
+            let x = x_at(item.published_at.timestamp() as f64);
+            Some(format!(
+                r##"<g class="news-marker"><line x1="{x:.1}" y1="0" x2="{x:.1}" y2="{h}" stroke="#ffc107" stroke-dasharray="2,2"/><circle cx="{x:.1}" cy="{y:.1}" r="4" fill="#ffc107"><title>{} ({}) at {}</title></circle></g>"##,
+                escape_html(&item.title),
+                item.published_at.format("%Y-%m-%d %H:%M UTC"),
+                prices::format_usd(price),
+                x = x,
+                y = y_at(price),
+                h = SPARKLINE_HEIGHT,
+            ))
+        })
+        .collect::<Vec<_>>()
+        .join("");
+
+    let stroke = if last.close >= first.close { "#28a745" } else { "#dc3545" };
+
+    format!(
+        r#"<svg class="sparkline" viewBox="0 0 {w} {h}" width="100%" height="{h}" preserveAspectRatio="none" role="img" aria-label="Price from {} to {}"><polyline fill="none" stroke="{}" stroke-width="2" points="{}"/>{}</svg>"#,
+        prices::format_usd(first.close),
+        prices::format_usd(last.close),
+        stroke,
+        line,
+        markers,
+        w = SPARKLINE_WIDTH,
+        h = SPARKLINE_HEIGHT,
+    )
+}
+
+fn bookmark_button(url: &str) -> String {
+    format!(
+        r#"<button class="bookmark-button" data-url="{}" onclick="bookmark(this)">Bookmark</button>"#,
+        escape_html(url)
+    )
+}
+
+pub(crate) fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// An article URL for an `href`. Feeds can carry `javascript:` or `data:`
+/// links, which would run on our origin, so anything but http(s) becomes `#`.
+pub(crate) fn escape_href(url: &str) -> String {
+    match url.trim_start().split_once(':').map(|(scheme, _)| scheme.to_ascii_lowercase()).as_deref() {
+        Some("http" | "https") => escape_html(url),
+        _ => "#".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn point(hour: u32, close: f64) -> PricePoint {
+        PricePoint {
+            timestamp: Utc.with_ymd_and_hms(2025, 4, 7, hour, 0, 0).unwrap(),
+            open: close,
+            high: close,
+            low: close,
+            close,
+        }
+    }
+
+    fn headline(title: &str, hour: u32) -> NewsItem {
+        NewsItem {
+            title: title.to_string(),
+            source: "test".to_string(),
+            url: format!("https://example.com/{}", hour),
+            published_at: Utc.with_ymd_and_hms(2025, 4, 7, hour, 30, 0).unwrap(),
+            summary: String::new(),
+            sentiment: "Neutral".to_string(),
+            api_source: "test".to_string(),
+        }
+    }
+
+    #[test]
+    fn sparkline_marks_headlines_inside_the_window() {
+        let history = vec![point(0, 100.0), point(4, 110.0), point(8, 105.0)];
+        let news = vec![headline("Fed & <crypto>", 4), headline("Too late", 9)];
+
+        let svg = render_sparkline(&history, &news);
+
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains(r#"points="6.0,114.0 300.0,6.0 594.0,60.0""#));
+        assert_eq!(svg.matches("news-marker").count(), 1);
+        assert!(svg.contains("<title>Fed &amp; &lt;crypto&gt; (2025-04-07 04:30 UTC) at $110.00</title>"));
+    }
+
+    #[test]
+    fn price_chart_needs_at_least_two_points() {
+        assert_eq!(render_price_chart(&[point(0, 100.0)], &[]), "");
+    }
+
+    #[tokio::test]
+    async fn homepage_shows_the_watchlist_feed_to_signed_in_users() {
+        use axum::{routing::get, Router};
+
+        let state = AppState::for_tests().await;
+        state.db.create_user("satoshi", "satoshi@example.com", "hunter2").await.unwrap();
+        let user = state.db.find_user("satoshi").await.unwrap().unwrap();
+        state.db.watch_coin(user.id, "bitcoin").await.unwrap();
+        state.db.store_news_items("btc", &[headline("Miners <rush> to upgrade", 3)]).await.unwrap();
+        let token = state.jwt.create_token("satoshi").unwrap();
+
+        let app = Router::new().route("/", get(homepage)).with_state(state);
+        let url = format!("{}/", crate::spawn_app(app).await);
+        let client = reqwest::Client::new();
+
+        let page = client.get(&url).header("Cookie", format!("token={}", token)).send().await.unwrap().text().await.unwrap();
+        assert!(page.contains("Your Watchlist"));
+        assert!(page.contains("Miners &lt;rush&gt; to upgrade"));
+
+        let page = client.get(&url).send().await.unwrap().text().await.unwrap();
+        assert!(!page.contains("Your Watchlist"));
+    }
+
+    #[tokio::test]
+    async fn search_renders_one_cached_fetch_as_json_and_rss() {
+        use crate::api::tests::StaticProvider;
+        use axum::{routing::get, Router};
+        use std::sync::Arc;
+
+        let mut state = AppState::for_tests().await;
+        state.providers = Arc::new(vec![Box::new(StaticProvider {
+            name: "A",
+            result: Ok(vec![headline("Bitcoin & friends", 3)]),
+        })]);
+
+        let app = Router::new().route("/search", get(handle_search)).with_state(state);
+        let url = format!("{}/search", crate::spawn_app(app).await);
+        let client = reqwest::Client::new();
+
+        let response = client.get(format!("{}?q=BTC&mode=live&format=json", url)).send().await.unwrap();
+        assert_eq!(response.status(), 200);
+        let results: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(results["query"], "btc");
+        assert_eq!(results["mode"], "live");
+        assert!(results["fetched_at"].is_string());
+        assert_eq!(results["items"][0]["title"], "Bitcoin & friends");
+
+        let response = client.get(format!("{}?q=btc&mode=live&format=rss", url)).send().await.unwrap();
+        assert_eq!(response.headers()["content-type"], "application/rss+xml; charset=utf-8");
+        let items = feeds::parse_feed(&response.text().await.unwrap()).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].title, "Bitcoin & friends");
+
+        let page = client.get(format!("{}?q=btc&mode=live", url)).send().await.unwrap().text().await.unwrap();
+        assert!(page.contains("friends"));
+
+        let response = client.get(format!("{}?q=+&format=json", url)).send().await.unwrap();
+        assert_eq!(response.status(), 400);
+    }
+
+    #[test]
+    fn news_pages_escape_article_fields() {
+        let hostile = NewsItem {
+            title: "<script>alert(1)</script>".to_string(),
+            source: "<b>feed</b>".to_string(),
+            url: "\"><img src=x onerror=alert(1)>".to_string(),
+            published_at: Utc.with_ymd_and_hms(2025, 4, 7, 12, 0, 0).unwrap(),
+            summary: "<iframe></iframe>".to_string(),
+            sentiment: "Neutral".to_string(),
+            api_source: "RSS".to_string(),
+        };
+        let hit = ArchiveHit {
+            article: crate::db::StoredArticle { id: 1, news: hostile.clone(), first_seen_at: Utc::now() },
+            rank: -1.0,
+            snippet: String::new(),
+        };
+
+        let page = format_news_html(&[hostile], &[], &[hit], None, &[], "<svg onload=alert(1)>", SearchMode::Combined);
+        for raw in ["<script>alert", "<b>", "<img", "<iframe>", "<svg"] {
+            assert!(!page.contains(raw), "{} is not escaped", raw);
+        }
+        assert_eq!(page.matches("&lt;script&gt;alert(1)&lt;/script&gt;").count(), 2);
+    }
+
+    #[test]
+    fn only_http_links_are_rendered() {
+        let rss = r#"<rss><channel><title>Feed</title>
+            <item><title>Bitcoin trap</title><link>javascript:alert(1)</link><pubDate>Mon, 07 Apr 2025 12:00:00 +0000</pubDate></item>
+            <item><title>Bitcoin data</title><link> DATA:text/html,&lt;script&gt;</link><pubDate>Mon, 07 Apr 2025 11:00:00 +0000</pubDate></item>
+            <item><title>Bitcoin real</title><link>https://example.com/btc?a=1&amp;b=2</link><pubDate>Mon, 07 Apr 2025 10:00:00 +0000</pubDate></item>
+        </channel></rss>"#;
+        let items = crate::feeds::parse_feed(rss).unwrap();
+        assert_eq!(items[0].url, "javascript:alert(1)");
+
+        let page = format_news_html(&items, &[], &[], None, &[], "btc", SearchMode::Live);
+        assert_eq!(page.matches(r##"href="#""##).count(), 2);
+        assert!(page.contains(r#"href="https://example.com/btc?a=1&amp;b=2""#));
+        assert!(!page.to_lowercase().contains("href=\"javascript:") && !page.to_lowercase().contains("href=\" data:"));
+    }
+
+    #[tokio::test]
+    async fn search_hides_provider_errors_from_clients() {
+        use crate::api::tests::StaticProvider;
+        use axum::{routing::get, Router};
+        use std::sync::Arc;
+
+        let leaky = || StaticProvider { name: "B", result: Err("error for url (https://example.com/?apikey=secret)".to_string()) };
+        let mut state = AppState::for_tests().await;
+        state.providers = Arc::new(vec![
+            Box::new(StaticProvider { name: "A", result: Ok(vec![headline("Bitcoin & friends", 3)]) }),
+            Box::new(leaky()),
+        ]);
+        let partial = Router::new().route("/search", get(handle_search)).with_state(state.clone());
+        state.providers = Arc::new(vec![Box::new(leaky())]);
+        let failed = Router::new().route("/search", get(handle_search)).with_state(state);
+        let client = reqwest::Client::new();
+        let mut urls = Vec::new();
+
+        for app in [partial, failed] {
+            let url = format!("{}/search", crate::spawn_app(app).await);
+            urls.push(url.clone());
+
+            for format in ["json", "rss", "html"] {
+                let body = client
+                    .get(format!("{}?q=btc&mode=live&format={}", url, format))
+                    .send()
+                    .await
+                    .unwrap()
+                    .text()
+                    .await
+                    .unwrap();
+                assert!(!body.contains("secret"), "{}", body);
             }
         }
+
+        let results: serde_json::Value = client.get(format!("{}?q=btc&mode=live&format=json", urls[0])).send().await.unwrap().json().await.unwrap();
+        assert_eq!(results["failures"][0]["provider"], "B");
+        assert_eq!(results["failures"][0]["error"], PROVIDER_FAILED);
+    }
+
+    #[tokio::test]
+    async fn searches_without_results_are_not_errors() {
+        use crate::api::tests::StaticProvider;
+        use axum::{routing::get, Router};
+        use std::sync::Arc;
+
+        let mut state = AppState::for_tests().await;
+        state.providers = Arc::new(vec![
+            Box::new(StaticProvider { name: "A", result: Ok(Vec::new()) }),
+            Box::new(StaticProvider { name: "B", result: Ok(Vec::new()) }),
+        ]);
+        let app = Router::new().route("/search", get(handle_search)).with_state(state);
+        let url = format!("{}/search", crate::spawn_app(app).await);
+        let client = reqwest::Client::new();
+
+        let response = client.get(format!("{}?q=nothing&mode=live&format=json", url)).send().await.unwrap();
+        assert_eq!(response.status(), 200);
+        let results: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(results["items"], serde_json::json!([]));
+        assert_eq!(results["failures"], serde_json::json!([]));
+
+        let response = client.get(format!("{}?q=nothing&mode=live&format=rss", url)).send().await.unwrap();
+        assert_eq!(response.status(), 200);
     }
+
 }
@@ -0,0 +1,79 @@
+use futures_util::future::{BoxFuture, FutureExt, Shared};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+
+/// Coalesces concurrent work by key: while one call for a key is running,
+/// later calls for the same key wait for its result instead of starting
+/// their own.
+pub struct SingleFlight<T> {
+    inflight: Mutex<HashMap<String, Shared<BoxFuture<'static, T>>>>,
+}
+
+impl<T> Default for SingleFlight<T> {
+    fn default() -> Self {
+        SingleFlight { inflight: Mutex::new(HashMap::new()) }
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static> SingleFlight<T> {
+    /// Runs `work` unless a call for `key` is already in flight, in which
+    /// case `work` is dropped and that call's result is returned.
+    pub async fn run<F>(&self, key: &str, work: F) -> T
+    where
+        F: Future<Output = T> + Send + 'static,
+    {
+        let flight = self
+            .inflight
+            .lock()
+            .unwrap()
+            .entry(key.to_string())
+            .or_insert_with(|| work.boxed().shared())
+            .clone();
+
+        let result = flight.clone().await;
+
+        // A newer flight may already have replaced this one
+        let mut inflight = self.inflight.lock().unwrap();
+        if inflight.get(key).is_some_and(|current| current.ptr_eq(&flight)) {
+            inflight.remove(key);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn concurrent_calls_share_one_run_per_key() {
+        let flights = Arc::new(SingleFlight::<u32>::default());
+        let runs = Arc::new(AtomicU32::new(0));
+
+        let call = |key: &'static str| {
+            let flights = flights.clone();
+            let runs = runs.clone();
+            async move {
+                flights
+                    .run(key, async move {
+                        tokio::time::sleep(Duration::from_millis(50)).await;
+                        runs.fetch_add(1, Ordering::SeqCst) + 1
+                    })
+                    .await
+            }
+        };
+
+        let results = futures_util::future::join_all([call("a"), call("a"), call("a"), call("b")]).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+        assert_eq!(results[0], results[1]);
+        assert_eq!(results[1], results[2]);
+        assert!(flights.inflight.lock().unwrap().is_empty());
+
+        // Finished flights are not reused
+        assert_eq!(call("a").await, 3);
+    }
+}
@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+use crate::api::canonical_coin;
+use crate::cache::Cache;
+
+/// All-time search counts, never expired. The cache keeps only the most
+/// searched terms, so rare ones drop out.
+const MOST_SEARCHED_KEY: &str = "search_counts";
+
+/// A sliding window that trending searches are computed over. Searches are
+/// counted in time buckets that expire once they leave the window, and
+/// older buckets weigh exponentially less.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Window {
+    Hour,
+    Day,
+    Week,
+}
+
+impl Window {
+    pub const ALL: [Window; 3] = [Window::Hour, Window::Day, Window::Week];
+
+    pub fn from_param(window: &str) -> Option<Self> {
+        match window.trim().to_lowercase().as_str() {
+            "1h" => Some(Window::Hour),
+            "24h" => Some(Window::Day),
+            "7d" => Some(Window::Week),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Window::Hour => "1h",
+            Window::Day => "24h",
+            Window::Week => "7d",
+        }
+    }
+
+    fn length_secs(self) -> u64 {
+        match self {
+            Window::Hour => 60 * 60,
+            Window::Day => 24 * 60 * 60,
+            Window::Week => 7 * 24 * 60 * 60,
+        }
+    }
+
+    fn bucket_secs(self) -> u64 {
+        match self {
+            Window::Hour => 5 * 60,
+            Window::Day => 60 * 60,
+            Window::Week => 24 * 60 * 60,
+        }
+    }
+
+    /// A search counts half as much this long after it was made.
+    fn half_life_secs(self) -> f64 {
+        self.length_secs() as f64 / 4.0
+    }
+
+    fn bucket_key(self, bucket: u64) -> String {
+        format!("trending:{}:{}", self.as_str(), bucket)
+    }
+
+    /// The buckets covering the window ending at `now`, newest first, with
+    /// the weight of the searches counted in each.
+    fn buckets(self, now: u64) -> Vec<(u64, f64)> {
+        let bucket_secs = self.bucket_secs();
+        let current = now / bucket_secs;
+        let count = self.length_secs() / bucket_secs;
+
+        (0..count)
+            .filter_map(|back| current.checked_sub(back))
+            .map(|bucket| {
+                // Age of the middle of the bucket, or of its elapsed part
+                let start = bucket * bucket_secs;
+                let middle = start + (now.min(start + bucket_secs) - start) / 2;
+                let age = now.saturating_sub(middle) as f64;
+                (bucket, 0.5f64.powf(age / self.half_life_secs()))
+            })
+            .collect()
+    }
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
+
+/// Counts a search under its canonical coin, so `btc` and `bitcoin` add up.
+pub async fn record_search(cache: &Cache, term: &str) {
+    let term = canonical_coin(term);
+    if term.is_empty() {
+        return;
+    }
+    record_search_at(cache, &term, unix_now()).await;
+}
+
+async fn record_search_at(cache: &Cache, term: &str, now: u64) {
+    cache.incr_score(MOST_SEARCHED_KEY, term, 1.0, None).await;
+    for window in Window::ALL {
+        let bucket = now / window.bucket_secs();
+        // Kept until the bucket has slid out of the window
+        let ttl = window.length_secs() + window.bucket_secs();
+        cache.incr_score(&window.bucket_key(bucket), term, 1.0, Some(ttl)).await;
+    }
+}
+
+/// The `count` terms searched most in `window`, with recent searches
+/// weighing more, highest score first.
+pub async fn trending(cache: &Cache, window: Window, count: usize) -> Vec<(String, f64)> {
+    trending_at(cache, window, count, unix_now()).await
+}
+
+async fn trending_at(cache: &Cache, window: Window, count: usize, now: u64) -> Vec<(String, f64)> {
+    let buckets = window.buckets(now);
+    let keys: Vec<String> = buckets.iter().map(|(bucket, _)| window.bucket_key(*bucket)).collect();
+
+    let mut scores: HashMap<String, f64> = HashMap::new();
+    for ((_, weight), bucket) in buckets.iter().zip(cache.all_scores(&keys).await) {
+        for (term, searches) in bucket {
+            *scores.entry(term).or_insert(0.0) += searches * weight;
+        }
+    }
+
+    let mut scores: Vec<(String, f64)> = scores.into_iter().collect();
+    scores.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    scores.truncate(count);
+    scores
+}
+
+/// The `count` terms searched most since counting began.
+pub async fn most_searched(cache: &Cache, count: usize) -> Vec<(String, u64)> {
+    cache
+        .top_scores(MOST_SEARCHED_KEY, count)
+        .await
+        .into_iter()
+        .map(|(term, searches)| (term, searches as u64))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::MemoryBackend;
+
+    const NOW: u64 = 1_744_000_000;
+
+    fn cache() -> Cache {
+        Cache::new(vec![Box::new(MemoryBackend::new(100))])
+    }
+
+    #[tokio::test]
+    async fn recent_searches_outrank_older_ones() {
+        let cache = cache();
+        for _ in 0..3 {
+            record_search_at(&cache, "ethereum", NOW - 20 * 60 * 60).await;
+        }
+        record_search_at(&cache, "bitcoin", NOW - 10 * 60).await;
+        record_search_at(&cache, "bitcoin", NOW).await;
+
+        let day = trending_at(&cache, Window::Day, 5, NOW).await;
+        let terms: Vec<&str> = day.iter().map(|(term, _)| term.as_str()).collect();
+        assert_eq!(terms, vec!["bitcoin", "ethereum"]);
+
+        // Searches older than the window no longer count
+        let hour = trending_at(&cache, Window::Hour, 5, NOW).await;
+        assert_eq!(hour.len(), 1);
+        assert_eq!(hour[0].0, "bitcoin");
+
+        // Most searched is all-time and undecayed
+        assert_eq!(most_searched(&cache, 5).await, vec![("ethereum".to_string(), 3), ("bitcoin".to_string(), 2)]);
+    }
+
+    #[tokio::test]
+    async fn searches_are_counted_by_canonical_coin() {
+        let cache = cache();
+        for term in ["BTC", "bitcoin", " Bitcoin ", "ether", "defi"] {
+            record_search(&cache, term).await;
+        }
+
+        let counts = most_searched(&cache, 5).await;
+        assert_eq!(counts[0], ("bitcoin".to_string(), 3));
+        assert!(counts.contains(&("ethereum".to_string(), 1)));
+        assert!(counts.contains(&("defi".to_string(), 1)));
+        assert_eq!(trending(&cache, Window::Week, 1).await[0].0, "bitcoin");
+    }
+
+    #[test]
+    fn weights_halve_every_quarter_window() {
+        let buckets = Window::Day.buckets(NOW - NOW % 3600);
+        assert_eq!(buckets.len(), 24);
+        // The current bucket has only just started
+        assert_eq!(buckets[0].1, 1.0);
+        let six_hours = buckets[6].1;
+        assert!((0.45..0.55).contains(&six_hours), "{}", six_hours);
+        assert_eq!(Window::from_param("7D"), Some(Window::Week));
+        assert_eq!(Window::from_param("1d"), None);
+    }
+}
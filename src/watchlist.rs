@@ -0,0 +1,236 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::api::canonical_coin;
+use crate::auth::{AuthUser, ErrorResponse};
+use crate::db::StoredArticle;
+use crate::routes::{escape_href, escape_html};
+use crate::AppState;
+
+/// Number of stored articles in the homepage watchlist feed.
+pub const FEED_SIZE: i64 = 20;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct WatchRequest {
+    pub coin: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct WatchlistResponse {
+    /// Canonical coin names in the order they were added.
+    pub coins: Vec<String>,
+}
+
+fn storage_error(e: String) -> Response {
+    tracing::error!("Watchlist storage error: {}", e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ErrorResponse { error: "Failed to update watchlist".to_string() }),
+    )
+        .into_response()
+}
+
+async fn coins_response(state: &AppState, user_id: i64, status: StatusCode) -> Response {
+    match state.db.watched_coins(user_id).await {
+        Ok(coins) => (status, Json(WatchlistResponse { coins })).into_response(),
+        Err(e) => storage_error(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/watchlist",
+    tag = "watchlist",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The user's watched coins", body = WatchlistResponse),
+        (status = 401, description = "Not signed in", body = ErrorResponse),
+    )
+)]
+pub async fn list_watchlist(State(state): State<AppState>, auth: AuthUser) -> Response {
+    coins_response(&state, auth.user.id, StatusCode::OK).await
+}
+
+/// Adds a coin by name or ticker ("btc", "Bitcoin"); it is stored under its
+/// canonical name. Responds 201 when it was added and 200 when it was
+/// already watched, with the full watchlist either way.
+#[utoipa::path(
+    post,
+    path = "/watchlist",
+    tag = "watchlist",
+    security(("bearer_auth" = [])),
+    request_body = WatchRequest,
+    responses(
+        (status = 201, description = "Coin added", body = WatchlistResponse),
+        (status = 200, description = "Coin was already watched", body = WatchlistResponse),
+        (status = 400, description = "No coin given", body = ErrorResponse),
+        (status = 401, description = "Not signed in", body = ErrorResponse),
+    )
+)]
+pub async fn add_to_watchlist(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(request): Json<WatchRequest>,
+) -> Response {
+    let coin = canonical_coin(&request.coin);
+    if coin.is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: "Coin is required".to_string() })).into_response();
+    }
+
+    match state.db.watch_coin(auth.user.id, &coin).await {
+        Ok(true) => coins_response(&state, auth.user.id, StatusCode::CREATED).await,
+        Ok(false) => coins_response(&state, auth.user.id, StatusCode::OK).await,
+        Err(e) => storage_error(e),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/watchlist/{coin}",
+    tag = "watchlist",
+    security(("bearer_auth" = [])),
+    params(("coin" = String, Path, description = "Ticker or name, e.g. btc")),
+    responses(
+        (status = 204, description = "Coin removed"),
+        (status = 401, description = "Not signed in", body = ErrorResponse),
+        (status = 404, description = "Coin is not on the watchlist", body = ErrorResponse),
+    )
+)]
+pub async fn remove_from_watchlist(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(coin): Path<String>,
+) -> Response {
+    match state.db.unwatch_coin(auth.user.id, &canonical_coin(&coin)).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => (StatusCode::NOT_FOUND, Json(ErrorResponse { error: "Coin is not on the watchlist".to_string() })).into_response(),
+        Err(e) => storage_error(e),
+    }
+}
+
+/// The homepage section for a signed-in user: their coins, a form to add
+/// more, and the latest archived news for them.
+pub fn render_watchlist(coins: &[String], feed: &[StoredArticle]) -> String {
+    let chips = coins
+        .iter()
+        .map(|coin| {
+            format!(
+                r#"<span class="watch-chip"><a href="/search?q={}">{1}</a> <button class="watch-remove" title="Remove" data-coin="{1}" onclick="unwatchCoin(this.dataset.coin)">&times;</button></span>"#,
+                escape_html(&urlencoding::encode(coin)),
+                escape_html(coin)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let feed_html = if coins.is_empty() {
+        "<p>Add coins to your watchlist to see their latest news here.</p>".to_string()
+    } else if feed.is_empty() {
+        "<p>No stored news for your coins yet. Search for one to fetch the latest headlines.</p>".to_string()
+    } else {
+        feed.iter()
+            .map(|article| {
+                let item = &article.news;
+                format!(
+                    r#"
+                    <div class="search-item">
+                        <a href="{}" target="_blank">{}</a>
+                        <div class="watch-meta">{} &middot; {} &middot; {}</div>
+                    </div>
+                    "#,
+                    escape_href(&item.url),
+                    escape_html(&item.title),
+                    escape_html(&item.source),
+                    item.published_at.format("%d %b %Y %H:%M"),
+                    escape_html(&item.sentiment)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    format!(
+        r#"
+            <div class="top-searches" id="watchlist">
+                <h2>Your Watchlist</h2>
+                <div class="watch-chips">{}</div>
+                <form id="watchForm" class="watch-form">
+                    <input type="text" id="watchInput" placeholder="Add a coin, e.g. BTC or solana" required>
+                    <button type="submit">Watch</button>
+                </form>
+                <h3>Latest for your coins</h3>
+                {}
+            </div>
+            <script>
+                document.getElementById('watchForm').addEventListener('submit', async (e) => {{
+                    e.preventDefault();
+                    const coin = document.getElementById('watchInput').value.trim();
+                    if (coin === '') {{
+                        return;
+                    }}
+                    await fetch('/watchlist', {{
+                        method: 'POST',
+                        headers: {{ 'Content-Type': 'application/json' }},
+                        body: JSON.stringify({{ coin }})
+                    }});
+                    window.location.reload();
+                }});
+
+                async function unwatchCoin(coin) {{
+                    await fetch('/watchlist/' + encodeURIComponent(coin), {{ method: 'DELETE' }});
+                    window.location.reload();
+                }}
+            </script>
+        "#,
+        chips, feed_html
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use axum::{routing::{delete, get}, Router};
+
+    #[test]
+    fn coin_links_are_percent_encoded() {
+        let html = render_watchlist(&["a&b #1+".to_string()], &[]);
+        assert!(html.contains(r#"<a href="/search?q=a%26b%20%231%2B">a&amp;b #1+</a>"#), "{}", html);
+    }
+
+    #[tokio::test]
+    async fn watchlist_crud_normalizes_coins() {
+        let state = AppState::for_tests().await;
+        state.db.create_user("satoshi", "satoshi@example.com", "hunter2").await.unwrap();
+        let token = state.jwt.create_token("satoshi").unwrap();
+
+        let app = Router::new()
+            .route("/watchlist", get(list_watchlist).post(add_to_watchlist))
+            .route("/watchlist/:coin", delete(remove_from_watchlist))
+            .with_state(state);
+        let base = format!("{}/watchlist", crate::spawn_app(app).await);
+        let client = reqwest::Client::new();
+        let add = |coin: &str| client.post(&base).bearer_auth(&token).json(&json!({"coin": coin})).send();
+
+        assert_eq!(add("BTC").await.unwrap().status(), 201);
+        let response = add("bitcoin").await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.json::<serde_json::Value>().await.unwrap(), json!({"coins": ["bitcoin"]}));
+        assert_eq!(add("ether").await.unwrap().status(), 201);
+        assert_eq!(add("  ").await.unwrap().status(), 400);
+
+        let remove = |coin: &str| client.delete(format!("{}/{}", base, coin)).bearer_auth(&token).send();
+        assert_eq!(remove("btc").await.unwrap().status(), 204);
+        assert_eq!(remove("btc").await.unwrap().status(), 404);
+
+        let response = client.get(&base).bearer_auth(&token).send().await.unwrap();
+        assert_eq!(response.json::<serde_json::Value>().await.unwrap(), json!({"coins": ["ethereum"]}));
+
+        assert_eq!(client.get(&base).send().await.unwrap().status(), 401);
+    }
+}
@@ -0,0 +1,405 @@
+use axum::{
+    extract::ws::{Message, WebSocket, WebSocketUpgrade},
+    extract::State,
+    response::IntoResponse,
+};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::time::Instant;
+use crate::api::{canonical_coin, NewsItem};
+use crate::db::ArticleFilter;
+use crate::metrics::ConnectionGuard;
+use crate::prices::{self, PriceQuote};
+use crate::{news, AppState};
+
+/// Version of the message schema below. Every message carries it as `v`;
+/// clients speaking another version get an `unsupported_version` error.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Stored headlines sent when a coin is subscribed.
+const SNAPSHOT_SIZE: i64 = 20;
+
+/// The server pings this often and drops clients that stay silent for
+/// `IDLE_TIMEOUT`.
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+const IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// Messages a client sends, e.g. `{"v": 1, "id": "1", "type": "subscribe", "coin": "btc"}`.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Subscribe { coin: String },
+    Unsubscribe { coin: String },
+    Search { query: String },
+    Ping,
+    Pong,
+}
+
+/// Messages the server sends. Replies echo the request id; pushed `news`
+/// and server `ping`s have none.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Subscribed { coin: String },
+    Unsubscribed { coin: String },
+    News { coin: String, items: Vec<NewsItem> },
+    Price(PriceQuote),
+    Error { code: String, message: String },
+    Ping,
+    Pong,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Envelope<T> {
+    pub v: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(flatten)]
+    pub message: T,
+}
+
+impl ServerMessage {
+    fn error(code: &str, message: impl Into<String>) -> Self {
+        ServerMessage::Error { code: code.to_string(), message: message.into() }
+    }
+}
+
+/// Splits a text frame into its request id and message. The id is recovered
+/// even when the rest is invalid so the error can be correlated.
+fn parse_client_message(text: &str) -> (Option<String>, Result<ClientMessage, ServerMessage>) {
+    let value: Value = match serde_json::from_str(text) {
+        Ok(value) => value,
+        Err(e) => return (None, Err(ServerMessage::error("invalid_message", format!("Invalid JSON: {}", e)))),
+    };
+    let id = match value.get("id") {
+        Some(Value::String(id)) => Some(id.clone()),
+        Some(Value::Number(id)) => Some(id.to_string()),
+        _ => None,
+    };
+
+    match value.get("v").and_then(Value::as_u64) {
+        Some(v) if v == PROTOCOL_VERSION as u64 => {}
+        _ => {
+            let message = format!("Only protocol version {} is supported", PROTOCOL_VERSION);
+            return (id, Err(ServerMessage::error("unsupported_version", message)));
+        }
+    }
+
+    let message = serde_json::from_value(value)
+        .map_err(|e| ServerMessage::error("invalid_message", e.to_string()));
+    (id, message)
+}
+
+pub async fn handle_ws(
+    State(state): State<AppState>,
+    ws: WebSocketUpgrade,
+) -> impl IntoResponse {
+    ws.on_upgrade(|socket| handle_socket(socket, state))
+}
+
+struct Connection {
+    socket: WebSocket,
+    subscribed: HashSet<String>,
+}
+
+impl Connection {
+    async fn send(&mut self, id: Option<String>, message: ServerMessage) -> bool {
+        let envelope = Envelope { v: PROTOCOL_VERSION, id, message };
+        match serde_json::to_string(&envelope) {
+            Ok(text) => self.socket.send(Message::Text(text)).await.is_ok(),
+            Err(e) => {
+                tracing::error!("Failed to encode WebSocket message: {}", e);
+                true
+            }
+        }
+    }
+}
+
+async fn handle_socket(socket: WebSocket, state: AppState) {
+    let _open = ConnectionGuard::new(&state.metrics);
+    let mut connection = Connection { socket, subscribed: HashSet::new() };
+    let mut updates = state.tx.subscribe();
+    let mut ping = tokio::time::interval_at(Instant::now() + PING_INTERVAL, PING_INTERVAL);
+    let mut last_seen = Instant::now();
+
+    loop {
+        let open = tokio::select! {
+            msg = connection.socket.next() => match msg {
+                Some(Ok(Message::Text(text))) => {
+                    last_seen = Instant::now();
+                    handle_message(&state, &mut connection, &text).await
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => false,
+                Some(Ok(_)) => {
+                    last_seen = Instant::now();
+                    true
+                }
+            },
+            update = updates.recv() => match update {
+                Ok(update) if connection.subscribed.contains(&update.coin) => {
+                    let message = ServerMessage::News { coin: update.coin, items: update.news };
+                    connection.send(None, message).await
+                }
+                Ok(_) => true,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("WebSocket client missed {} news updates", skipped);
+                    state.metrics.broadcast_lagged.with_label_values(&["ws"]).inc_by(skipped);
+                    true
+                }
+                Err(broadcast::error::RecvError::Closed) => false,
+            },
+            _ = ping.tick() => {
+                last_seen.elapsed() < IDLE_TIMEOUT && connection.send(None, ServerMessage::Ping).await
+            }
+        };
+
+        if !open {
+            break;
+        }
+    }
+
+    for coin in &connection.subscribed {
+        state.subscriptions.remove(coin);
+    }
+}
+
+/// Answers one client message; false once the socket is closed.
+async fn handle_message(state: &AppState, connection: &mut Connection, text: &str) -> bool {
+    let (id, message) = parse_client_message(text);
+    let message = match message {
+        Ok(message) => message,
+        Err(error) => return connection.send(id, error).await,
+    };
+
+    match message {
+        ClientMessage::Subscribe { coin } => {
+            let coin = canonical_coin(&coin);
+            if coin.is_empty() {
+                return connection.send(id, ServerMessage::error("invalid_coin", "Coin is required")).await;
+            }
+            if connection.subscribed.insert(coin.clone()) {
+                state.subscriptions.add(&coin);
+            }
+            if !connection.send(id.clone(), ServerMessage::Subscribed { coin: coin.clone() }).await {
+                return false;
+            }
+
+            // Start from the archive; a coin nobody has fetched yet is fetched
+            // now and its articles arrive as a pushed `news` message
+            let filter = ArticleFilter { coin: Some(coin.clone()), limit: Some(SNAPSHOT_SIZE), ..Default::default() };
+            let items: Vec<NewsItem> = match state.db.list_articles(&filter).await {
+                Ok(articles) => articles.into_iter().map(|article| article.news).collect(),
+                Err(e) => {
+                    tracing::error!("Failed to load stored news for {}: {}", coin, e);
+                    Vec::new()
+                }
+            };
+            let fetch_now = items.is_empty();
+            if !connection.send(id.clone(), ServerMessage::News { coin: coin.clone(), items }).await {
+                return false;
+            }
+
+            if let Some(quote) = prices::cached_quote(&state.cache, state.prices.as_ref(), &coin).await {
+                if !connection.send(id.clone(), ServerMessage::Price(quote)).await {
+                    return false;
+                }
+            }
+
+            // In the background, so the socket keeps answering, and shared
+            // with any search already fetching the coin
+            if fetch_now {
+                let state = state.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = news::cached_fetch(&state, &coin).await {
+                        tracing::warn!("Fetching news for new subscription {} failed: {}", coin, e);
+                    }
+                });
+            }
+            true
+        }
+        ClientMessage::Unsubscribe { coin } => {
+            let coin = canonical_coin(&coin);
+            if connection.subscribed.remove(&coin) {
+                state.subscriptions.remove(&coin);
+            }
+            connection.send(id, ServerMessage::Unsubscribed { coin }).await
+        }
+        ClientMessage::Search { query } => {
+            let term = query.trim().to_lowercase();
+            if term.is_empty() {
+                return connection.send(id, ServerMessage::error("invalid_query", "Query is required")).await;
+            }
+            // Same cache entry as `/search`, so both show the same results
+            match news::cached_fetch(state, &term).await {
+                Ok(cached) => {
+                    let message = ServerMessage::News { coin: canonical_coin(&term), items: cached.fetch.items };
+                    connection.send(id, message).await
+                }
+                Err(e) => {
+                    // The error can name upstream URLs, so it stays in the log
+                    tracing::error!("Error fetching news for {}: {}", term, e);
+                    connection.send(id, ServerMessage::error("fetch_failed", "Could not fetch news, please try again later")).await
+                }
+            }
+        }
+        ClientMessage::Ping => connection.send(id, ServerMessage::Pong).await,
+        ClientMessage::Pong => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::NewsUpdate;
+    use crate::db::tests::news_item;
+    use chrono::Utc;
+    use std::sync::Arc;
+    use axum::{routing::get, Router};
+    use futures_util::SinkExt;
+    use serde_json::json;
+    use tokio_tungstenite::{connect_async, tungstenite, MaybeTlsStream, WebSocketStream};
+
+    type Client = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
+
+    async fn connect(state: AppState) -> Client {
+        let app = Router::new().route("/ws", get(handle_ws)).with_state(state);
+        let base = crate::spawn_app(app).await;
+        connect_async(format!("{}/ws", base.replacen("http", "ws", 1))).await.unwrap().0
+    }
+
+    async fn send(client: &mut Client, message: Value) {
+        client.send(tungstenite::Message::Text(message.to_string())).await.unwrap();
+    }
+
+    async fn receive(client: &mut Client) -> Envelope<ServerMessage> {
+        loop {
+            let frame = tokio::time::timeout(Duration::from_secs(5), client.next())
+                .await
+                .expect("no message from the server")
+                .unwrap()
+                .unwrap();
+            if let tungstenite::Message::Text(text) = frame {
+                return serde_json::from_str(&text).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn client_messages_are_versioned_and_tagged() {
+        let (id, message) = parse_client_message(r#"{"v": 1, "id": "7", "type": "subscribe", "coin": "btc"}"#);
+        assert_eq!(id.as_deref(), Some("7"));
+        assert_eq!(message.unwrap(), ClientMessage::Subscribe { coin: "btc".into() });
+
+        let (id, message) = parse_client_message(r#"{"v": 2, "id": 8, "type": "ping"}"#);
+        assert_eq!(id.as_deref(), Some("8"));
+        assert!(matches!(message, Err(ServerMessage::Error { code, .. }) if code == "unsupported_version"));
+
+        let (_, message) = parse_client_message(r#"{"v": 1, "type": "dance"}"#);
+        assert!(matches!(message, Err(ServerMessage::Error { code, .. }) if code == "invalid_message"));
+    }
+
+    #[tokio::test]
+    async fn subscribers_get_a_snapshot_and_pushed_news() {
+        let state = AppState::for_tests().await;
+        state.db.store_news_items("btc", &[news_item("https://stored", 1, "Neutral", "NewsData.io")]).await.unwrap();
+        let mut client = connect(state.clone()).await;
+
+        send(&mut client, json!({"v": 1, "id": "1", "type": "subscribe", "coin": "BTC"})).await;
+        let reply = receive(&mut client).await;
+        assert_eq!(reply.id.as_deref(), Some("1"));
+        assert_eq!(reply.message, ServerMessage::Subscribed { coin: "bitcoin".into() });
+        match receive(&mut client).await.message {
+            ServerMessage::News { coin, items } => {
+                assert_eq!(coin, "bitcoin");
+                assert_eq!(items[0].url, "https://stored");
+            }
+            other => panic!("expected a news snapshot, got {:?}", other),
+        }
+        assert_eq!(state.subscriptions.coins(), vec!["bitcoin"]);
+
+        let pushed = news_item("https://pushed", 2, "Positive", "NewsData.io");
+        state.tx.send(NewsUpdate { coin: "ethereum".into(), news: vec![pushed.clone()], found_at: Utc::now() }).unwrap();
+        state.tx.send(NewsUpdate { coin: "bitcoin".into(), news: vec![pushed.clone()], found_at: Utc::now() }).unwrap();
+        let push = receive(&mut client).await;
+        assert_eq!(push.id, None);
+        assert_eq!(push.message, ServerMessage::News { coin: "bitcoin".into(), items: vec![pushed.clone()] });
+
+        send(&mut client, json!({"v": 1, "id": "2", "type": "unsubscribe", "coin": "bitcoin"})).await;
+        assert_eq!(receive(&mut client).await.message, ServerMessage::Unsubscribed { coin: "bitcoin".into() });
+        assert!(state.subscriptions.coins().is_empty());
+
+        // Nothing more arrives for the coin, so the next reply is the pong
+        state.tx.send(NewsUpdate { coin: "bitcoin".into(), news: vec![pushed], found_at: Utc::now() }).unwrap();
+        send(&mut client, json!({"v": 1, "id": "3", "type": "ping"})).await;
+        let pong = receive(&mut client).await;
+        assert_eq!((pong.id.as_deref(), pong.message), (Some("3"), ServerMessage::Pong));
+    }
+
+    /// Answers only once the test releases it.
+    struct GatedProvider {
+        release: Arc<tokio::sync::Notify>,
+    }
+
+    #[async_trait::async_trait]
+    impl crate::api::NewsProvider for GatedProvider {
+        fn name(&self) -> &str {
+            "Gated"
+        }
+
+        async fn fetch(&self, _query: &str) -> Result<Vec<NewsItem>, String> {
+            self.release.notified().await;
+            Ok(vec![news_item("https://fetched", 1, "Neutral", "Gated")])
+        }
+    }
+
+    #[tokio::test]
+    async fn new_coins_are_fetched_without_blocking_the_socket() {
+        let release = Arc::new(tokio::sync::Notify::new());
+        let mut state = AppState::for_tests().await;
+        state.providers = Arc::new(vec![Box::new(GatedProvider { release: release.clone() })]);
+        let mut client = connect(state).await;
+
+        send(&mut client, json!({"v": 1, "id": "1", "type": "subscribe", "coin": "btc"})).await;
+        assert_eq!(receive(&mut client).await.message, ServerMessage::Subscribed { coin: "bitcoin".into() });
+        assert_eq!(receive(&mut client).await.message, ServerMessage::News { coin: "bitcoin".into(), items: vec![] });
+
+        // The fetch is still waiting, yet the socket answers
+        send(&mut client, json!({"v": 1, "id": "2", "type": "ping"})).await;
+        assert_eq!(receive(&mut client).await.message, ServerMessage::Pong);
+
+        release.notify_one();
+        match receive(&mut client).await.message {
+            ServerMessage::News { coin, items } => {
+                assert_eq!(coin, "bitcoin");
+                assert_eq!(items[0].url, "https://fetched");
+            }
+            other => panic!("expected the fetched news, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn invalid_messages_get_errors_with_their_id() {
+        let mut client = connect(AppState::for_tests().await).await;
+
+        send(&mut client, json!({"v": 0, "id": "old", "type": "ping"})).await;
+        let reply = receive(&mut client).await;
+        assert_eq!(reply.id.as_deref(), Some("old"));
+        assert!(matches!(reply.message, ServerMessage::Error { code, .. } if code == "unsupported_version"));
+
+        client.send(tungstenite::Message::Text("\"btc\"".into())).await.unwrap();
+        assert!(matches!(receive(&mut client).await.message, ServerMessage::Error { .. }));
+
+        // No providers are configured in tests, so a search fails
+        send(&mut client, json!({"v": 1, "id": "s", "type": "search", "query": "btc"})).await;
+        let reply = receive(&mut client).await;
+        assert_eq!(reply.id.as_deref(), Some("s"));
+        assert!(matches!(
+            reply.message,
+            ServerMessage::Error { code, message } if code == "fetch_failed" && message == "Could not fetch news, please try again later"
+        ));
+    }
+}